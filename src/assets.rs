@@ -0,0 +1,111 @@
+use std::{
+    env,
+    path::{Path, PathBuf},
+};
+
+/// Environment variable pointing at an assets directory to use instead
+const ASSETS_DIR: &str = "ASSETS_DIR";
+const FONT: &str = "open-sans/OpenSans-ExtraBold.ttf";
+/// The font built into the binary, for when there is no assets directory
+const EMBEDDED_FONT: &[u8] = include_bytes!("../assets/open-sans/OpenSans-ExtraBold.ttf");
+
+/// Where to load a font from
+#[derive(Clone)]
+pub enum FontSource {
+    File(PathBuf),
+    Embedded(&'static [u8]),
+}
+
+/// Files the game needs at runtime, found on disk where possible
+pub struct Assets {
+    pub font: FontSource,
+}
+
+impl Assets {
+    /// Look for assets on disk, falling back to the copies in the binary
+    pub fn load() -> Assets {
+        let font = match find(FONT) {
+            Ok(path) => FontSource::File(path),
+            Err(e) => {
+                eprintln!("{}\nUsing the built in font", e);
+                FontSource::Embedded(EMBEDDED_FONT)
+            }
+        };
+
+        Assets { font }
+    }
+}
+
+/// Directories to look for assets in, best first. `ASSETS_DIR` replaces
+/// them all, otherwise they are next to the executable, in the repository
+/// when run from `target/debug` or `target/release`, then the working
+/// directory
+fn search_dirs() -> Vec<PathBuf> {
+    if let Some(dir) = env::var_os(ASSETS_DIR) {
+        return vec![PathBuf::from(dir)];
+    }
+
+    let mut dirs = Vec::new();
+    if let Some(exe_dir) = env::current_exe()
+        .ok()
+        .and_then(|exe| exe.parent().map(Path::to_path_buf))
+    {
+        dirs.push(exe_dir.join("assets"));
+        dirs.push(exe_dir.join("../../assets"));
+    }
+    dirs.push(PathBuf::from("assets"));
+
+    dirs
+}
+
+/// The first existing copy of an asset, or an error listing every path tried
+pub fn find(name: &str) -> Result<PathBuf, String> {
+    let tried: Vec<PathBuf> = search_dirs().iter().map(|dir| dir.join(name)).collect();
+
+    if let Some(path) = tried.iter().find(|path| path.is_file()) {
+        return Ok(path.clone());
+    }
+
+    let list: Vec<String> = tried
+        .iter()
+        .map(|path| format!("  {}", path.display()))
+        .collect();
+    let searched = match env::var_os(ASSETS_DIR) {
+        Some(_) => format!("in {}", ASSETS_DIR),
+        None => format!(
+            "next to the executable, in the repository or the working directory \
+             ({} is not set)",
+            ASSETS_DIR
+        ),
+    };
+    Err(format!(
+        "Could not find {} {}, tried:\n{}",
+        name,
+        searched,
+        list.join("\n")
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn missing_assets_list_where_they_were_looked_for() {
+        // Tests don't set ASSETS_DIR, so these are the defaults
+        if env::var_os(ASSETS_DIR).is_some() {
+            return;
+        }
+
+        assert!(find(FONT).is_ok());
+        let e = find("missing.png").unwrap_err();
+        assert!(e.contains("ASSETS_DIR is not set"), "{}", e);
+        assert!(e.contains(
+            &PathBuf::from("assets")
+                .join("missing.png")
+                .display()
+                .to_string()
+        ));
+        assert_eq!(e.lines().count(), search_dirs().len() + 1);
+    }
+}
@@ -0,0 +1,179 @@
+pub mod null;
+pub mod sdl;
+mod synth;
+
+/// Every sound effect in the game, all synthesised at startup
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Sound {
+    Fire,
+    Thrust,
+    BangLarge,
+    BangSmall,
+    ShipDeath,
+    ExtraLife,
+    BeatLow,
+    BeatHigh,
+}
+
+impl Sound {
+    pub const ALL: [Sound; 8] = [
+        Sound::Fire,
+        Sound::Thrust,
+        Sound::BangLarge,
+        Sound::BangSmall,
+        Sound::ShipDeath,
+        Sound::ExtraLife,
+        Sound::BeatLow,
+        Sound::BeatHigh,
+    ];
+}
+
+const VOLUME_STEP: f32 = 0.1;
+
+/// Volume between 0 and 1 and whether everything is muted
+#[derive(Clone, Copy)]
+pub struct AudioSettings {
+    pub volume: f32,
+    pub muted: bool,
+}
+
+impl Default for AudioSettings {
+    fn default() -> Self {
+        Self {
+            volume: 0.5,
+            muted: false,
+        }
+    }
+}
+
+impl AudioSettings {
+    /// The gain actually applied to the output
+    pub fn gain(&self) -> f32 {
+        if self.muted {
+            0.
+        } else {
+            self.volume
+        }
+    }
+
+    pub fn volume_up(&mut self) {
+        self.volume = (self.volume + VOLUME_STEP).min(1.);
+    }
+
+    pub fn volume_down(&mut self) {
+        self.volume = (self.volume - VOLUME_STEP).max(0.);
+    }
+}
+
+/// An audio output the game can send sounds to
+pub trait Audio {
+    /// Play a one shot sound
+    fn play(&mut self, sound: Sound);
+
+    /// Start or stop the looping thrust sound
+    fn set_thrust(&mut self, on: bool);
+
+    fn settings(&self) -> AudioSettings;
+
+    fn set_settings(&mut self, settings: AudioSettings);
+}
+
+const BEAT_SLOWEST: f32 = 1.;
+const BEAT_FASTEST: f32 = 0.25;
+/// Asteroid count at which the heartbeat is at its slowest
+const BEAT_CROWDED: usize = 12;
+
+/// The alternating two note beat that speeds up as the asteroids thin out
+#[derive(Default)]
+pub struct Heartbeat {
+    timer: f32,
+    high: bool,
+}
+
+impl Heartbeat {
+    pub fn update(&mut self, dt: f32, asteroid_count: usize, audio: &mut dyn Audio) {
+        let crowd = asteroid_count.min(BEAT_CROWDED) as f32 / BEAT_CROWDED as f32;
+        let interval = BEAT_FASTEST + (BEAT_SLOWEST - BEAT_FASTEST) * crowd;
+
+        self.timer += dt;
+        if self.timer >= interval {
+            self.timer = 0.;
+            self.high = !self.high;
+            audio.play(if self.high {
+                Sound::BeatHigh
+            } else {
+                Sound::BeatLow
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Remembers what it was asked to play
+    #[derive(Default)]
+    struct Recorder {
+        played: Vec<Sound>,
+    }
+
+    impl Audio for Recorder {
+        fn play(&mut self, sound: Sound) {
+            self.played.push(sound);
+        }
+
+        fn set_thrust(&mut self, _on: bool) {}
+
+        fn settings(&self) -> AudioSettings {
+            AudioSettings::default()
+        }
+
+        fn set_settings(&mut self, _settings: AudioSettings) {}
+    }
+
+    /// Beats in ten seconds of frames with `asteroids` on the field
+    fn beats(asteroids: usize) -> Vec<Sound> {
+        let mut heartbeat = Heartbeat::default();
+        let mut audio = Recorder::default();
+        for _ in 0..600 {
+            heartbeat.update(1. / 60., asteroids, &mut audio);
+        }
+        audio.played
+    }
+
+    #[test]
+    fn beat_speeds_up_as_asteroids_thin_out() {
+        let crowded = beats(BEAT_CROWDED).len();
+        let half = beats(BEAT_CROWDED / 2).len();
+        let last = beats(1).len();
+
+        assert!(
+            (9..=10).contains(&crowded),
+            "{} beats when crowded",
+            crowded
+        );
+        assert!(crowded < half && half < last);
+        // More asteroids than crowded is no slower
+        assert_eq!(beats(BEAT_CROWDED * 2).len(), crowded);
+    }
+
+    #[test]
+    fn beat_alternates_notes() {
+        let played = beats(4);
+        assert_eq!(played[0], Sound::BeatHigh);
+        assert!(played.windows(2).all(|pair| pair[0] != pair[1]));
+    }
+
+    #[test]
+    fn volume_stays_in_range() {
+        let mut settings = AudioSettings::default();
+        for _ in 0..20 {
+            settings.volume_up();
+        }
+        assert_eq!(settings.gain(), 1.);
+
+        settings.muted = true;
+        assert_eq!(settings.gain(), 0.);
+    }
+}
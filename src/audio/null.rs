@@ -0,0 +1,21 @@
+use super::{Audio, AudioSettings, Sound};
+
+/// Audio output that discards everything, used when there is no sound device
+#[derive(Default)]
+pub struct NullAudio {
+    settings: AudioSettings,
+}
+
+impl Audio for NullAudio {
+    fn play(&mut self, _sound: Sound) {}
+
+    fn set_thrust(&mut self, _on: bool) {}
+
+    fn settings(&self) -> AudioSettings {
+        self.settings
+    }
+
+    fn set_settings(&mut self, settings: AudioSettings) {
+        self.settings = settings;
+    }
+}
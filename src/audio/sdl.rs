@@ -0,0 +1,140 @@
+use super::{synth, Audio, AudioSettings, Sound};
+use sdl2::{
+    audio::{AudioCallback, AudioDevice, AudioSpecDesired},
+    Sdl,
+};
+use std::{collections::HashMap, sync::Arc};
+
+const SAMPLE_RATE: i32 = 44_100;
+const MAX_VOICES: usize = 16;
+
+/// A sound currently being played
+struct Voice {
+    sound: Sound,
+    samples: Arc<Vec<f32>>,
+    pos: usize,
+    looping: bool,
+}
+
+/// Mixes the playing voices on the audio thread
+pub struct Mixer {
+    voices: Vec<Voice>,
+    gain: f32,
+}
+
+impl AudioCallback for Mixer {
+    type Channel = f32;
+
+    fn callback(&mut self, out: &mut [f32]) {
+        for sample in out.iter_mut() {
+            *sample = 0.;
+        }
+
+        for voice in self.voices.iter_mut() {
+            for sample in out.iter_mut() {
+                if voice.pos >= voice.samples.len() {
+                    if !voice.looping {
+                        break;
+                    }
+                    voice.pos = 0;
+                }
+                *sample += voice.samples[voice.pos] * self.gain;
+                voice.pos += 1;
+            }
+        }
+
+        self.voices.retain(|v| v.looping || v.pos < v.samples.len());
+
+        for sample in out.iter_mut() {
+            *sample = sample.clamp(-1., 1.);
+        }
+    }
+}
+
+/// Audio output through an SDL audio callback
+pub struct SdlAudio {
+    device: AudioDevice<Mixer>,
+    bank: HashMap<Sound, Arc<Vec<f32>>>,
+    settings: AudioSettings,
+}
+
+impl SdlAudio {
+    pub fn new(sdl_context: &Sdl, settings: AudioSettings) -> Result<SdlAudio, String> {
+        let audio_subsystem = sdl_context.audio()?;
+
+        let desired = AudioSpecDesired {
+            freq: Some(SAMPLE_RATE),
+            channels: Some(1),
+            samples: None,
+        };
+
+        let mut rate = SAMPLE_RATE;
+        let device = audio_subsystem.open_playback(None, &desired, |spec| {
+            rate = spec.freq;
+            Mixer {
+                voices: Vec::new(),
+                gain: settings.gain(),
+            }
+        })?;
+        device.resume();
+
+        let bank = Sound::ALL
+            .iter()
+            .map(|&sound| (sound, Arc::new(synth::generate(sound, rate))))
+            .collect();
+
+        Ok(SdlAudio {
+            device,
+            bank,
+            settings,
+        })
+    }
+
+    fn start(&mut self, sound: Sound, looping: bool) {
+        let samples = self.bank[&sound].clone();
+        let mut mixer = self.device.lock();
+
+        if mixer.voices.len() >= MAX_VOICES {
+            mixer.voices.remove(0);
+        }
+        mixer.voices.push(Voice {
+            sound,
+            samples,
+            pos: 0,
+            looping,
+        });
+    }
+}
+
+impl Audio for SdlAudio {
+    fn play(&mut self, sound: Sound) {
+        self.start(sound, false);
+    }
+
+    fn set_thrust(&mut self, on: bool) {
+        let playing = self
+            .device
+            .lock()
+            .voices
+            .iter()
+            .any(|v| v.sound == Sound::Thrust);
+
+        if on && !playing {
+            self.start(Sound::Thrust, true);
+        } else if !on && playing {
+            self.device
+                .lock()
+                .voices
+                .retain(|v| v.sound != Sound::Thrust);
+        }
+    }
+
+    fn settings(&self) -> AudioSettings {
+        self.settings
+    }
+
+    fn set_settings(&mut self, settings: AudioSettings) {
+        self.settings = settings;
+        self.device.lock().gain = settings.gain();
+    }
+}
@@ -0,0 +1,149 @@
+use super::Sound;
+use std::f32::consts::TAU;
+
+/// Tiny xorshift generator so noise sounds are the same on every run
+struct Noise(u32);
+
+impl Noise {
+    fn next(&mut self) -> f32 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 17;
+        self.0 ^= self.0 << 5;
+        (self.0 as f32 / u32::MAX as f32) * 2. - 1.
+    }
+}
+
+/// Number of samples in `seconds` of audio
+fn len(rate: i32, seconds: f32) -> usize {
+    (rate as f32 * seconds) as usize
+}
+
+fn square(phase: f32) -> f32 {
+    if phase.fract() < 0.5 {
+        1.
+    } else {
+        -1.
+    }
+}
+
+/// Square wave sweeping from `from` to `to` hz with a linear fade out
+fn sweep(rate: i32, seconds: f32, from: f32, to: f32, gain: f32) -> Vec<f32> {
+    let n = len(rate, seconds);
+    let mut phase = 0.;
+
+    (0..n)
+        .map(|i| {
+            let t = i as f32 / n as f32;
+            phase += (from + (to - from) * t) / rate as f32;
+            square(phase) * (1. - t) * gain
+        })
+        .collect()
+}
+
+/// Low passed white noise with an exponential decay, smaller `cutoff` sounds deeper
+fn rumble(rate: i32, seconds: f32, cutoff: f32, decay: f32, gain: f32) -> Vec<f32> {
+    let mut noise = Noise(0x9E37_79B9);
+    let mut last = 0.;
+
+    (0..len(rate, seconds))
+        .map(|i| {
+            let t = i as f32 / rate as f32;
+            last += (noise.next() - last) * cutoff;
+            last * (-t * decay).exp() * gain
+        })
+        .collect()
+}
+
+/// Short low sine thump used for the heartbeat
+fn thump(rate: i32, freq: f32) -> Vec<f32> {
+    let n = len(rate, 0.12);
+
+    (0..n)
+        .map(|i| {
+            let t = i as f32 / rate as f32;
+            (TAU * freq * t).sin() * (1. - i as f32 / n as f32) * 0.9
+        })
+        .collect()
+}
+
+/// Rising beeps for an extra life
+fn chime(rate: i32) -> Vec<f32> {
+    [880., 1175., 1760.]
+        .iter()
+        .flat_map(|&freq| {
+            let n = len(rate, 0.09);
+            (0..n).map(move |i| {
+                let t = i as f32 / rate as f32;
+                square(freq * t) * (1. - i as f32 / n as f32) * 0.25
+            })
+        })
+        .collect()
+}
+
+/// Synthesise the samples for a sound at the given sample rate
+pub fn generate(sound: Sound, rate: i32) -> Vec<f32> {
+    match sound {
+        Sound::Fire => sweep(rate, 0.12, 1400., 300., 0.2),
+        // Looped, so keep it flat rather than decaying
+        Sound::Thrust => rumble(rate, 0.5, 0.05, 0., 0.6),
+        Sound::BangLarge => rumble(rate, 0.9, 0.06, 4., 1.),
+        Sound::BangSmall => rumble(rate, 0.4, 0.2, 9., 0.8),
+        Sound::ShipDeath => {
+            let mut samples = rumble(rate, 1.6, 0.04, 2., 1.);
+            let tone = sweep(rate, 1., 400., 40., 0.2);
+            for (s, t) in samples.iter_mut().zip(tone) {
+                *s += t;
+            }
+            samples
+        }
+        Sound::ExtraLife => chime(rate),
+        Sound::BeatLow => thump(rate, 55.),
+        Sound::BeatHigh => thump(rate, 62.),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RATE: i32 = 44_100;
+
+    fn peak(samples: &[f32]) -> f32 {
+        samples.iter().fold(0., |peak, s| peak.max(s.abs()))
+    }
+
+    #[test]
+    fn sounds_are_the_length_asked_for() {
+        assert_eq!(generate(Sound::Fire, RATE).len(), len(RATE, 0.12));
+        assert_eq!(generate(Sound::ShipDeath, RATE).len(), len(RATE, 1.6));
+        assert_eq!(generate(Sound::ExtraLife, RATE).len(), len(RATE, 0.09) * 3);
+    }
+
+    #[test]
+    fn sounds_stay_in_range_and_are_the_same_every_time() {
+        for sound in Sound::ALL {
+            let samples = generate(sound, RATE);
+            assert!(peak(&samples) > 0.05, "{:?} is silent", sound);
+            assert!(peak(&samples) <= 1.2, "{:?} clips", sound);
+            assert!(samples == generate(sound, RATE));
+        }
+    }
+
+    #[test]
+    fn one_shots_fade_out() {
+        for sound in [Sound::Fire, Sound::BangLarge, Sound::BeatLow] {
+            let samples = generate(sound, RATE);
+            let tail = &samples[samples.len() * 9 / 10..];
+            assert!(peak(tail) < peak(&samples) / 4., "{:?} doesn't fade", sound);
+        }
+    }
+
+    #[test]
+    fn fire_sweeps_down() {
+        let samples = generate(Sound::Fire, RATE);
+        // Count sign changes in the first and last thirds
+        let crossings = |part: &[f32]| part.windows(2).filter(|w| w[0] * w[1] < 0.).count();
+        let third = samples.len() / 3;
+        assert!(crossings(&samples[..third]) > crossings(&samples[third * 2..]));
+    }
+}
@@ -0,0 +1,109 @@
+use asteroids::{
+    cli::parse_size,
+    config::Config,
+    game::rules::{Difficulty, GameMode, Players, Rules},
+    math::playfield::Playfield,
+    sim::{self, Driver, Report},
+    storage::replay::Replay,
+};
+use clap::Parser;
+use std::{path::PathBuf, thread, time::Instant};
+
+/// Play lots of games of Asteroids without a window as fast as possible,
+/// printing how they went as JSON
+#[derive(Parser)]
+#[command(version, about)]
+struct Options {
+    /// How many games to play
+    #[arg(long, value_name = "N", default_value_t = 100)]
+    games: u64,
+
+    /// Seed of the first game, each one after uses the next seed up and
+    /// the largest is followed by 0
+    #[arg(long, default_value_t = 0)]
+    seed: u64,
+
+    /// Who plays, bot or idle
+    #[arg(long, default_value = "bot")]
+    driver: Driver,
+
+    /// Play these replays back instead
+    #[arg(long, value_name = "PATH", num_args = 1.., conflicts_with_all = ["games", "seed", "driver", "ticks"])]
+    replay: Vec<PathBuf>,
+
+    /// Most ticks to play each game for, at 60 ticks a second
+    #[arg(long, default_value_t = 36_000)]
+    ticks: u64,
+
+    #[arg(long)]
+    difficulty: Option<Difficulty>,
+
+    #[arg(long)]
+    mode: Option<GameMode>,
+
+    #[arg(long)]
+    players: Option<Players>,
+
+    /// Size of the play area
+    #[arg(long, value_name = "WIDTHxHEIGHT", value_parser = parse_size)]
+    playfield: Option<(u32, u32)>,
+
+    /// Threads to play on, every core by default
+    #[arg(long)]
+    threads: Option<usize>,
+
+    /// Include every game's result, not just the totals
+    #[arg(long)]
+    each: bool,
+}
+
+fn main() -> Result<(), String> {
+    let options = Options::parse();
+
+    // The defaults rather than anyone's config file, so runs compare
+    let defaults = Config::default().rules();
+    let rules = Rules {
+        difficulty: options.difficulty.unwrap_or(defaults.difficulty),
+        mode: options.mode.unwrap_or(defaults.mode),
+        players: options.players.unwrap_or(defaults.players),
+        field: match options.playfield {
+            Some((width, height)) => Playfield::new(width as f32, height as f32),
+            None => defaults.field,
+        },
+    };
+    let threads = options
+        .threads
+        .unwrap_or_else(|| thread::available_parallelism().map_or(1, |n| n.get()));
+
+    let replays = options
+        .replay
+        .iter()
+        .map(|path| {
+            // Games here are played without mods
+            let replay = Replay::load(path)?;
+            replay.check_mods(&[])?;
+            Ok(replay)
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+
+    let start = Instant::now();
+    let results = if replays.is_empty() {
+        sim::play_all(options.games as usize, threads, |i| {
+            sim::play(
+                options.seed.wrapping_add(i as u64),
+                rules,
+                options.driver,
+                options.ticks,
+            )
+        })
+    } else {
+        sim::play_all(replays.len(), threads, |i| sim::play_replay(&replays[i]))
+    };
+    let seconds = start.elapsed().as_secs_f64();
+
+    let report = Report::new(results, seconds, threads, options.each);
+    let json = serde_json::to_string_pretty(&report).map_err(|e| e.to_string())?;
+    println!("{}", json);
+
+    Ok(())
+}
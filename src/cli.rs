@@ -0,0 +1,279 @@
+use crate::{
+    config::Config,
+    game::rules::{Difficulty, GameMode, Players},
+    net::{link::Conditions, lobby::Lobby},
+};
+use clap::Parser;
+use std::{path::PathBuf, time::Duration};
+
+/// Asteroids written in Rust with SDL2
+#[derive(Parser)]
+#[command(version, about)]
+pub struct Options {
+    /// Window width in pixels
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..))]
+    pub width: Option<u32>,
+
+    /// Window height in pixels
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..))]
+    pub height: Option<u32>,
+
+    /// Start in fullscreen
+    #[arg(long)]
+    pub fullscreen: bool,
+
+    /// Playfield size in logical units, independent of the window size
+    #[arg(long, value_name = "WIDTHxHEIGHT", value_parser = parse_size)]
+    pub playfield: Option<(u32, u32)>,
+
+    /// Sync frames to the display refresh rate
+    #[arg(long, value_name = "on|off", value_parser = parse_switch)]
+    pub vsync: Option<bool>,
+
+    /// Draw games on the CPU, exactly as headless frames are drawn
+    #[arg(long)]
+    pub software_render: bool,
+
+    /// Glow and fading trails like a vector monitor, G toggles it in game
+    #[arg(long)]
+    pub glow: bool,
+
+    /// Seed for every new game, random if not given
+    #[arg(long)]
+    pub seed: Option<u64>,
+
+    /// easy, normal or hard
+    #[arg(long)]
+    pub difficulty: Option<Difficulty>,
+
+    /// classic or endless
+    #[arg(long)]
+    pub mode: Option<GameMode>,
+
+    /// single, coop or versus, for two players on one machine
+    #[arg(long)]
+    pub players: Option<Players>,
+
+    /// Config file to use instead of the one in the config directory
+    #[arg(long, value_name = "PATH")]
+    pub config: Option<PathBuf>,
+
+    /// Record new games to a replay file, later games in a session get
+    /// -2, -3 and so on added to the name
+    #[arg(long, value_name = "PATH")]
+    pub record: Option<PathBuf>,
+
+    /// Play back a replay file instead of taking input
+    #[arg(long, value_name = "PATH", conflicts_with = "record")]
+    pub replay: Option<PathBuf>,
+
+    /// Run the simulation without a window or sound
+    #[arg(long)]
+    pub headless: bool,
+
+    /// Most ticks to simulate when headless, at 60 ticks a second
+    #[arg(long, default_value_t = 36_000, requires = "headless")]
+    pub ticks: u64,
+
+    /// Let the autopilot fly every ship when headless, for soak testing
+    #[arg(long, requires = "headless", conflicts_with = "replay")]
+    pub bot: bool,
+
+    /// Save headless frames as numbered PNGs in this directory
+    #[arg(long, value_name = "DIR", requires = "headless")]
+    pub frames: Option<PathBuf>,
+
+    /// Save headless frames as an animated GIF
+    #[arg(long, value_name = "PATH", requires = "headless")]
+    pub gif: Option<PathBuf>,
+
+    /// Only save every Nth tick as a frame
+    #[arg(long, value_name = "N", default_value_t = 1, value_parser = clap::value_parser!(u64).range(1..))]
+    pub every: u64,
+
+    /// Host a game over the network on this UDP port
+    #[arg(long, value_name = "PORT", conflicts_with_all = ["join", "record", "replay"])]
+    pub host: Option<u16>,
+
+    /// Join the game hosted at this address, like 192.168.1.20:7777
+    #[arg(long, value_name = "ADDR", conflicts_with_all = ["record", "replay"])]
+    pub join: Option<String>,
+
+    /// Players to wait for before a headless host starts the game
+    #[arg(long, value_name = "N", default_value_t = 2, value_parser = clap::value_parser!(u8).range(2..=4))]
+    pub net_players: u8,
+
+    /// Hold back everything sent over the network by this many ms
+    #[arg(long, value_name = "MS", default_value_t = 0)]
+    pub latency: u64,
+
+    /// Hold back everything sent by up to this many ms more at random
+    #[arg(long, value_name = "MS", default_value_t = 0)]
+    pub jitter: u64,
+
+    /// Stream every tick of games to spectators on this local TCP port
+    #[arg(long, value_name = "PORT")]
+    pub stream: Option<u16>,
+
+    /// Watch a game streaming at this address, like 127.0.0.1:7780
+    #[arg(long, value_name = "ADDR", conflicts_with_all = ["host", "join", "replay", "headless"])]
+    pub spectate: Option<String>,
+
+    /// Drop this percentage of everything sent over the network
+    #[arg(long, value_name = "PERCENT", default_value_t = 0.)]
+    pub loss: f32,
+
+    /// Load Rhai scripts from this folder into games played here, not
+    /// online
+    #[arg(long, value_name = "DIR", default_value = "mods")]
+    pub mods: PathBuf,
+}
+
+impl Options {
+    /// Load the config file and apply any options given on the command line over it
+    pub fn config(&self) -> Result<Config, String> {
+        let mut config = Config::load(self.config.as_deref())?;
+
+        if let Some(width) = self.width {
+            config.width = width;
+        }
+        if let Some(height) = self.height {
+            config.height = height;
+        }
+        if let Some((width, height)) = self.playfield {
+            config.field_width = width;
+            config.field_height = height;
+        }
+        if self.fullscreen {
+            config.fullscreen = true;
+        }
+        if self.software_render {
+            config.software_render = true;
+        }
+        if self.glow {
+            config.glow = true;
+        }
+        if let Some(vsync) = self.vsync {
+            config.vsync = vsync;
+        }
+        if let Some(difficulty) = self.difficulty {
+            config.difficulty = difficulty;
+        }
+        if let Some(mode) = self.mode {
+            config.mode = mode;
+        }
+        if let Some(players) = self.players {
+            config.players = players;
+        }
+
+        Ok(config)
+    }
+
+    /// A lobby for hosting or joining a game over the network, if asked for
+    pub fn lobby(&self) -> Result<Option<Lobby>, String> {
+        let conditions = Conditions {
+            latency: Duration::from_millis(self.latency),
+            jitter: Duration::from_millis(self.jitter),
+            loss: (self.loss / 100.).clamp(0., 1.),
+        };
+
+        match (self.host, &self.join) {
+            (Some(port), _) => Lobby::host(port, conditions).map(Some),
+            (None, Some(addr)) => Lobby::join(addr, conditions).map(Some),
+            (None, None) => Ok(None),
+        }
+    }
+}
+
+fn parse_switch(s: &str) -> Result<bool, String> {
+    match s {
+        "on" | "true" => Ok(true),
+        "off" | "false" => Ok(false),
+        _ => Err(format!("expected on or off, got '{}'", s)),
+    }
+}
+
+pub fn parse_size(s: &str) -> Result<(u32, u32), String> {
+    let error = || format!("expected a size like 1000x800, got '{}'", s);
+
+    let (width, height) = s.split_once('x').ok_or_else(error)?;
+    match (width.parse(), height.parse()) {
+        (Ok(width), Ok(height)) if width > 0 && height > 0 => Ok((width, height)),
+        _ => Err(error()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::test_dir;
+    use std::fs;
+
+    fn options(args: &[&str]) -> Result<Options, clap::Error> {
+        Options::try_parse_from(["asteroids"].iter().chain(args))
+    }
+
+    #[test]
+    fn sizes_need_both_sides() {
+        assert_eq!(parse_size("1000x800"), Ok((1000, 800)));
+        for size in [
+            "0x800", "800x0", "800", "800x", "x800", "big", "-5x5", "1x2x3",
+        ] {
+            assert!(parse_size(size).is_err(), "{}", size);
+        }
+
+        assert!(options(&["--playfield", "0x800"]).is_err());
+        assert!(options(&["--width", "0"]).is_err());
+        assert!(options(&["--height", "0"]).is_err());
+    }
+
+    #[test]
+    fn switches_are_on_or_off() {
+        for (s, on) in [
+            ("on", true),
+            ("true", true),
+            ("off", false),
+            ("false", false),
+        ] {
+            assert_eq!(parse_switch(s), Ok(on));
+        }
+        assert!(parse_switch("yes").is_err());
+
+        assert_eq!(options(&["--vsync", "off"]).unwrap().vsync, Some(false));
+        assert_eq!(options(&[]).unwrap().vsync, None);
+        assert!(options(&["--vsync", "maybe"]).is_err());
+    }
+
+    #[test]
+    fn options_win_over_the_config_file() {
+        let path = test_dir("cli").join("config.toml");
+        let text = "width = 640\nfield_width = 1000\ndifficulty = \"hard\"\nvsync = true\n";
+        fs::write(&path, text).unwrap();
+        let path = path.to_str().unwrap();
+
+        let config = options(&["--config", path]).unwrap().config().unwrap();
+        assert_eq!((config.width, config.height), (640, 800));
+        assert_eq!((config.field_width, config.field_height), (1000, 800));
+        assert_eq!(config.difficulty, Difficulty::Hard);
+        assert!(config.vsync);
+
+        let args = [
+            "--config",
+            path,
+            "--width",
+            "900",
+            "--playfield",
+            "300x200",
+            "--vsync",
+            "off",
+            "--mode",
+            "endless",
+        ];
+        let config = options(&args).unwrap().config().unwrap();
+        assert_eq!((config.width, config.height), (900, 800));
+        assert_eq!((config.field_width, config.field_height), (300, 200));
+        assert_eq!(config.difficulty, Difficulty::Hard);
+        assert_eq!(config.mode, GameMode::Endless);
+        assert!(!config.vsync);
+    }
+}
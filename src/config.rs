@@ -0,0 +1,205 @@
+use crate::{
+    controls::{Bindings, Scheme},
+    entity::star::StarSettings,
+    game::rules::{Difficulty, GameMode, Players, Rules},
+    math::playfield::Playfield,
+    render::effects::EffectSettings,
+    storage::write_atomic,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+const FILE_NAME: &str = "config.toml";
+
+/// Settings read from the config file, anything missing takes its default
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    /// Window size in pixels
+    pub width: u32,
+    pub height: u32,
+    pub fullscreen: bool,
+    pub vsync: bool,
+    /// Playfield size in logical units, scaled to fit the window
+    pub field_width: u32,
+    pub field_height: u32,
+    /// Draw games with the software renderer instead of SDL_gfx
+    pub software_render: bool,
+    /// Start games with the vector monitor glow on
+    pub glow: bool,
+    /// Depth layers in the starfield
+    pub star_layers: u32,
+    /// Stars in each 100 by 100 patch of the playfield
+    pub star_density: f32,
+    /// Shake the playfield when things blow up
+    pub screen_shake: bool,
+    /// Freeze for a moment when the ship is destroyed
+    pub hit_stop: bool,
+    /// Flash the screen on an extra life or hyperspace
+    pub flash: bool,
+    pub difficulty: Difficulty,
+    pub mode: GameMode,
+    pub players: Players,
+    pub volume: f32,
+    pub muted: bool,
+    /// Rumble controllers when the ship is destroyed
+    pub rumble: bool,
+    pub control_scheme: Scheme,
+    pub controls: Bindings,
+    /// Player two's controls in two player games
+    pub controls_two: Bindings,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            width: 800,
+            height: 800,
+            fullscreen: false,
+            vsync: true,
+            field_width: 800,
+            field_height: 800,
+            software_render: false,
+            glow: false,
+            star_layers: 3,
+            star_density: 3.,
+            screen_shake: true,
+            hit_stop: true,
+            flash: true,
+            difficulty: Difficulty::Normal,
+            mode: GameMode::Classic,
+            players: Players::Single,
+            volume: 0.5,
+            muted: false,
+            rumble: true,
+            control_scheme: Scheme::Classic,
+            controls: Bindings::for_player(0),
+            controls_two: Bindings::for_player(1),
+        }
+    }
+}
+
+impl Config {
+    /// Load from `path`, or from the users config directory if no path is
+    /// given. Only an explicitly given file has to exist
+    pub fn load(path: Option<&Path>) -> Result<Config, String> {
+        let path = match path {
+            Some(path) => path.to_path_buf(),
+            None => match default_path() {
+                Some(path) if path.exists() => path,
+                _ => return Ok(Config::default()),
+            },
+        };
+
+        let text = fs::read_to_string(&path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        let mut config: Config =
+            toml::from_str(&text).map_err(|e| format!("{}: {}", path.display(), e))?;
+
+        for (key, size) in [("width", config.width), ("height", config.height)] {
+            if size == 0 {
+                return Err(format!("{}: {} has to be more than 0", path.display(), key));
+            }
+        }
+        let field = config.rules().field;
+        field
+            .check()
+            .map_err(|e| format!("{}: field_{}", path.display(), e))?;
+
+        // Actions left out of the file keep their usual controls
+        config.controls = config.controls.or(Bindings::for_player(0));
+        config.controls_two = config.controls_two.or(Bindings::for_player(1));
+
+        Ok(config)
+    }
+
+    /// Write the control scheme and both players' bindings into the config
+    /// file at `path`, or the one in the config directory, keeping
+    /// everything else that is in it
+    pub fn save_controls(
+        path: Option<&Path>,
+        scheme: Scheme,
+        controls: &[Bindings; 2],
+    ) -> Result<(), String> {
+        let path = match path {
+            Some(path) => path.to_path_buf(),
+            None => default_path().ok_or("Could not find a config directory")?,
+        };
+
+        let mut table = if path.exists() {
+            let text =
+                fs::read_to_string(&path).map_err(|e| format!("{}: {}", path.display(), e))?;
+            toml::from_str(&text).map_err(|e| format!("{}: {}", path.display(), e))?
+        } else {
+            toml::value::Table::new()
+        };
+        let scheme = toml::Value::try_from(scheme).map_err(|e| e.to_string())?;
+        table.insert("control_scheme".to_string(), scheme);
+        for (key, controls) in ["controls", "controls_two"].iter().zip(controls) {
+            let controls = toml::Value::try_from(controls).map_err(|e| e.to_string())?;
+            table.insert(key.to_string(), controls);
+        }
+
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+        }
+        let text = toml::to_string(&toml::Value::Table(table)).map_err(|e| e.to_string())?;
+        write_atomic(&path, text.as_bytes())
+    }
+
+    pub fn rules(&self) -> Rules {
+        Rules {
+            difficulty: self.difficulty,
+            mode: self.mode,
+            players: self.players,
+            field: Playfield::new(self.field_width as f32, self.field_height as f32),
+        }
+    }
+
+    pub fn effects(&self) -> EffectSettings {
+        EffectSettings {
+            shake: self.screen_shake,
+            hit_stop: self.hit_stop,
+            flash: self.flash,
+        }
+    }
+
+    pub fn stars(&self) -> StarSettings {
+        StarSettings {
+            layers: self.star_layers,
+            density: self.star_density,
+        }
+    }
+}
+
+fn default_path() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join("asteroids").join(FILE_NAME))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::test_dir;
+
+    fn load(text: &str) -> Result<Config, String> {
+        let path = test_dir("config").join(FILE_NAME);
+        fs::write(&path, text).unwrap();
+        Config::load(Some(&path))
+    }
+
+    #[test]
+    fn sizes_of_nothing_are_rejected() {
+        assert!(load("field_width = 1000\nwidth = 640").is_ok());
+        for (text, key) in [
+            ("field_width = 0", "field_width"),
+            ("field_height = 0", "field_height"),
+            ("width = 0", "width"),
+            ("height = 0", "height"),
+        ] {
+            let error = load(text).err().unwrap();
+            assert!(error.contains(&format!(": {} has", key)), "{}", error);
+        }
+    }
+}
@@ -0,0 +1,146 @@
+use super::{pad::Sticks, Action};
+use crate::{
+    entity::{ship, world::Entity},
+    game::input::Input,
+    math::vec2::Vec2,
+};
+use sdl2::event::Event;
+
+/// What is held for the twin-stick scheme, where the ship turns to face the
+/// mouse or right stick and WASD or the left stick push it around
+#[derive(Default)]
+pub struct TwinStick {
+    up: bool,
+    down: bool,
+    left: bool,
+    right: bool,
+    /// Where the mouse is in playfield units, until the right stick is used
+    mouse: Option<Vec2>,
+}
+
+impl TwinStick {
+    /// Follow the mouse, SDL has already scaled it to the playfield
+    pub fn event(&mut self, event: &Event) {
+        if let Event::MouseMotion { x, y, .. } = *event {
+            self.mouse = Some(Vec2::new(x as f32, y as f32));
+        }
+    }
+
+    pub fn hold(&mut self, action: Action, held: bool) {
+        match action {
+            Action::MoveUp => self.up = held,
+            Action::MoveDown => self.down = held,
+            Action::MoveLeft => self.left = held,
+            Action::MoveRight => self.right = held,
+            _ => (),
+        }
+    }
+
+    /// The held controls as twin-stick input for `ship`. With nothing to
+    /// aim at the ship keeps facing the same way, and thrust on its own
+    /// goes the way it faces
+    pub fn apply(&mut self, mut input: Input, sticks: &Sticks, ship: &Entity) -> Input {
+        input.twin_stick = true;
+
+        let center = ship.center();
+        let aim = match sticks.right() {
+            Some(aim) => {
+                self.mouse = None;
+                aim
+            }
+            None => match &self.mouse {
+                Some(mouse) => (mouse.y - center.y).atan2(mouse.x - center.x),
+                None => ship::facing(ship),
+            },
+        };
+        input.set_aim(aim);
+
+        let x = self.right as i8 - self.left as i8;
+        let y = self.down as i8 - self.up as i8;
+        let heading = if x != 0 || y != 0 {
+            Some((y as f32).atan2(x as f32))
+        } else {
+            sticks.left()
+        };
+        match heading {
+            Some(heading) => {
+                input.set_heading(heading);
+                input.thrust = true;
+            }
+            None => input.set_heading(ship::facing(ship)),
+        }
+
+        input
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        entity::{system, world::World},
+        game::TICK,
+        math::playfield::Playfield,
+    };
+    use std::f32::consts::{FRAC_PI_2, FRAC_PI_4};
+
+    fn mouse_at(x: i32, y: i32) -> Event {
+        Event::MouseMotion {
+            timestamp: 0,
+            window_id: 0,
+            which: 0,
+            mousestate: sdl2::mouse::MouseState::from_sdl_state(0),
+            x,
+            y,
+            xrel: 0,
+            yrel: 0,
+        }
+    }
+
+    #[test]
+    fn keys_set_heading() {
+        let ship = ship::at(&Playfield::new(400., 400.).center(), 0);
+        let mut twin = TwinStick::default();
+
+        let input = twin.apply(Input::default(), &Sticks::default(), &ship);
+        assert!(!input.thrust);
+
+        twin.hold(Action::MoveDown, true);
+        twin.hold(Action::MoveRight, true);
+        let input = twin.apply(Input::default(), &Sticks::default(), &ship);
+        assert!(input.thrust);
+        assert!((input.heading_angle() - FRAC_PI_4).abs() < 0.01);
+    }
+
+    #[test]
+    fn ship_turns_to_the_mouse_at_a_capped_rate() {
+        let field = Playfield::new(400., 400.);
+        let mut world = World::default();
+        let id = world.spawn(ship::at(&field.center(), 0));
+        let mut twin = TwinStick::default();
+
+        // Steer with whatever the ship is aimed at and move it on a tick
+        let step = |world: &mut World, twin: &mut TwinStick| {
+            let ship = world.get_mut(id).unwrap();
+            let input = twin.apply(Input::default(), &Sticks::default(), ship);
+            ship::apply(ship, input, TICK);
+            system::movement(world, TICK);
+            input
+        };
+
+        // The ship starts facing up, put the mouse straight below it
+        let center = world.get(id).unwrap().center();
+        twin.event(&mouse_at(center.x as i32, 390));
+
+        let before = ship::facing(world.get(id).unwrap());
+        let input = step(&mut world, &mut twin);
+        assert!((input.aim_angle() - FRAC_PI_2).abs() < 0.01);
+        let turned = (ship::facing(world.get(id).unwrap()) - before).abs();
+        assert!(turned > 0. && turned < 0.2, "turned {} in a tick", turned);
+
+        for _ in 0..60 {
+            step(&mut world, &mut twin);
+        }
+        assert!((ship::facing(world.get(id).unwrap()) - FRAC_PI_2).abs() < 0.01);
+    }
+}
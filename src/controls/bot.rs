@@ -0,0 +1,281 @@
+use crate::{
+    entity::{
+        asteroid,
+        ship::{self, laser::LASER_SPEED, ROTATION_AMOUNT, SHIP_SCALE},
+        world::Entity,
+    },
+    game::{input::Input, Game, TICK},
+    math::vec2::Vec2,
+};
+use std::f32::consts::PI;
+
+/// Furthest a corner of the ship is from its centre, the nose
+const SHIP_RADIUS: f32 = 5. * SHIP_SCALE;
+/// Room to spare around the ship when deciding if an asteroid will hit
+const MARGIN: f32 = 15.;
+/// How far ahead to look for asteroids about to hit, in seconds
+const LOOKAHEAD: f32 = 0.8;
+/// Too late to fly out of the way, jump instead
+const PANIC: f32 = 0.12;
+/// Ways to try flying off in when something is coming
+const ESCAPE_HEADINGS: usize = 16;
+/// How fast the ship is guessed to get going when it flies off
+const ESCAPE_SPEED: f32 = 150.;
+/// Lasers fade after a little under 1.8 seconds
+const SHOT_TIME: f32 = 1.6;
+/// Lasers in flight before waiting for some to land
+const MAX_SHOTS: usize = 4;
+/// How close to dead on a shot has to be, in radians
+const AIM_TOLERANCE: f32 = 0.06;
+
+/// Flies a ship with the same controls as a player, shooting asteroids
+/// and keeping out of their way
+#[derive(Default)]
+pub struct Autopilot {
+    /// Controls held last tick, as firing and jumping need letting go
+    last: Input,
+}
+
+/// How an asteroid passes the ship, as seen from the ship
+struct Approach {
+    /// When it will be closest within the lookahead, in seconds from now
+    time: f32,
+    /// How near it gets then before they touch, below 0 if they do
+    clearance: f32,
+}
+
+impl Autopilot {
+    /// What to hold this tick to fly `player`'s ship in `game`
+    pub fn input(&mut self, game: &Game, player: usize) -> Input {
+        let mut input = Input::default();
+        let ship = match game.ship(player) {
+            Some(ship) => ship,
+            None => return input,
+        };
+
+        let threat = approaches(game, ship, &ship.velocity())
+            .filter(|approach| approach.clearance < 0.)
+            .min_by(|a, b| a.time.total_cmp(&b.time));
+
+        if let Some(threat) = threat {
+            let (heading, clearance) = escape(game, ship);
+            // Hyperspace might land somewhere worse, so only when there is
+            // nothing else for it
+            if threat.time < PANIC && clearance < 0. {
+                input.hyperspace = !self.last.hyperspace;
+            } else {
+                input.turn = steer(ship::facing(ship), heading);
+                input.thrust = turn_size(ship::facing(ship), heading) < PI / 4.;
+            }
+        } else if let Some(aim) = lead(game, ship) {
+            input.turn = steer(ship::facing(ship), aim);
+            input.fire = turn_size(ship::facing(ship), aim) < AIM_TOLERANCE
+                && game.lasers(player).count() < MAX_SHOTS
+                && !self.last.fire;
+        }
+
+        self.last = input;
+        input
+    }
+}
+
+/// How every asteroid will pass the ship if it flies at `velocity`, taking
+/// the short way across the edges
+fn approaches<'a>(
+    game: &'a Game,
+    ship: &Entity,
+    velocity: &'a Vec2,
+) -> impl Iterator<Item = Approach> + 'a {
+    let field = &game.rules.field;
+    let center = ship.center();
+
+    game.asteroids().map(move |asteroid| {
+        let offset = field.offset(&center, &asteroid.center());
+        let drift = asteroid.velocity();
+        let relative = Vec2::new(drift.x - velocity.x, drift.y - velocity.y);
+
+        let speed = relative.x.powi(2) + relative.y.powi(2);
+        let time = if speed > 0. {
+            (-(offset.x * relative.x + offset.y * relative.y) / speed).clamp(0., LOOKAHEAD)
+        } else {
+            0.
+        };
+        let closest = Vec2::new(offset.x + relative.x * time, offset.y + relative.y * time);
+
+        Approach {
+            time,
+            clearance: closest.magnitude() - asteroid::radius(asteroid) - SHIP_RADIUS - MARGIN,
+        }
+    })
+}
+
+/// The safest way to fly, and how close that still comes to anything.
+/// Thrust sends the ship the way it faces, so try going every way
+fn escape(game: &Game, ship: &Entity) -> (f32, f32) {
+    let speed = ship.velocity().magnitude().max(ESCAPE_SPEED);
+    let facing = ship::facing(ship);
+
+    (0..ESCAPE_HEADINGS)
+        .map(|i| {
+            let heading = facing + 2. * PI * i as f32 / ESCAPE_HEADINGS as f32;
+            let velocity = Vec2::new(speed * heading.cos(), speed * heading.sin());
+            let clearance = approaches(game, ship, &velocity)
+                .map(|approach| approach.clearance)
+                .fold(f32::MAX, f32::min);
+            (heading, clearance)
+        })
+        // Turning takes time, so a little less room the nearer way round
+        // is worth it
+        .max_by(|a, b| {
+            let score = |(heading, clearance): &(f32, f32)| {
+                clearance.min(MARGIN * 4.) - turn_size(facing, *heading) * MARGIN
+            };
+            score(a).total_cmp(&score(b))
+        })
+        .unwrap_or((facing, 0.))
+}
+
+/// Which way to face to hit the asteroid a laser would reach soonest,
+/// allowing for how far it moves while the laser flies
+fn lead(game: &Game, ship: &Entity) -> Option<f32> {
+    let field = &game.rules.field;
+    let center = ship.center();
+
+    game.asteroids()
+        .filter_map(|asteroid| {
+            let offset = field.offset(&center, &asteroid.center());
+            let velocity = asteroid.velocity();
+
+            // Where it will be when the laser gets there, which moves when
+            // the laser takes longer, so go round a few times
+            let mut time = offset.magnitude() / LASER_SPEED;
+            let mut target = offset.clone();
+            for _ in 0..3 {
+                target = Vec2::new(offset.x + velocity.x * time, offset.y + velocity.y * time);
+                time = (target.magnitude() - SHIP_RADIUS).max(0.) / LASER_SPEED;
+            }
+
+            (time < SHOT_TIME).then(|| (time, target.y.atan2(target.x)))
+        })
+        .min_by(|a, b| a.0.total_cmp(&b.0))
+        .map(|(_, aim)| aim)
+}
+
+/// The short way round from `from` to `to`, between -PI and PI
+fn turn_between(from: f32, to: f32) -> f32 {
+    (to - from + PI).rem_euclid(2. * PI) - PI
+}
+
+fn turn_size(from: f32, to: f32) -> f32 {
+    turn_between(from, to).abs()
+}
+
+/// Analog steering to turn from `from` to `to`, easing off near the end so
+/// it doesn't overshoot
+fn steer(from: f32, to: f32) -> i8 {
+    let full = ROTATION_AMOUNT * TICK;
+    (turn_between(from, to) / full * 127.)
+        .round()
+        .clamp(-127., 127.) as i8
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        entity::world::Kind,
+        game::rules::{Difficulty, GameMode, Players, Rules},
+        math::playfield::Playfield,
+    };
+
+    fn rules() -> Rules {
+        Rules {
+            difficulty: Difficulty::Normal,
+            mode: GameMode::Classic,
+            players: Players::Single,
+            field: Playfield::new(800., 600.),
+        }
+    }
+
+    fn square(x: f32, y: f32, size: f32) -> Entity {
+        let corners = [(-1., -1.), (1., -1.), (1., 1.), (-1., 1.)];
+        asteroid::outline(
+            corners
+                .iter()
+                .map(|(dx, dy)| Vec2::new(x + dx * size, y + dy * size))
+                .collect(),
+        )
+    }
+
+    /// Clear the field for just the one asteroid
+    fn only_asteroid(game: &mut Game, asteroid: Entity) {
+        game.world
+            .retain(|entity| !matches!(entity.kind, Kind::Asteroid { .. }));
+        game.world.spawn(asteroid);
+    }
+
+    #[test]
+    fn turns_to_shoot_across_the_edge() {
+        let mut game = Game::new(0, rules());
+        game.world.retain(|entity| entity.kind.player() != Some(0));
+        game.world.spawn(ship::at(&Vec2::new(60., 300.), 0));
+        // Just over the left edge, so the ship faces left to hit it
+        only_asteroid(&mut game, square(740., 300., 10.));
+
+        let mut autopilot = Autopilot::default();
+        let fired_facing = (0..60).find_map(|_| {
+            let input = autopilot.input(&game, 0);
+            let facing = ship::facing(game.ship(0).unwrap());
+            game.tick(&[input]);
+            input.fire.then_some(facing)
+        });
+
+        let facing = fired_facing.expect("never fired");
+        assert!(turn_size(facing, PI) < AIM_TOLERANCE);
+    }
+
+    #[test]
+    fn jumps_when_it_is_too_late() {
+        let mut game = Game::new(0, rules());
+        let center = game.ship(0).unwrap().center();
+        only_asteroid(&mut game, square(center.x + 60., center.y, 20.));
+
+        let input = Autopilot::default().input(&game, 0);
+        assert!(input.hyperspace);
+    }
+
+    #[test]
+    fn outlasts_holding_still() {
+        // A soak test of sorts, the engine has to hold up to a whole game
+        // of a busy ship
+        let play = |seed, autopilot: Option<&mut Autopilot>| {
+            let mut game = Game::new(seed, rules());
+            let mut ticks = 0;
+            let mut autopilot = autopilot;
+            while !game.is_over() && ticks < 20_000 {
+                let input = match autopilot.as_mut() {
+                    Some(autopilot) => autopilot.input(&game, 0),
+                    None => Input::default(),
+                };
+                game.tick(&[input]);
+                ticks += 1;
+            }
+            (ticks, game.score())
+        };
+
+        let (mut flown, mut held, mut scored) = (0, 0, 0);
+        for seed in 0..4 {
+            let (ticks, score) = play(seed, Some(&mut Autopilot::default()));
+            flown += ticks;
+            scored += score;
+            held += play(seed, None).0;
+        }
+        assert!(
+            flown > held * 2,
+            "flew {} ticks, holding still lasted {}",
+            flown,
+            held
+        );
+        assert!(scored > 200);
+    }
+}
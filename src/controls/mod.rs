@@ -0,0 +1,475 @@
+pub mod aim;
+pub mod bot;
+pub mod pad;
+
+use crate::game::input::Input;
+use sdl2::{controller::Button, event::Event, keyboard::Keycode, mouse::MouseButton};
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, convert::TryFrom};
+
+/// Something the player can do from the keyboard or a controller
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub enum Action {
+    RotateLeft,
+    RotateRight,
+    Thrust,
+    Fire,
+    Hyperspace,
+    /// Twin-stick movement
+    MoveUp,
+    MoveDown,
+    MoveLeft,
+    MoveRight,
+    Pause,
+    /// Leave a game, saving it
+    Leave,
+    Fullscreen,
+    Screenshot,
+    Glow,
+    Mute,
+    VolumeDown,
+    VolumeUp,
+}
+
+impl Action {
+    pub const ALL: [Action; 17] = [
+        Action::RotateLeft,
+        Action::RotateRight,
+        Action::Thrust,
+        Action::Fire,
+        Action::Hyperspace,
+        Action::MoveUp,
+        Action::MoveDown,
+        Action::MoveLeft,
+        Action::MoveRight,
+        Action::Pause,
+        Action::Leave,
+        Action::Fullscreen,
+        Action::Screenshot,
+        Action::Glow,
+        Action::Mute,
+        Action::VolumeDown,
+        Action::VolumeUp,
+    ];
+
+    /// Name used in the config file
+    pub fn name(self) -> &'static str {
+        match self {
+            Action::RotateLeft => "rotate_left",
+            Action::RotateRight => "rotate_right",
+            Action::Thrust => "thrust",
+            Action::Fire => "fire",
+            Action::Hyperspace => "hyperspace",
+            Action::MoveUp => "move_up",
+            Action::MoveDown => "move_down",
+            Action::MoveLeft => "move_left",
+            Action::MoveRight => "move_right",
+            Action::Pause => "pause",
+            Action::Leave => "leave",
+            Action::Fullscreen => "fullscreen",
+            Action::Screenshot => "screenshot",
+            Action::Glow => "glow",
+            Action::Mute => "mute",
+            Action::VolumeDown => "volume_down",
+            Action::VolumeUp => "volume_up",
+        }
+    }
+
+    /// Name shown on the controls screen
+    pub fn label(self) -> &'static str {
+        match self {
+            Action::RotateLeft => "Rotate left",
+            Action::RotateRight => "Rotate right",
+            Action::Thrust => "Thrust",
+            Action::Fire => "Fire",
+            Action::Hyperspace => "Hyperspace",
+            Action::MoveUp => "Move up",
+            Action::MoveDown => "Move down",
+            Action::MoveLeft => "Move left",
+            Action::MoveRight => "Move right",
+            Action::Pause => "Pause",
+            Action::Leave => "Save and leave",
+            Action::Fullscreen => "Fullscreen",
+            Action::Screenshot => "Screenshot",
+            Action::Glow => "Glow",
+            Action::Mute => "Mute",
+            Action::VolumeDown => "Volume down",
+            Action::VolumeUp => "Volume up",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Action> {
+        Action::ALL
+            .iter()
+            .copied()
+            .find(|action| action.name() == name)
+    }
+
+    /// Player two's keys, only for flying the ship
+    fn second_key(self) -> Option<Keycode> {
+        match self {
+            Action::RotateLeft => Some(Keycode::J),
+            Action::RotateRight => Some(Keycode::L),
+            Action::Thrust => Some(Keycode::I),
+            Action::Fire => Some(Keycode::O),
+            Action::Hyperspace => Some(Keycode::K),
+            _ => None,
+        }
+    }
+
+    /// Everyone gets the same controller buttons, as each has their own
+    /// controller. The keyboard and mouse are player one's apart from a
+    /// few keys for player two
+    fn defaults(self, player: usize) -> Vec<Control> {
+        let (key, button) = match self {
+            Action::RotateLeft => (Keycode::Left, Some(Button::DPadLeft)),
+            Action::RotateRight => (Keycode::Right, Some(Button::DPadRight)),
+            Action::Thrust => (Keycode::Up, Some(Button::DPadUp)),
+            Action::Fire => (Keycode::Space, Some(Button::A)),
+            Action::Hyperspace => (Keycode::Down, Some(Button::B)),
+            Action::MoveUp => (Keycode::W, None),
+            Action::MoveDown => (Keycode::S, None),
+            Action::MoveLeft => (Keycode::A, None),
+            Action::MoveRight => (Keycode::D, None),
+            Action::Pause => (Keycode::P, Some(Button::Start)),
+            Action::Leave => (Keycode::Escape, Some(Button::Back)),
+            Action::Fullscreen => (Keycode::F11, None),
+            Action::Screenshot => (Keycode::F12, None),
+            Action::Glow => (Keycode::G, Some(Button::Y)),
+            Action::Mute => (Keycode::M, None),
+            Action::VolumeDown => (Keycode::Minus, None),
+            Action::VolumeUp => (Keycode::Equals, None),
+        };
+
+        let key = if player == 0 {
+            Some(key)
+        } else {
+            self.second_key()
+        };
+
+        let mut controls: Vec<_> = key.map(Control::Key).into_iter().collect();
+        controls.extend(button.map(Control::Button));
+        if self == Action::Fire && player == 0 {
+            controls.push(Control::Mouse(MouseButton::Left));
+        }
+        controls
+    }
+
+    /// Set the control this action holds in `input`, if it is one
+    pub fn hold(self, input: &mut Input, held: bool) {
+        match self {
+            Action::RotateLeft => input.left = held,
+            Action::RotateRight => input.right = held,
+            Action::Thrust => input.thrust = held,
+            Action::Fire => input.fire = held,
+            Action::Hyperspace => input.hyperspace = held,
+            _ => (),
+        }
+    }
+}
+
+/// The ways of steering the ship
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Scheme {
+    /// Rotate and thrust the way the ship faces
+    Classic,
+    /// Face the mouse or right stick and move with WASD or the left stick
+    TwinStick,
+}
+
+impl Scheme {
+    pub fn label(self) -> &'static str {
+        match self {
+            Scheme::Classic => "Classic",
+            Scheme::TwinStick => "Twin-stick",
+        }
+    }
+
+    pub fn next(self) -> Scheme {
+        match self {
+            Scheme::Classic => Scheme::TwinStick,
+            Scheme::TwinStick => Scheme::Classic,
+        }
+    }
+}
+
+/// A key, controller button or mouse button that can be bound to an action
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Control {
+    Key(Keycode),
+    Button(Button),
+    Mouse(MouseButton),
+}
+
+/// Names for mouse buttons, SDL doesn't have any
+const MOUSE_BUTTONS: [(MouseButton, &str); 5] = [
+    (MouseButton::Left, "left"),
+    (MouseButton::Middle, "middle"),
+    (MouseButton::Right, "right"),
+    (MouseButton::X1, "x1"),
+    (MouseButton::X2, "x2"),
+];
+
+impl Control {
+    /// Name used in the config file, SDL's name for keys, `pad:` followed
+    /// by SDL's name for controller buttons and `mouse:` for mouse buttons
+    fn name(self) -> String {
+        match self {
+            Control::Key(key) => key.name(),
+            Control::Button(button) => format!("pad:{}", button.string()),
+            Control::Mouse(button) => format!("mouse:{}", mouse_name(button)),
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Control> {
+        if let Some(button) = name.strip_prefix("pad:") {
+            return Button::from_string(button).map(Control::Button);
+        }
+        if let Some(button) = name.strip_prefix("mouse:") {
+            return MOUSE_BUTTONS
+                .iter()
+                .find(|(_, name)| *name == button)
+                .map(|&(button, _)| Control::Mouse(button));
+        }
+        Keycode::from_name(name).map(Control::Key)
+    }
+
+    /// Name shown on the controls screen
+    fn label(self) -> String {
+        match self {
+            Control::Key(key) => key.name(),
+            Control::Button(button) => format!("Pad {}", button.string()),
+            Control::Mouse(button) => format!("Mouse {}", mouse_name(button)),
+        }
+    }
+}
+
+fn mouse_name(button: MouseButton) -> &'static str {
+    MOUSE_BUTTONS
+        .iter()
+        .find(|&&(b, _)| b == button)
+        .map_or("unknown", |&(_, name)| name)
+}
+
+/// The keys and buttons for each action, any of them will do. In the
+/// config file it is a table of action names to lists of control names
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(
+    try_from = "BTreeMap<String, Vec<String>>",
+    into = "BTreeMap<String, Vec<String>>"
+)]
+pub struct Bindings {
+    controls: BTreeMap<Action, Vec<Control>>,
+}
+
+impl Bindings {
+    /// The usual controls for the first or second player
+    pub fn for_player(player: usize) -> Self {
+        Self {
+            controls: Action::ALL
+                .iter()
+                .map(|&action| (action, action.defaults(player)))
+                .collect(),
+        }
+    }
+
+    /// Fill in any actions that weren't given, like ones left out of the
+    /// config file, from `defaults`
+    pub fn or(mut self, defaults: Bindings) -> Self {
+        for (action, controls) in defaults.controls {
+            self.controls.entry(action).or_insert(controls);
+        }
+        self
+    }
+
+    /// The action a control is bound to, if any
+    pub fn action(&self, control: Control) -> Option<Action> {
+        self.controls
+            .iter()
+            .find(|(_, controls)| controls.contains(&control))
+            .map(|(&action, _)| action)
+    }
+
+    /// The bound action for a key or button press or release and whether
+    /// it is now held. Key repeats are left out so toggles only happen
+    /// once per press
+    pub fn event(&self, event: &Event) -> Option<(Action, bool)> {
+        let (control, held) = match *event {
+            Event::KeyDown {
+                keycode: Some(key),
+                repeat: false,
+                ..
+            } => (Control::Key(key), true),
+            Event::KeyUp {
+                keycode: Some(key), ..
+            } => (Control::Key(key), false),
+            Event::ControllerButtonDown { button, .. } => (Control::Button(button), true),
+            Event::ControllerButtonUp { button, .. } => (Control::Button(button), false),
+            Event::MouseButtonDown { mouse_btn, .. } => (Control::Mouse(mouse_btn), true),
+            Event::MouseButtonUp { mouse_btn, .. } => (Control::Mouse(mouse_btn), false),
+            _ => return None,
+        };
+
+        self.action(control).map(|action| (action, held))
+    }
+
+    /// Add a control to an action, taking it from any other action it was on
+    pub fn bind(&mut self, action: Action, control: Control) {
+        self.unbind(control);
+        self.controls.entry(action).or_default().push(control);
+    }
+
+    pub fn unbind(&mut self, control: Control) {
+        for controls in self.controls.values_mut() {
+            controls.retain(|&c| c != control);
+        }
+    }
+
+    /// Leave an action with nothing bound to it
+    pub fn clear(&mut self, action: Action) {
+        self.controls.insert(action, Vec::new());
+    }
+
+    /// The controls for an action as the player would read them
+    pub fn describe(&self, action: Action) -> String {
+        match self.controls.get(&action) {
+            Some(controls) if !controls.is_empty() => controls
+                .iter()
+                .map(|control| control.label())
+                .collect::<Vec<_>>()
+                .join(", "),
+            _ => "-".to_string(),
+        }
+    }
+}
+
+impl TryFrom<BTreeMap<String, Vec<String>>> for Bindings {
+    type Error = String;
+
+    fn try_from(names: BTreeMap<String, Vec<String>>) -> Result<Self, Self::Error> {
+        let mut bindings = Bindings {
+            controls: BTreeMap::new(),
+        };
+
+        for (name, controls) in names {
+            let action = Action::from_name(&name)
+                .ok_or_else(|| format!("'{}' is not a control that can be bound", name))?;
+            let controls = controls
+                .iter()
+                .map(|control| {
+                    Control::from_name(control)
+                        .ok_or_else(|| format!("Unknown key or button '{}' for {}", control, name))
+                })
+                .collect::<Result<_, _>>()?;
+            bindings.controls.insert(action, controls);
+        }
+
+        Ok(bindings)
+    }
+}
+
+impl From<Bindings> for BTreeMap<String, Vec<String>> {
+    fn from(bindings: Bindings) -> Self {
+        Action::ALL
+            .iter()
+            .map(|&action| {
+                let controls = match bindings.controls.get(&action) {
+                    Some(controls) => controls.iter().map(|control| control.name()).collect(),
+                    None => Vec::new(),
+                };
+                (action.name().to_string(), controls)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{config::Config, storage};
+    use std::fs;
+
+    fn parse_error(text: &str) -> String {
+        match toml::from_str::<Bindings>(text) {
+            Ok(_) => panic!("{} parsed", text),
+            Err(e) => e.to_string(),
+        }
+    }
+
+    fn names(bindings: &Bindings) -> BTreeMap<String, Vec<String>> {
+        bindings.clone().into()
+    }
+
+    #[test]
+    fn bindings_parse_from_toml() {
+        let bindings: Bindings = toml::from_str(
+            r#"
+            fire = ["Left Ctrl", "pad:x", "mouse:right"]
+            thrust = []
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            bindings.action(Control::Key(Keycode::LCtrl)),
+            Some(Action::Fire)
+        );
+        assert_eq!(
+            bindings.action(Control::Button(Button::X)),
+            Some(Action::Fire)
+        );
+        assert_eq!(
+            bindings.action(Control::Mouse(MouseButton::Right)),
+            Some(Action::Fire)
+        );
+        assert_eq!(bindings.describe(Action::Thrust), "-");
+        // Only what was in the file until the defaults are filled in
+        assert_eq!(bindings.action(Control::Key(Keycode::Space)), None);
+        let bindings = bindings.or(Bindings::for_player(0));
+        assert_eq!(
+            bindings.action(Control::Key(Keycode::Left)),
+            Some(Action::RotateLeft)
+        );
+        assert_eq!(bindings.action(Control::Key(Keycode::Space)), None);
+    }
+
+    #[test]
+    fn saved_controls_load_back_the_same() {
+        let path = storage::test_dir("controls").join("config.toml");
+        fs::write(&path, "volume = 0.25\n").unwrap();
+
+        let mut one = Bindings::for_player(0);
+        one.bind(Action::Hyperspace, Control::Key(Keycode::Return));
+        one.bind(Action::Fire, Control::Mouse(MouseButton::Right));
+        one.clear(Action::Glow);
+        let mut two = Bindings::for_player(1);
+        two.bind(Action::Fire, Control::Button(Button::X));
+        let controls = [one, two];
+
+        Config::save_controls(Some(&path), Scheme::TwinStick, &controls).unwrap();
+        let config = Config::load(Some(&path)).unwrap();
+
+        assert!(config.control_scheme == Scheme::TwinStick);
+        assert_eq!(names(&config.controls), names(&controls[0]));
+        assert_eq!(names(&config.controls_two), names(&controls[1]));
+        // Everything else in the file is kept
+        assert_eq!(config.volume, 0.25);
+    }
+
+    #[test]
+    fn unknown_names_are_rejected() {
+        let action = parse_error(r#"warp = ["Space"]"#);
+        assert!(action.contains("'warp' is not a control"), "{}", action);
+
+        for control in ["Nope", "pad:nope", "mouse:nope"] {
+            let text = format!(r#"fire = ["{}"]"#, control);
+            let e = parse_error(&text);
+            assert!(
+                e.contains(&format!("Unknown key or button '{}' for fire", control)),
+                "{}",
+                e
+            );
+        }
+    }
+}
@@ -0,0 +1,250 @@
+use crate::game::input::Input;
+use sdl2::{
+    controller::{Axis, GameController},
+    event::Event,
+    GameControllerSubsystem, Sdl,
+};
+
+/// Stick travel around the centre that is ignored, out of 32767
+const DEADZONE: i16 = 8000;
+/// How far the right trigger has to be pulled to thrust, out of 32767
+const TRIGGER_PULL: i16 = 6000;
+/// Low and high frequency strength and length in ms of the rumble when the
+/// ship is destroyed
+const DEATH_RUMBLE: (u16, u16, u32) = (0xc000, 0xffff, 400);
+
+/// Where the analog sticks and trigger are, from whichever controller
+/// moved them last
+#[derive(Default)]
+pub struct Sticks {
+    left_x: i16,
+    left_y: i16,
+    right_x: i16,
+    right_y: i16,
+    trigger: i16,
+}
+
+impl Sticks {
+    pub fn event(&mut self, event: &Event) {
+        if let Event::ControllerAxisMotion { axis, value, .. } = *event {
+            match axis {
+                Axis::LeftX => self.left_x = value,
+                Axis::LeftY => self.left_y = value,
+                Axis::RightX => self.right_x = value,
+                Axis::RightY => self.right_y = value,
+                Axis::TriggerRight => self.trigger = value,
+                _ => (),
+            }
+        }
+    }
+
+    /// The held controls with the stick and trigger mixed in. Past the
+    /// deadzone the stick turns the ship in proportion to how far it is
+    /// pushed
+    pub fn apply(&self, mut input: Input) -> Input {
+        input.turn = steer(self.left_x);
+        input.thrust |= self.trigger > TRIGGER_PULL;
+        input
+    }
+
+    /// Which way the left stick is pushed, if it is out of the deadzone
+    pub fn left(&self) -> Option<f32> {
+        direction(self.left_x, self.left_y)
+    }
+
+    pub fn right(&self) -> Option<f32> {
+        direction(self.right_x, self.right_y)
+    }
+}
+
+/// Angle of a stick clockwise from the right, like the playfield
+fn direction(x: i16, y: i16) -> Option<f32> {
+    let (x, y) = (x as f32, y as f32);
+    if x.hypot(y) > DEADZONE as f32 {
+        Some(y.atan2(x))
+    } else {
+        None
+    }
+}
+
+/// Stick position to steering, with the deadzone taken out so steering
+/// starts from nothing just past it
+fn steer(value: i16) -> i8 {
+    let value = value as f32;
+    let travel = (value.abs() - DEADZONE as f32).max(0.) / (i16::MAX - DEADZONE) as f32;
+
+    (travel.min(1.) * 127. * value.signum()).round() as i8
+}
+
+/// Controllers that are plugged in, opened and closed as they come and go
+pub struct Pads {
+    subsystem: Option<GameControllerSubsystem>,
+    open: Vec<GameController>,
+}
+
+impl Pads {
+    pub fn new(sdl: &Sdl) -> Self {
+        let subsystem = match sdl.game_controller() {
+            Ok(subsystem) => Some(subsystem),
+            Err(e) => {
+                eprintln!("No controller support: {}", e);
+                None
+            }
+        };
+
+        Self {
+            subsystem,
+            open: Vec::new(),
+        }
+    }
+
+    /// Open controllers as they are plugged in and let go of them when they
+    /// are removed. Controllers connected at startup are announced too
+    pub fn event(&mut self, event: &Event) {
+        match *event {
+            Event::ControllerDeviceAdded { which, .. } => {
+                if let Some(subsystem) = &self.subsystem {
+                    match subsystem.open(which) {
+                        Ok(pad) => self.open.push(pad),
+                        Err(e) => eprintln!("Could not open controller {}: {}", which, e),
+                    }
+                }
+            }
+            Event::ControllerDeviceRemoved { which, .. } => {
+                self.open.retain(|pad| pad.instance_id() != which)
+            }
+            _ => (),
+        }
+    }
+
+    /// The player a controller event belongs to, or None if it isn't from
+    /// a controller. Playing alone every controller is player one's,
+    /// otherwise they go to the last players so player one can stay on the
+    /// keyboard when there aren't enough to go round
+    pub fn player(&self, event: &Event, players: usize) -> Option<usize> {
+        let which = match *event {
+            Event::ControllerAxisMotion { which, .. }
+            | Event::ControllerButtonDown { which, .. }
+            | Event::ControllerButtonUp { which, .. } => which,
+            _ => return None,
+        };
+
+        let index = self
+            .open
+            .iter()
+            .position(|pad| pad.instance_id() == which)
+            .unwrap_or(self.open.len());
+        Some(self.owner(index, players))
+    }
+
+    fn owner(&self, index: usize, players: usize) -> usize {
+        if players == 1 {
+            0
+        } else {
+            index + players.saturating_sub(self.open.len())
+        }
+    }
+
+    /// Rumble the controllers of a player whose ship was destroyed
+    pub fn rumble_death(&mut self, player: usize, players: usize) {
+        let (low, high, length) = DEATH_RUMBLE;
+        for index in 0..self.open.len() {
+            if self.owner(index, players) == player {
+                // Not every controller can rumble
+                self.open[index].set_rumble(low, high, length).ok();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::controls::{Action, Bindings, Control};
+    use sdl2::controller::Button;
+
+    fn axis(axis: Axis, value: i16) -> Event {
+        Event::ControllerAxisMotion {
+            timestamp: 0,
+            which: 0,
+            axis,
+            value,
+        }
+    }
+
+    fn button(button: Button, down: bool) -> Event {
+        if down {
+            Event::ControllerButtonDown {
+                timestamp: 0,
+                which: 0,
+                button,
+            }
+        } else {
+            Event::ControllerButtonUp {
+                timestamp: 0,
+                which: 0,
+                button,
+            }
+        }
+    }
+
+    fn turn(value: i16) -> i8 {
+        let mut sticks = Sticks::default();
+        sticks.event(&axis(Axis::LeftX, value));
+        sticks.apply(Input::default()).turn
+    }
+
+    #[test]
+    fn stick_deadzone() {
+        assert_eq!(turn(DEADZONE), 0);
+        assert_eq!(turn(-DEADZONE), 0);
+        assert!(turn(DEADZONE + 500) > 0);
+    }
+
+    #[test]
+    fn stick_turns_in_proportion() {
+        assert_eq!(turn(i16::MAX), 127);
+        assert_eq!(turn(i16::MIN), -127);
+
+        let half = turn(DEADZONE + (i16::MAX - DEADZONE) / 2);
+        assert!((63..=64).contains(&half), "half way turned {}", half);
+    }
+
+    #[test]
+    fn trigger_thrusts() {
+        let mut sticks = Sticks::default();
+        sticks.event(&axis(Axis::TriggerRight, TRIGGER_PULL - 1));
+        assert!(!sticks.apply(Input::default()).thrust);
+
+        sticks.event(&axis(Axis::TriggerRight, i16::MAX));
+        assert!(sticks.apply(Input::default()).thrust);
+
+        // The keyboard still thrusts with the trigger let go
+        sticks.event(&axis(Axis::TriggerRight, 0));
+        let held = Input {
+            thrust: true,
+            ..Input::default()
+        };
+        assert!(sticks.apply(held).thrust);
+    }
+
+    #[test]
+    fn buttons_go_through_bindings() {
+        let mut bindings = Bindings::for_player(0);
+        assert_eq!(
+            bindings.event(&button(Button::A, true)),
+            Some((Action::Fire, true))
+        );
+        assert_eq!(
+            bindings.event(&button(Button::A, false)),
+            Some((Action::Fire, false))
+        );
+        assert_eq!(bindings.event(&button(Button::Guide, true)), None);
+
+        bindings.bind(Action::Hyperspace, Control::Button(Button::A));
+        assert_eq!(
+            bindings.event(&button(Button::A, true)),
+            Some((Action::Hyperspace, true))
+        );
+    }
+}
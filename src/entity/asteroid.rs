@@ -1,150 +1,98 @@
-use crate::{
-    math::{
-        functions::{get_random_radius, rand_angle},
-        vec2::{UpdateVerts, Vec2, Vec2Vec},
-    },
-    render::window::{MID_SIZE, SIZE},
+use super::{
+    component::{Collider, Layer, Renderable, Shape, Transform, Velocity, Wrap},
+    world::{Entity, Kind},
+};
+use crate::math::{
+    functions::get_random_radius,
+    playfield::Playfield,
+    vec2::{Vec2, Vec2Vec},
 };
 use rand::Rng;
-use sdl2::{gfx::primitives::DrawRenderer, pixels::Color, render::Canvas, video::Window};
-use std::{f32::consts::PI, f32::consts::TAU, mem};
+use std::{f32::consts::PI, f32::consts::TAU};
 
 pub const ASTEROID_VERTS: usize = 20;
 const SPEED_MIN: f32 = 1.;
-static mut SPEED_MAX: f32 = 1.7;
-
-#[derive(Clone)]
-pub struct Asteroid {
-    verts: Vec<Vec2>,
-    ghost_verts: Vec<Vec2>,
-    vel: Vec2,
-    angle: f32,
-    divided: bool,
-}
-
-pub trait RemoveAsteroid<Asteroid> {
-    fn break_up(&mut self, index: usize);
-}
-
-impl RemoveAsteroid<Asteroid> for Vec<Asteroid> {
-    fn break_up(&mut self, index: usize) {
-        // divide_remove(self, index);
-        unsafe { SPEED_MAX += 0.1 }
-
-        let asteroid = self.remove(index);
-
-        if !asteroid.divided {
-            let Vec2 { x, y } = asteroid.verts.get_center();
-            for _ in 0..rand::thread_rng().gen_range(2..4) {
-                let new_asteroid = &mut Asteroid::new(20, 50, x, y);
-                new_asteroid.divided = true;
-                new_asteroid.angle = rand_angle();
 
-                self.push(new_asteroid.clone());
-            }
-        }
-
-        if self.is_empty() {
-            for _ in 0..2 {
-                let (x, y) = get_random_radius();
-                self.push(Asteroid::new(40, 100, x, y));
-            }
-        }
-    }
-}
-
-impl UpdateVerts for Asteroid {
-    fn get_verts(&mut self) -> &mut Vec<Vec2> {
-        &mut self.verts
+pub fn new_vec<R: Rng>(speed_max: f32, field: &Playfield, rng: &mut R) -> Vec<Entity> {
+    let mut asteroids = Vec::new();
+    for _ in 0..3 {
+        let (x, y) = get_random_radius(rng, field);
+        asteroids.push(new(40, 100, x, y, speed_max, field, rng));
     }
 
-    fn get_ghost_verts(&mut self) -> &mut Vec<Vec2> {
-        &mut self.ghost_verts
-    }
-
-    fn swap(&mut self) {
-        mem::swap(&mut self.verts, &mut self.ghost_verts);
-    }
+    asteroids
 }
 
-impl Asteroid {
-    pub fn new_vec() -> Vec<Asteroid> {
-        let mut asteroids: Vec<Asteroid> = Vec::new();
-        for _ in 0..3 {
-            let (x, y) = get_random_radius();
-            asteroids.push(Asteroid::new(40, 100, x, y));
-        }
-
-        asteroids
+pub fn new<R: Rng>(
+    min_r: u16,
+    max_r: u16,
+    center_x: f32,
+    center_y: f32,
+    speed_max: f32,
+    field: &Playfield,
+    rng: &mut R,
+) -> Entity {
+    let mut verts = Vec::new();
+
+    for i in 0..ASTEROID_VERTS {
+        let radius = rng.gen_range(min_r..max_r) as f32;
+
+        let angle = (i as f32 / ASTEROID_VERTS as f32) * TAU;
+
+        verts.push(Vec2::new(
+            radius * angle.sin() + center_x,
+            radius * angle.cos() + center_y,
+        ));
     }
 
-    pub fn new(min_r: u16, max_r: u16, center_x: f32, center_y: f32) -> Asteroid {
-        let mut verts = Vec::new();
-
-        for i in 0..ASTEROID_VERTS {
-            let radius = rand::thread_rng().gen_range(min_r..max_r) as f32;
-
-            let angle = (i as f32 / ASTEROID_VERTS as f32) * TAU;
-
-            verts.push(Vec2::new(
-                radius * angle.sin() + center_x,
-                radius * angle.cos() + center_y,
-            ));
-        }
-
-        let center = verts.get_center();
-        let mut angle_to_center = (MID_SIZE - center.y).atan2(MID_SIZE - center.x);
-        // println!("{}", 2. * PI_32 * rand::thread_rng().gen::<f32>());
-        angle_to_center += 2. * PI * rand::thread_rng().gen::<f32>();
-
-        let vel_x = unsafe { SPEED_MIN..SPEED_MAX };
-        let vel_y = unsafe { SPEED_MIN..SPEED_MAX };
-
-        Asteroid {
-            verts: verts.clone(),
-            ghost_verts: verts,
-            vel: Vec2::new(
-                rand::thread_rng().gen_range(vel_x),
-                rand::thread_rng().gen_range(vel_y),
-            ),
-            angle: angle_to_center,
-            divided: false,
-        }
-    }
+    let center = verts.get_center();
+    let mid = field.center();
+    let mut angle_to_center = (mid.y - center.y).atan2(mid.x - center.x);
+    angle_to_center += 2. * PI * rng.gen::<f32>();
 
-    pub fn update(&mut self) {
-        let vel_x = self.vel.x * self.angle.cos();
-        let vel_y = self.vel.y * self.angle.sin();
+    let vel_x = SPEED_MIN..speed_max;
+    let vel_y = SPEED_MIN..speed_max;
 
-        for i in 0..self.verts.len() {
-            self.verts[i].x += vel_x;
-            self.verts[i].y += vel_y;
+    let mut asteroid = outline(verts);
+    asteroid.velocity = Some(Velocity {
+        per_tick: true,
+        ..Velocity::new(
+            Vec2::new(rng.gen_range(vel_x), rng.gen_range(vel_y)),
+            angle_to_center,
+        )
+    });
 
-            self.ghost_verts[i].x += vel_x;
-            self.ghost_verts[i].y += vel_y;
-        }
-    }
+    asteroid
+}
 
-    /// Draw the asteroid
-    pub fn draw(&mut self, canvas: &Canvas<Window>) {
-        // Main verts
-        let (x, y) = self.verts.convert_to_xy_vec();
-        let _ = canvas.filled_polygon(&x, &y, Color::BLACK);
-        let _ = canvas.aa_polygon(&x, &y, Color::WHITE);
+/// A still asteroid with the given outline, for showing one as it was
+/// somewhere else
+pub fn outline(verts: Vec<Vec2>) -> Entity {
+    let mut asteroid = Entity::new(
+        Kind::Asteroid { divided: false },
+        Transform::new(verts),
+        Shape::Polygon,
+    );
+    asteroid.wrap = Some(Wrap::Ghost { settle: false });
+    asteroid.collider = Some(Collider {
+        layer: Layer::Asteroid,
+    });
+    asteroid.renderable = Some(Renderable::Outline);
+
+    asteroid
+}
 
-        // Draw ghost verts if they are on the screen
-        if !self
-            .verts
-            .iter()
-            .all(|f| f.x < SIZE && f.x > 0. && f.y < SIZE && f.y > 0.)
-        {
-            let (x, y) = self.ghost_verts.convert_to_xy_vec();
-            let _ = canvas.filled_polygon(&x, &y, Color::BLACK);
-            let _ = canvas.aa_polygon(&x, &y, Color::WHITE);
-        }
-    }
+/// How far the furthest corner is from the centre
+pub fn radius(asteroid: &Entity) -> f32 {
+    let center = asteroid.center();
+    asteroid
+        .verts()
+        .iter()
+        .map(|vert| Vec2::new(vert.x - center.x, vert.y - center.y).magnitude())
+        .fold(0., f32::max)
+}
 
-    pub fn collision(&mut self, point: &Vec2) -> bool {
-        self.verts.collision(point) || self.ghost_verts.collision(point)
-    }
+/// Whether this is one of the smaller pieces of a broken up asteroid
+pub fn is_divided(asteroid: &Entity) -> bool {
+    asteroid.kind == Kind::Asteroid { divided: true }
 }
@@ -0,0 +1,209 @@
+//! The pieces entities are made of. Every entity has a kind, a transform
+//! and a shape, and whichever of the rest it needs
+
+use crate::{
+    game::TICK,
+    math::vec2::{Vec2, Vec2Vec},
+};
+use serde::{Deserialize, Serialize};
+
+/// Where an entity is, as the corners of its outline in the playfield.
+/// Corners are moved and turned where they are rather than kept around a
+/// centre, which is how the game has always done it, so games play out the
+/// same to the last bit
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Transform {
+    pub verts: Vec<Vec2>,
+    /// The same corners across an edge the entity is over, see `Wrap`.
+    /// Empty for entities that don't need one
+    pub ghost: Vec<Vec2>,
+}
+
+impl Transform {
+    /// At `verts`, with the ghost on top until it is needed
+    pub fn new(verts: Vec<Vec2>) -> Self {
+        Self {
+            ghost: verts.clone(),
+            verts,
+        }
+    }
+
+    /// A single point, like a laser
+    pub fn point(pos: Vec2) -> Self {
+        Self {
+            verts: vec![pos],
+            ghost: Vec::new(),
+        }
+    }
+
+    pub fn center(&self) -> Vec2 {
+        self.verts.get_center()
+    }
+
+    /// Whether a point is inside the outline or its ghost
+    pub fn contains(&self, point: &Vec2) -> bool {
+        self.verts.collision(point) || !self.ghost.is_empty() && self.ghost.collision(point)
+    }
+}
+
+/// What the corners of the transform make
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Shape {
+    /// A closed polygon through them
+    Polygon,
+    /// Just the one point, for something too small for an outline
+    Point,
+}
+
+/// How an entity moves, `speed` along each axis in the direction of
+/// `heading`
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Velocity {
+    pub speed: Vec2,
+    pub heading: f32,
+    /// Radians a second to turn around the centre. When set it is turned
+    /// every tick, even by nothing
+    pub spin: Option<f32>,
+    /// Speed is a distance each tick rather than each second, as asteroids
+    /// have always had it
+    pub per_tick: bool,
+}
+
+impl Velocity {
+    pub fn new(speed: Vec2, heading: f32) -> Self {
+        Self {
+            speed,
+            heading,
+            spin: None,
+            per_tick: false,
+        }
+    }
+
+    /// Going `v`, in units a second
+    pub fn of(v: &Vec2) -> Self {
+        let speed = v.magnitude();
+        Self::new(Vec2::new(speed, speed), v.y.atan2(v.x))
+    }
+
+    /// Which way and how fast it is going, in units a second
+    pub fn per_second(&self) -> Vec2 {
+        let x = self.speed.x * self.heading.cos();
+        let y = self.speed.y * self.heading.sin();
+        if self.per_tick {
+            Vec2::new(x / TICK, y / TICK)
+        } else {
+            Vec2::new(x, y)
+        }
+    }
+}
+
+/// What happens at the edges of the playfield
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Wrap {
+    /// Each point comes back in on the other side
+    Point,
+    /// The ghost shows across any edge the outline is over and takes over
+    /// once the outline is all the way off. With `settle` the ghost is put
+    /// back on top after that, otherwise it trails behind off the field
+    Ghost { settle: bool },
+}
+
+/// What sort of thing collisions are worked out for
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum Layer {
+    Ship,
+    Laser,
+    Asteroid,
+    /// Spawned by a mod, which decides what hitting it does
+    Scripted,
+}
+
+/// Something that can hit and be hit, by anything but its own player's
+#[derive(Clone, Copy, Serialize, Deserialize)]
+pub struct Collider {
+    pub layer: Layer,
+}
+
+/// Goes once it has travelled far enough, or after long enough
+#[derive(Clone, Serialize, Deserialize)]
+pub enum Lifetime {
+    Range {
+        travelled: f32,
+        range: f32,
+    },
+    /// Seconds left
+    Timer {
+        left: f32,
+    },
+}
+
+impl Lifetime {
+    pub fn range(range: f32) -> Self {
+        Lifetime::Range {
+            travelled: 0.,
+            range,
+        }
+    }
+
+    pub fn is_over(&self) -> bool {
+        match *self {
+            Lifetime::Range { travelled, range } => travelled >= range,
+            Lifetime::Timer { left } => left <= 0.,
+        }
+    }
+}
+
+/// How an entity is drawn
+#[derive(Clone, Copy, Serialize, Deserialize)]
+pub enum Renderable {
+    /// Filled in with its owner's colour
+    Solid,
+    /// A white outline over black, hiding whatever is behind
+    Outline,
+    /// A dot in its owner's colour
+    Dot { radius: f32 },
+    /// Just the outline, in a colour of its own
+    Stroke { color: [u8; 3] },
+    /// A dot in a colour of its own, dimmed by any twinkle
+    Star { radius: f32, color: [u8; 3] },
+}
+
+/// Scrolls against the ship's movement rather than moving by itself, for
+/// things far off in the background
+#[derive(Clone, Copy, Serialize, Deserialize)]
+pub struct Parallax {
+    /// How much of the ship's speed it scrolls at
+    pub rate: f32,
+}
+
+/// Gently brightens and dims over and over
+#[derive(Clone, Copy, Serialize, Deserialize)]
+pub struct Twinkle {
+    /// Brightness before twinkling, from 0 to 1
+    pub brightness: f32,
+    /// Twinkles a second and how far through one it is, in radians
+    pub rate: f32,
+    pub phase: f32,
+}
+
+impl Twinkle {
+    /// Most it dims by at the bottom of a twinkle
+    const DEPTH: f32 = 0.35;
+
+    /// How bright it is now
+    pub fn brightness(&self) -> f32 {
+        let twinkle = 0.5 + 0.5 * self.phase.sin();
+        self.brightness * (1. - Twinkle::DEPTH * twinkle)
+    }
+}
+
+/// A ship's controls, kept between ticks
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct Helm {
+    /// Acceleration built up by holding thrust
+    pub accel: f32,
+    /// Fire held last tick, so holding it only fires once
+    pub firing: bool,
+    /// Hyperspace held last tick, so holding it only jumps once
+    pub jumping: bool,
+}
@@ -1,3 +1,6 @@
 pub mod asteroid;
+pub mod component;
 pub mod ship;
 pub mod star;
+pub mod system;
+pub mod world;
@@ -1,37 +1,26 @@
-use crate::math::vec2::Vec2;
-use sdl2::{gfx::primitives::DrawRenderer, pixels::Color, render::Canvas, video::Window};
+use crate::{
+    entity::{
+        component::{Collider, Layer, Lifetime, Renderable, Shape, Transform, Velocity, Wrap},
+        world::{Entity, Kind},
+    },
+    math::vec2::Vec2,
+};
 
-/// The Ships Lasers
-pub struct Laser {
-    pub pos: Vec2,
-    vel: Vec2,
-    angle: f32,
-    pub ddelta: f32,
-}
-
-/// Laser implementation
-impl Laser {
-    pub fn new(pos: Vec2, angle: f32) -> Self {
-        Self {
-            pos: Vec2::new(pos.x, pos.y),
-            vel: Vec2::new(400., 400.),
-            angle,
-            ddelta: 0.,
-        }
-    }
-
-    pub fn draw(&mut self, canvas: &Canvas<Window>) {
-        let _ = canvas.filled_circle(self.pos.x as i16, self.pos.y as i16, 4, Color::WHITE);
-    }
-
-    pub fn update(&mut self, dt: f32) {
-        self.ddelta += self.vel.magnitude() * dt;
+/// How fast lasers fly, in units a second
+pub const LASER_SPEED: f32 = 400.;
+/// How far lasers fly before they fade out
+const LASER_RANGE: f32 = 1000.;
 
-        self.pos.x += self.vel.x * dt * self.angle.cos();
-        self.pos.y += self.vel.y * dt * self.angle.sin();
-    }
+/// A player's laser fired from `pos` towards `angle`
+pub fn new(pos: Vec2, angle: f32, player: usize) -> Entity {
+    let mut laser = Entity::new(Kind::Laser { player }, Transform::point(pos), Shape::Point);
+    laser.velocity = Some(Velocity::new(Vec2::new(LASER_SPEED, LASER_SPEED), angle));
+    laser.wrap = Some(Wrap::Point);
+    laser.collider = Some(Collider {
+        layer: Layer::Laser,
+    });
+    laser.lifetime = Some(Lifetime::range(LASER_RANGE));
+    laser.renderable = Some(Renderable::Dot { radius: 4. });
 
-    pub fn get_pos(&self) -> &Vec2 {
-        &self.pos
-    }
+    laser
 }
@@ -1,2 +1,149 @@
-pub mod body;
 pub mod laser;
+
+use super::{
+    component::{Collider, Helm, Layer, Renderable, Shape, Transform, Velocity, Wrap},
+    world::{Entity, Kind},
+};
+use crate::{
+    game::input::Input,
+    math::vec2::{Vec2, Vec2Vec},
+};
+use std::f32::consts::PI;
+
+pub const SHIP_SCALE: f32 = 7.;
+const MAX_VELOCITY: f32 = 700.;
+/// Radians a second the ship turns with the steering all the way over
+pub const ROTATION_AMOUNT: f32 = 4.;
+/// Fastest the ship turns towards where it is aimed, in radians a second
+const AIM_TURN_RATE: f32 = 8.;
+/// Acceleration gained per second of holding thrust
+const THRUST: f32 = 300.;
+
+/// A player's ship at a standstill centred on `mid`, facing up
+pub fn at(mid: &Vec2, player: usize) -> Entity {
+    // Verts for an isosceles triangle
+    let verts = vec![
+        Vec2::new(mid.x - 2.5 * SHIP_SCALE, mid.y + 2.5 * SHIP_SCALE),
+        Vec2::new(mid.x + 2.5 * SHIP_SCALE, mid.y + 2.5 * SHIP_SCALE),
+        Vec2::new(mid.x, mid.y - 5. * SHIP_SCALE),
+    ];
+
+    let mut ship = Entity::new(Kind::Ship { player }, Transform::new(verts), Shape::Polygon);
+    ship.velocity = Some(Velocity {
+        spin: Some(0.),
+        ..Velocity::new(Vec2::new(0., 0.), 0.)
+    });
+    ship.wrap = Some(Wrap::Ghost { settle: true });
+    ship.collider = Some(Collider { layer: Layer::Ship });
+    ship.renderable = Some(Renderable::Solid);
+    ship.helm = Some(Helm::default());
+
+    ship
+}
+
+/// A still ship centred on `mid` facing `facing`, for showing a ship as it
+/// was somewhere else
+pub fn posed(mid: &Vec2, facing: f32, player: usize) -> Entity {
+    let mut ship = at(mid, player);
+    // Ships start facing up
+    let turn = facing + PI / 2.;
+    ship.transform.verts.rotate(turn);
+    ship.transform.ghost.rotate(turn);
+
+    ship
+}
+
+/// Which way the nose points, in radians clockwise from the right
+pub fn facing(ship: &Entity) -> f32 {
+    let verts = ship.verts();
+    let cent = verts.get_center();
+    (verts[2].y - cent.y).atan2(verts[2].x - cent.x)
+}
+
+pub fn is_thrusting(ship: &Entity) -> bool {
+    ship.helm.as_ref().is_some_and(|helm| helm.accel > 0.)
+}
+
+/// Steer, thrust and fire from the controls held this tick, returns the
+/// laser if one was fired
+pub fn apply(ship: &mut Entity, input: Input, dt: f32) -> Option<Entity> {
+    let facing = facing(ship);
+    let player = ship.kind.player()?;
+    let (helm, velocity) = match (ship.helm.as_mut(), ship.velocity.as_mut()) {
+        (Some(helm), Some(velocity)) => (helm, velocity),
+        _ => return None,
+    };
+
+    velocity.spin = Some(if input.twin_stick {
+        // The short way round, without overshooting
+        let turn = (input.aim_angle() - facing + PI).rem_euclid(2. * PI) - PI;
+        (turn / dt).clamp(-AIM_TURN_RATE, AIM_TURN_RATE)
+    } else {
+        match (input.turn, input.left, input.right) {
+            (0, true, false) => -ROTATION_AMOUNT,
+            (0, false, true) => ROTATION_AMOUNT,
+            (0, _, _) => 0.,
+            (turn, _, _) => ROTATION_AMOUNT * turn as f32 / 127.,
+        }
+    });
+
+    if input.thrust {
+        velocity.heading = if input.twin_stick {
+            input.heading_angle()
+        } else {
+            facing
+        };
+
+        if velocity.speed.magnitude() < MAX_VELOCITY {
+            helm.accel += THRUST * dt;
+        } else {
+            helm.accel = 0.;
+        }
+    } else {
+        helm.accel = 0.;
+    }
+
+    // Only fire once per press
+    let fired = input.fire && !helm.firing;
+    helm.firing = input.fire;
+    if !fired {
+        return None;
+    }
+
+    // Classic ships carry on the way they fire, twin-stick ones keep going
+    // where they were heading
+    if !input.twin_stick {
+        velocity.heading = facing;
+    }
+    Some(laser::new(ship.verts()[2].clone(), facing, player))
+}
+
+/// Whether hyperspace was just pressed, holding it only counts once
+pub fn wants_jump(ship: &mut Entity, input: Input) -> bool {
+    let helm = match ship.helm.as_mut() {
+        Some(helm) => helm,
+        None => return false,
+    };
+    let jump = input.hyperspace && !helm.jumping;
+    helm.jumping = input.hyperspace;
+
+    jump
+}
+
+/// Vanish and reappear centred on `pos`, at a standstill
+pub fn jump_to(ship: &mut Entity, pos: &Vec2) {
+    let transform = &mut ship.transform;
+    let cent = transform.center();
+    for vert in transform.verts.iter_mut() {
+        vert.x += pos.x - cent.x;
+        vert.y += pos.y - cent.y;
+    }
+    transform.ghost = transform.verts.clone();
+
+    if let Some(velocity) = ship.velocity.as_mut() {
+        velocity.speed = Vec2::new(0., 0.);
+    }
+    if let Some(helm) = ship.helm.as_mut() {
+        helm.accel = 0.;
+    }
+}
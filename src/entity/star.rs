@@ -1,36 +1,187 @@
-use crate::math::vec2::Vec2;
-use crate::render::window::SIZE;
+use super::{
+    component::{Parallax, Renderable, Shape, Transform, Twinkle, Wrap},
+    system,
+    world::{Entity, Kind, World},
+};
+use crate::{
+    math::{playfield::Playfield, vec2::Vec2},
+    render::renderer::Renderer,
+};
 use rand::Rng;
-use sdl2::{gfx::primitives::DrawRenderer, pixels::Color, render::Canvas, video::Window};
+use sdl2::pixels::Color;
+use std::f32::consts::TAU;
 
-const STAR_COUNT: usize = 200;
+/// How fast the nearest layer scrolls compared to the ship
+const MAX_PARALLAX: f32 = 0.25;
+/// Faint tints stars are given, most are plain white
+const TINTS: [[u8; 3]; 6] = [
+    [255, 255, 255],
+    [255, 255, 255],
+    [255, 255, 255],
+    [200, 215, 255],
+    [255, 240, 200],
+    [255, 210, 200],
+];
 
-pub struct Star {
-    pos: Vec2,
-    r: i16,
+/// Layers of the starfield and how many stars there are in each 100 by
+/// 100 patch of the playfield, across all the layers
+#[derive(Clone, Copy)]
+pub struct StarSettings {
+    pub layers: u32,
+    pub density: f32,
 }
 
-impl Star {
-    pub fn new(pos: Vec2, r: i16) -> Self {
-        Self { pos, r }
-    }
-
-    pub fn new_vec() -> Vec<Star> {
-        let mut stars: Vec<Star> = Vec::new();
-        for _ in 0..STAR_COUNT {
-            stars.push(Star::new(
-                Vec2::new(
-                    rand::thread_rng().gen_range(0..SIZE as u16) as f32,
-                    rand::thread_rng().gen_range(0..SIZE as u16) as f32,
-                ),
-                rand::thread_rng().gen_range(1..3) as i16,
-            ));
+/// A star at `pos` in the layer at `depth`, from 0 for far off to 1 for
+/// the nearest. Nearer stars are bigger, brighter and scroll faster
+pub fn new<R: Rng>(pos: Vec2, depth: f32, rng: &mut R) -> Entity {
+    let mut star = Entity::new(Kind::Star, Transform::point(pos), Shape::Point);
+    star.wrap = Some(Wrap::Point);
+    star.parallax = Some(Parallax {
+        rate: depth * MAX_PARALLAX,
+    });
+    star.renderable = Some(Renderable::Star {
+        radius: 1. + depth * rng.gen_range(0.3..1.2),
+        color: TINTS[rng.gen_range(0..TINTS.len())],
+    });
+    star.twinkle = Some(Twinkle {
+        brightness: 0.45 + 0.55 * depth * rng.gen_range(0.7..1.),
+        rate: rng.gen_range(0.3..1.5),
+        phase: rng.gen_range(0.0..TAU),
+    });
+
+    star
+}
+
+/// Layers of stars that scroll against the ship's movement, kept in a
+/// world of their own as they aren't part of the game
+pub struct Starfield {
+    world: World,
+    field: Playfield,
+}
+
+impl Starfield {
+    pub fn new<R: Rng>(field: &Playfield, settings: StarSettings, rng: &mut R) -> Self {
+        let layers = settings.layers.max(1);
+        let count = (field.width * field.height / 10_000. * settings.density / layers as f32)
+            .max(0.) as usize;
+
+        // Furthest first so nearer stars draw over them
+        let mut world = World::default();
+        for i in 0..layers {
+            let depth = (i + 1) as f32 / layers as f32;
+            for _ in 0..count {
+                let pos = Vec2::new(
+                    rng.gen_range(0.0..field.width),
+                    rng.gen_range(0.0..field.height),
+                );
+                world.spawn(new(pos, depth, rng));
+            }
+        }
+
+        Self {
+            world,
+            field: *field,
+        }
+    }
+
+    /// Scroll against the ship's `velocity`, in units a second
+    pub fn update(&mut self, velocity: &Vec2, dt: f32, field: &Playfield) {
+        self.field = *field;
+
+        system::parallax(&mut self.world, velocity);
+        system::twinkle(&mut self.world, dt);
+        system::movement(&mut self.world, dt);
+        system::wrap(&mut self.world, field);
+    }
+
+    pub fn draw(&self, renderer: &mut dyn Renderer) {
+        system::render(&self.world, renderer, &self.field, |_| Color::WHITE);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    fn starfield(layers: u32, density: f32) -> Starfield {
+        let field = Playfield::new(800., 600.);
+        let settings = StarSettings { layers, density };
+        Starfield::new(&field, settings, &mut StdRng::seed_from_u64(1))
+    }
+
+    /// Where the stars in each layer are, furthest first
+    fn positions(starfield: &Starfield) -> Vec<Vec<Vec2>> {
+        let mut layers: Vec<(f32, Vec<Vec2>)> = Vec::new();
+        for star in starfield.world.iter() {
+            let rate = star.parallax.map_or(0., |parallax| parallax.rate);
+            match layers.last_mut() {
+                Some((layer, stars)) if *layer == rate => stars.push(star.center()),
+                _ => layers.push((rate, vec![star.center()])),
+            }
         }
 
-        stars
+        layers.into_iter().map(|(_, stars)| stars).collect()
     }
 
-    pub fn draw(&mut self, canvas: &Canvas<Window>) {
-        let _ = canvas.filled_circle(self.pos.x as i16, self.pos.y as i16, self.r, Color::WHITE);
+    #[test]
+    fn nearer_layers_scroll_faster() {
+        let field = Playfield::new(800., 600.);
+        let mut stars = starfield(3, 2.);
+        let before = positions(&stars);
+        stars.update(&Vec2::new(-10., 0.), 1., &field);
+        let after = positions(&stars);
+
+        let moved: Vec<f32> = before
+            .iter()
+            .zip(&after)
+            .map(|(before, after)| {
+                // Against the ship, so right while it goes left
+                let dx = field.offset(&before[0], &after[0]).x;
+                for (before, after) in before.iter().zip(after) {
+                    let offset = field.offset(before, after);
+                    assert!((offset.x - dx).abs() < 1e-3);
+                    assert_eq!(offset.y, 0.);
+                }
+                dx
+            })
+            .collect();
+
+        assert!(moved[0] > 0.);
+        assert!(moved.windows(2).all(|pair| pair[1] > pair[0]));
+        assert!((moved[2] - 10. * MAX_PARALLAX).abs() < 1e-3);
+    }
+
+    #[test]
+    fn stars_wrap_at_the_edges() {
+        let field = Playfield::new(800., 600.);
+        let mut stars = starfield(2, 2.);
+        for _ in 0..100 {
+            stars.update(&Vec2::new(300., -700.), 0.1, &field);
+        }
+
+        for star in stars.world.iter().map(Entity::center) {
+            assert!(star.x >= 0. && star.x <= field.width);
+            assert!(star.y >= 0. && star.y <= field.height);
+        }
+    }
+
+    #[test]
+    fn density_sets_the_star_count() {
+        let count = |layers, density| -> usize {
+            positions(&starfield(layers, density))
+                .iter()
+                .map(Vec::len)
+                .sum()
+        };
+
+        // 48 patches of 100 by 100
+        assert_eq!(count(1, 1.), 48);
+        assert_eq!(count(1, 2.5), 120);
+        assert_eq!(count(3, 2.5), 120);
+        assert_eq!(count(4, 0.), 0);
+        // No layers still means one, and fewer stars never means negative
+        assert_eq!(positions(&starfield(0, 1.)).len(), 1);
+        assert_eq!(count(1, -1.), 0);
     }
 }
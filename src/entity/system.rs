@@ -0,0 +1,373 @@
+//! Systems run over every entity that has the components they need, so a
+//! new sort of entity only needs the right components to join in
+
+use super::{
+    component::{Layer, Lifetime, Renderable, Shape, Transform, Velocity, Wrap},
+    world::{Entity, Id, World},
+};
+use crate::{
+    math::{
+        playfield::Playfield,
+        vec2::{Vec2, Vec2Vec},
+    },
+    render::renderer::Renderer,
+};
+use sdl2::pixels::Color;
+use std::{cmp::Reverse, f32::consts::TAU, mem};
+
+/// Slows everything with a helm a little (even though in space there is
+/// no friction), then speeds it up by as much thrust as is held
+pub fn thrust(world: &mut World, dt: f32) {
+    for entity in world.iter_mut() {
+        if let (Some(helm), Some(velocity)) = (&entity.helm, entity.velocity.as_mut()) {
+            velocity.speed.x *= 0.98;
+            velocity.speed.y *= 0.98;
+
+            let dv = helm.accel * dt * 50.;
+            velocity.speed.x += dv;
+            velocity.speed.y += dv;
+        }
+    }
+}
+
+/// Set everything in the background scrolling against `velocity`, the
+/// ship's in units a second
+pub fn parallax(world: &mut World, velocity: &Vec2) {
+    for entity in world.iter_mut() {
+        if let Some(parallax) = entity.parallax {
+            let scroll = Vec2::new(-velocity.x * parallax.rate, -velocity.y * parallax.rate);
+            entity.velocity = Some(Velocity::of(&scroll));
+        }
+    }
+}
+
+/// Move along everything that twinkles
+pub fn twinkle(world: &mut World, dt: f32) {
+    for entity in world.iter_mut() {
+        if let Some(twinkle) = entity.twinkle.as_mut() {
+            twinkle.phase = (twinkle.phase + twinkle.rate * TAU * dt) % TAU;
+        }
+    }
+}
+
+/// Turn and move everything with a velocity
+pub fn movement(world: &mut World, dt: f32) {
+    for entity in world.iter_mut() {
+        let (velocity, transform) = match (&entity.velocity, &mut entity.transform) {
+            (Some(velocity), transform) => (velocity, transform),
+            (None, _) => continue,
+        };
+
+        if let Some(spin) = velocity.spin {
+            transform.verts.rotate(spin * dt);
+            if !transform.ghost.is_empty() {
+                transform.ghost.rotate(spin * dt);
+            }
+        }
+
+        let step = if velocity.per_tick { 1. } else { dt };
+        let dx = velocity.speed.x * step * velocity.heading.cos();
+        let dy = velocity.speed.y * step * velocity.heading.sin();
+        for vert in transform.verts.iter_mut().chain(transform.ghost.iter_mut()) {
+            vert.x += dx;
+            vert.y += dy;
+        }
+    }
+}
+
+/// Count up how far everything with a lifetime has gone, or how long it
+/// has been around, and remove what is done
+pub fn lifetime(world: &mut World, dt: f32) {
+    for entity in world.iter_mut() {
+        match (entity.lifetime.as_mut(), &entity.velocity) {
+            (Some(Lifetime::Range { travelled, .. }), Some(velocity)) => {
+                *travelled += velocity.speed.magnitude() * dt
+            }
+            (Some(Lifetime::Timer { left }), _) => *left -= dt,
+            _ => (),
+        }
+    }
+
+    world.retain(|entity| {
+        entity
+            .lifetime
+            .as_ref()
+            .is_none_or(|lifetime| !lifetime.is_over())
+    });
+}
+
+/// Bring everything that has gone off an edge back in the other side
+pub fn wrap(world: &mut World, field: &Playfield) {
+    for entity in world.iter_mut() {
+        match entity.wrap {
+            Some(Wrap::Point) => {
+                for vert in entity.transform.verts.iter_mut() {
+                    vert.wrap_point(field);
+                }
+            }
+            Some(Wrap::Ghost { settle }) => wrap_ghost(&mut entity.transform, settle, field),
+            None => (),
+        }
+    }
+}
+
+fn wrap_ghost(transform: &mut Transform, settle: bool, field: &Playfield) {
+    let mut dx = 0.;
+    let mut dy = 0.;
+
+    for Vec2 { x, y } in transform.verts.iter() {
+        if y < &0. {
+            dy = field.height;
+        }
+        if y > &field.height {
+            dy = -field.height;
+        }
+        if x < &0. {
+            dx = field.width;
+        }
+        if x > &field.width {
+            dx = -field.width;
+        }
+    }
+
+    if (dx - dy).abs() > 0. {
+        for (ghost, vert) in transform.ghost.iter_mut().zip(&transform.verts) {
+            ghost.x = vert.x + dx;
+            ghost.y = vert.y + dy;
+        }
+    }
+
+    if transform
+        .verts
+        .iter()
+        .all(|f| (f.y < 0. || f.y > field.height) || (f.x < 0. || f.x > field.width))
+    {
+        mem::swap(&mut transform.verts, &mut transform.ghost);
+        if settle {
+            transform.ghost = transform.verts.clone();
+        }
+    }
+}
+
+/// Everything on `layer` that could hit something, in the order their
+/// hits are worked out: each player's in turn, newest first
+pub fn hitters(world: &World, layer: Layer) -> Vec<Id> {
+    let mut hitters: Vec<_> = world
+        .iter()
+        .filter(|entity| entity.on_layer(layer))
+        .map(|entity| (entity.kind.player(), entity.id()))
+        .collect();
+    hitters.sort_by_key(|&(player, id)| (player, Reverse(id)));
+
+    hitters.into_iter().map(|(_, id)| id).collect()
+}
+
+/// The first thing on `layer` that `hitter` is touching, in the order they
+/// were added. Nothing hits anything of the same player's. `checks` counts
+/// every test made
+pub fn first_hit(world: &World, hitter: Id, layer: Layer, checks: &mut u64) -> Option<Id> {
+    let hitter = world.get(hitter)?;
+    let player = hitter.kind.player();
+
+    let hit = world
+        .iter()
+        .filter(|target| target.on_layer(layer) && target.id() != hitter.id())
+        .filter(|target| player.is_none() || target.kind.player() != player)
+        .find(|target| {
+            *checks += 1;
+            touches(hitter, target)
+        });
+
+    hit.map(Entity::id)
+}
+
+/// Whether any corner of `hitter`, or of its ghost, is inside `target`
+pub fn touches(hitter: &Entity, target: &Entity) -> bool {
+    if target.shape != Shape::Polygon {
+        return false;
+    }
+
+    let transform = &hitter.transform;
+    transform
+        .verts
+        .iter()
+        .any(|point| target.transform.contains(point))
+        || transform
+            .ghost
+            .iter()
+            .any(|point| target.transform.contains(point))
+}
+
+/// Draw everything with a look, in the order it was added but with
+/// outlines over the rest, as asteroids always have been. Anything drawn in
+/// its player's colour gets it from `color`, and anything with a colour of
+/// its own is drawn in that
+pub fn render(
+    world: &World,
+    renderer: &mut dyn Renderer,
+    field: &Playfield,
+    color: impl Fn(usize) -> Color,
+) {
+    let (outlines, rest): (Vec<_>, Vec<_>) = world
+        .iter()
+        .partition(|entity| matches!(entity.renderable, Some(Renderable::Outline)));
+
+    for entity in rest.into_iter().chain(outlines) {
+        let transform = &entity.transform;
+        let player = entity.kind.player().map_or(Color::WHITE, &color);
+        // The ghost only shows while the outline is over an edge
+        let over_edge = !transform.verts.iter().all(|vert| field.contains(vert));
+
+        match entity.renderable {
+            Some(Renderable::Solid) => {
+                renderer.filled_polygon(&transform.verts, player);
+                if over_edge {
+                    renderer.filled_polygon(&transform.ghost, player);
+                }
+            }
+            Some(Renderable::Outline) => {
+                renderer.filled_polygon(&transform.verts, Color::BLACK);
+                renderer.polygon(&transform.verts, Color::WHITE);
+                if over_edge {
+                    renderer.filled_polygon(&transform.ghost, Color::BLACK);
+                    renderer.polygon(&transform.ghost, Color::WHITE);
+                }
+            }
+            Some(Renderable::Dot { radius }) => {
+                for vert in transform.verts.iter() {
+                    renderer.circle(vert, radius, player);
+                }
+            }
+            Some(Renderable::Stroke { color: [r, g, b] }) => {
+                let color = Color::RGB(r, g, b);
+                renderer.polygon(&transform.verts, color);
+                if over_edge {
+                    renderer.polygon(&transform.ghost, color);
+                }
+            }
+            Some(Renderable::Star { radius, color }) => {
+                let brightness = entity.twinkle.map_or(1., |twinkle| twinkle.brightness());
+                let [r, g, b] = color.map(|c| (c as f32 * brightness) as u8);
+                for vert in transform.verts.iter() {
+                    renderer.circle(vert, radius, Color::RGB(r, g, b));
+                }
+            }
+            None => (),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        entity::{asteroid, component::Velocity, ship, ship::laser},
+        game::TICK,
+    };
+
+    fn square(x: f32, y: f32, size: f32) -> Vec<Vec2> {
+        vec![
+            Vec2::new(x, y),
+            Vec2::new(x + size, y),
+            Vec2::new(x + size, y + size),
+            Vec2::new(x, y + size),
+        ]
+    }
+
+    fn points(verts: &[Vec2]) -> Vec<(f32, f32)> {
+        verts.iter().map(|vert| (vert.x, vert.y)).collect()
+    }
+
+    #[test]
+    fn lasers_go_at_their_range() {
+        let mut world = World::default();
+        let laser = laser::new(Vec2::new(100., 100.), 0.3, 0);
+        let (speed, range) = match (&laser.velocity, &laser.lifetime) {
+            (Some(velocity), Some(Lifetime::Range { range, .. })) => {
+                (velocity.speed.magnitude(), *range)
+            }
+            _ => panic!("lasers have a speed and range"),
+        };
+        let laser = world.spawn(laser);
+        let rock = world.spawn(asteroid::outline(square(0., 0., 10.)));
+
+        let mut ticks = 0;
+        while world.get(laser).is_some() {
+            movement(&mut world, TICK);
+            lifetime(&mut world, TICK);
+            ticks += 1;
+        }
+
+        let expected = range / speed / TICK;
+        assert!(
+            (ticks as f32 - expected).abs() <= 1.,
+            "{} {}",
+            ticks,
+            expected
+        );
+        assert!(world.get(rock).is_some());
+    }
+
+    fn wrapped(settle: bool) -> Transform {
+        let field = Playfield::new(800., 600.);
+        let mut world = World::default();
+        let mut entity = asteroid::outline(square(790., 100., 20.));
+        entity.wrap = Some(Wrap::Ghost { settle });
+        entity.velocity = Some(Velocity::new(Vec2::new(20., 20.), 0.));
+        let id = world.spawn(entity);
+
+        // Over the edge the ghost shows on the other side
+        wrap(&mut world, &field);
+        let transform = &world.get(id).unwrap().transform;
+        assert_eq!(points(&transform.verts), points(&square(790., 100., 20.)));
+        assert_eq!(points(&transform.ghost), points(&square(-10., 100., 20.)));
+
+        // All the way off, the ghost takes over
+        movement(&mut world, 1.);
+        wrap(&mut world, &field);
+        world.get(id).unwrap().transform.clone()
+    }
+
+    #[test]
+    fn ghosts_take_over_off_the_edge() {
+        let settled = wrapped(true);
+        assert_eq!(points(&settled.verts), points(&square(10., 100., 20.)));
+        assert_eq!(points(&settled.ghost), points(&settled.verts));
+
+        // Without settling the old outline trails off the field
+        let trailing = wrapped(false);
+        assert_eq!(points(&trailing.verts), points(&square(10., 100., 20.)));
+        assert_eq!(points(&trailing.ghost), points(&square(810., 100., 20.)));
+    }
+
+    #[test]
+    fn nothing_hits_its_own_players() {
+        let mut world = World::default();
+        let mine = world.spawn(ship::at(&Vec2::new(400., 400.), 0));
+        let theirs = world.spawn(ship::at(&Vec2::new(100., 100.), 1));
+        let own_shot = world.spawn(laser::new(Vec2::new(400., 400.), 0., 0));
+        let their_shot = world.spawn(laser::new(Vec2::new(400., 400.), 0., 1));
+        let rock = world.spawn(asteroid::outline(square(350., 350., 100.)));
+
+        let mut checks = 0;
+        assert_eq!(first_hit(&world, own_shot, Layer::Ship, &mut checks), None);
+        // Their ship is checked but missed, ours isn't checked at all
+        assert_eq!(checks, 1);
+        assert_eq!(
+            first_hit(&world, their_shot, Layer::Ship, &mut checks),
+            Some(mine)
+        );
+        assert_eq!(
+            first_hit(&world, own_shot, Layer::Asteroid, &mut checks),
+            Some(rock)
+        );
+        assert_eq!(
+            first_hit(&world, mine, Layer::Asteroid, &mut checks),
+            Some(rock)
+        );
+        assert_eq!(
+            first_hit(&world, theirs, Layer::Asteroid, &mut checks),
+            None
+        );
+    }
+}
@@ -0,0 +1,227 @@
+use super::component::{
+    Collider, Helm, Layer, Lifetime, Parallax, Renderable, Shape, Transform, Twinkle, Velocity,
+    Wrap,
+};
+use crate::math::vec2::Vec2;
+use serde::{Deserialize, Serialize};
+
+/// Stays the same for as long as an entity is in the world, unlike where it
+/// is in the list
+pub type Id = u32;
+
+/// What an entity is, for the game to decide what happens when it hits
+/// something
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum Kind {
+    Ship {
+        player: usize,
+    },
+    Laser {
+        player: usize,
+    },
+    Asteroid {
+        divided: bool,
+    },
+    /// Something in the background, not part of the game
+    Star,
+    /// Spawned by the `script`th mod, which can make it kill ships that
+    /// touch it and let lasers shoot it
+    Scripted {
+        script: usize,
+        deadly: bool,
+        shootable: bool,
+    },
+}
+
+impl Kind {
+    /// The player it belongs to, if anyone
+    pub fn player(&self) -> Option<usize> {
+        match *self {
+            Kind::Ship { player } | Kind::Laser { player } => Some(player),
+            Kind::Asteroid { .. } | Kind::Star | Kind::Scripted { .. } => None,
+        }
+    }
+}
+
+/// Anything in the playfield, made of components. Build one with the
+/// functions in `ship`, `laser`, `asteroid` and `star`, or from a mod, and
+/// add it to a `World`
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Entity {
+    id: Id,
+    pub kind: Kind,
+    pub transform: Transform,
+    pub shape: Shape,
+    pub velocity: Option<Velocity>,
+    pub wrap: Option<Wrap>,
+    pub collider: Option<Collider>,
+    pub lifetime: Option<Lifetime>,
+    pub renderable: Option<Renderable>,
+    pub helm: Option<Helm>,
+    pub parallax: Option<Parallax>,
+    pub twinkle: Option<Twinkle>,
+}
+
+impl Entity {
+    /// Something that sits at `transform` and does nothing until it is
+    /// given more components
+    pub fn new(kind: Kind, transform: Transform, shape: Shape) -> Self {
+        Self {
+            id: 0,
+            kind,
+            transform,
+            shape,
+            velocity: None,
+            wrap: None,
+            collider: None,
+            lifetime: None,
+            renderable: None,
+            helm: None,
+            parallax: None,
+            twinkle: None,
+        }
+    }
+
+    pub fn id(&self) -> Id {
+        self.id
+    }
+
+    pub fn center(&self) -> Vec2 {
+        self.transform.center()
+    }
+
+    /// Which way and how fast it is going, in units a second
+    pub fn velocity(&self) -> Vec2 {
+        self.velocity
+            .as_ref()
+            .map_or(Vec2::new(0., 0.), |velocity| velocity.per_second())
+    }
+
+    /// Corners of the outline, or the point
+    pub fn verts(&self) -> &[Vec2] {
+        &self.transform.verts
+    }
+
+    pub fn on_layer(&self, layer: Layer) -> bool {
+        self.collider
+            .is_some_and(|collider| collider.layer == layer)
+    }
+}
+
+/// Every entity in a game, or in the background of one, in the order they
+/// were added
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct World {
+    entities: Vec<Entity>,
+    next_id: Id,
+}
+
+impl World {
+    /// Add an entity after the rest, returning its new id
+    pub fn spawn(&mut self, mut entity: Entity) -> Id {
+        let id = self.next_id;
+        self.next_id += 1;
+        entity.id = id;
+        self.entities.push(entity);
+
+        id
+    }
+
+    pub fn despawn(&mut self, id: Id) -> Option<Entity> {
+        let index = self.index(id)?;
+        Some(self.entities.remove(index))
+    }
+
+    /// Put `entity` where the one with `id` is, taking over its id.
+    /// Returns the one it replaced
+    pub fn replace(&mut self, id: Id, mut entity: Entity) -> Option<Entity> {
+        let index = self.index(id)?;
+        entity.id = id;
+        Some(std::mem::replace(&mut self.entities[index], entity))
+    }
+
+    pub fn get(&self, id: Id) -> Option<&Entity> {
+        self.index(id).map(|index| &self.entities[index])
+    }
+
+    pub fn get_mut(&mut self, id: Id) -> Option<&mut Entity> {
+        self.index(id).map(move |index| &mut self.entities[index])
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Entity> {
+        self.entities.iter()
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut Entity> {
+        self.entities.iter_mut()
+    }
+
+    /// Keep only the entities `keep` says to
+    pub fn retain(&mut self, keep: impl FnMut(&Entity) -> bool) {
+        self.entities.retain(keep);
+    }
+
+    pub fn len(&self) -> usize {
+        self.entities.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entities.is_empty()
+    }
+
+    /// Entities are only ever added at the end, so ids are in order
+    fn index(&self, id: Id) -> Option<usize> {
+        self.entities
+            .binary_search_by_key(&id, |entity| entity.id)
+            .ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(x: f32) -> Entity {
+        let transform = Transform::point(Vec2::new(x, 0.));
+        Entity::new(Kind::Asteroid { divided: false }, transform, Shape::Point)
+    }
+
+    fn ids(world: &World) -> Vec<Id> {
+        world.iter().map(Entity::id).collect()
+    }
+
+    #[test]
+    fn ids_stay_in_order() {
+        let mut world = World::default();
+        let spawned: Vec<Id> = (0..5).map(|i| world.spawn(at(i as f32))).collect();
+        assert_eq!(ids(&world), spawned);
+
+        assert_eq!(world.despawn(spawned[1]).map(|e| e.center().x), Some(1.));
+        assert_eq!(world.despawn(spawned[3]).map(|e| e.center().x), Some(3.));
+        assert!(world.despawn(spawned[3]).is_none());
+        assert!(world.get(spawned[1]).is_none());
+
+        // Ids are never used again, so new ones still go on the end
+        let late = world.spawn(at(5.));
+        assert!(late > spawned[4]);
+        assert_eq!(ids(&world), [spawned[0], spawned[2], spawned[4], late]);
+        for (id, x) in [
+            (spawned[0], 0.),
+            (spawned[2], 2.),
+            (spawned[4], 4.),
+            (late, 5.),
+        ] {
+            assert_eq!(world.get(id).map(|e| e.center().x), Some(x));
+        }
+
+        // Swapped entities keep their place
+        let old = world.replace(spawned[2], at(9.));
+        assert_eq!(old.map(|e| e.center().x), Some(2.));
+        assert_eq!(world.get(spawned[2]).map(|e| e.center().x), Some(9.));
+        assert_eq!(ids(&world), [spawned[0], spawned[2], spawned[4], late]);
+
+        world.retain(|entity| entity.center().x > 4.);
+        assert_eq!(ids(&world), [spawned[2], late]);
+        assert_eq!(world.len(), 2);
+    }
+}
@@ -0,0 +1,426 @@
+use crate::{
+    entity::{
+        asteroid, ship,
+        star::{StarSettings, Starfield},
+    },
+    game::{
+        input::Input,
+        rules::{Players, Rules},
+        Game, GameEvent,
+    },
+    math::vec2::Vec2,
+    render::capture,
+};
+use std::{cmp::Ordering, ops::Range};
+
+/// How many different actions `action` knows: steering three ways, and
+/// thrust, fire and hyperspace each on or off
+pub const ACTIONS: usize = 24;
+/// Numbers in the features for the ship, before the asteroids
+const SHIP_FEATURES: usize = 9;
+/// Numbers in the features for each asteroid
+const ASTEROID_FEATURES: usize = 7;
+/// Speeds are divided by this so they mostly land between -1 and 1
+const SPEED_SCALE: f32 = 500.;
+
+/// What happening things are worth to the agent. The default is just the
+/// score, anything else is shaping
+#[derive(Clone, Copy)]
+pub struct Rewards {
+    /// For every point scored
+    pub point: f32,
+    /// For each life lost
+    pub death: f32,
+    /// For every tick the ship is still flying
+    pub alive: f32,
+    /// For each laser fired
+    pub shot: f32,
+    /// For clearing the field
+    pub wave: f32,
+}
+
+impl Default for Rewards {
+    fn default() -> Self {
+        Self {
+            point: 1.,
+            death: 0.,
+            alive: 0.,
+            shot: 0.,
+            wave: 0.,
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct EnvSettings {
+    /// Ticks each action is held for
+    pub frame_skip: u32,
+    /// Asteroids described in the features, nearest first
+    pub nearest: usize,
+    /// Width and height of the greyscale frame in each observation, none
+    /// to not render at all
+    pub frame: Option<(u32, u32)>,
+    /// Steps before an episode is cut short, if ever
+    pub max_steps: Option<u32>,
+    pub rewards: Rewards,
+}
+
+impl Default for EnvSettings {
+    fn default() -> Self {
+        Self {
+            frame_skip: 4,
+            nearest: 8,
+            frame: None,
+            max_steps: None,
+            rewards: Rewards::default(),
+        }
+    }
+}
+
+/// What the agent sees after each step
+pub struct Observation {
+    /// The ship's position, heading, velocity, whether it is thrusting,
+    /// lives and lasers in flight, then for each of the nearest asteroids
+    /// whether it is there at all, the shortest way to it across the edges,
+    /// how far that is, its velocity and whether it is a large one
+    pub features: Vec<f32>,
+    /// Rows of greyscale bytes, top to bottom
+    pub frame: Option<Vec<u8>>,
+}
+
+pub struct Step {
+    pub observation: Observation,
+    pub reward: f32,
+    pub done: bool,
+}
+
+/// A single ship game to train agents on, stepped by hand in the style of
+/// a gym environment. Episodes are the same for the same seed and actions
+pub struct Env {
+    rules: Rules,
+    settings: EnvSettings,
+    game: Game,
+    steps: u32,
+    /// No stars, so frames only show what matters
+    stars: Starfield,
+}
+
+impl Env {
+    /// An environment for games with `rules`, always with one player
+    pub fn new(rules: Rules, settings: EnvSettings) -> Env {
+        let rules = Rules {
+            players: Players::Single,
+            ..rules
+        };
+        let none = StarSettings {
+            layers: 1,
+            density: 0.,
+        };
+
+        Env {
+            rules,
+            settings,
+            game: Game::new(0, rules),
+            steps: 0,
+            stars: Starfield::new(&rules.field, none, &mut rand::thread_rng()),
+        }
+    }
+
+    /// Start a new episode
+    pub fn reset(&mut self, seed: u64) -> Observation {
+        self.game = Game::new(seed, self.rules);
+        self.steps = 0;
+
+        self.observe()
+    }
+
+    /// Hold `input` for `frame_skip` ticks, or until the game ends
+    pub fn step(&mut self, input: Input) -> Step {
+        let rewards = self.settings.rewards;
+        let mut reward = 0.;
+
+        if !self.is_done() {
+            for _ in 0..self.settings.frame_skip.max(1) {
+                let (score, wave) = (self.game.score(), self.game.wave);
+
+                for event in self.game.tick(&[input]) {
+                    reward += match event {
+                        GameEvent::Fired => rewards.shot,
+                        GameEvent::ShipDestroyed { .. } => rewards.death,
+                        _ => 0.,
+                    };
+                }
+                reward += (self.game.score() - score) as f32 * rewards.point;
+                reward += (self.game.wave - wave) as f32 * rewards.wave;
+
+                if self.game.is_over() {
+                    break;
+                }
+                reward += rewards.alive;
+            }
+            self.steps += 1;
+        }
+
+        Step {
+            observation: self.observe(),
+            reward,
+            done: self.is_done(),
+        }
+    }
+
+    /// Over, or out of steps
+    pub fn is_done(&self) -> bool {
+        self.game.is_over() || self.settings.max_steps.is_some_and(|max| self.steps >= max)
+    }
+
+    pub fn game(&self) -> &Game {
+        &self.game
+    }
+
+    /// How many numbers are in the features
+    pub fn feature_count(&self) -> usize {
+        SHIP_FEATURES + self.settings.nearest * ASTEROID_FEATURES
+    }
+
+    pub fn observe(&self) -> Observation {
+        Observation {
+            features: self.features(),
+            frame: self
+                .settings
+                .frame
+                .map(|(width, height)| self.frame(width, height)),
+        }
+    }
+
+    /// The game as numbers, see `Observation`
+    pub fn features(&self) -> Vec<f32> {
+        let field = &self.game.rules.field;
+        let player = &self.game.players[0];
+        // Once out there is no ship, it reads as still in the middle
+        let ship = self.game.ship(0);
+        let (center, velocity, facing) = match ship {
+            Some(ship) => (ship.center(), ship.velocity(), ship::facing(ship)),
+            None => (field.center(), Vec2::new(0., 0.), 0.),
+        };
+
+        let mut features = vec![
+            center.x / field.width,
+            center.y / field.height,
+            facing.cos(),
+            facing.sin(),
+            velocity.x / SPEED_SCALE,
+            velocity.y / SPEED_SCALE,
+            ship.is_some_and(ship::is_thrusting) as u8 as f32,
+            player.lives as f32,
+            self.game.lasers(0).count() as f32,
+        ];
+
+        let mut asteroids: Vec<_> = self
+            .game
+            .asteroids()
+            .map(|asteroid| {
+                let offset = field.offset(&center, &asteroid.center());
+                (offset.magnitude(), offset, asteroid)
+            })
+            .collect();
+        asteroids.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(Ordering::Equal));
+
+        // Half the diagonal is as far away as anything can be
+        let furthest = (field.width.powi(2) + field.height.powi(2)).sqrt() / 2.;
+        for i in 0..self.settings.nearest {
+            match asteroids.get(i) {
+                Some((distance, offset, asteroid)) => {
+                    let velocity = asteroid.velocity();
+                    features.extend_from_slice(&[
+                        1.,
+                        offset.x / field.width,
+                        offset.y / field.height,
+                        distance / furthest,
+                        velocity.x / SPEED_SCALE,
+                        velocity.y / SPEED_SCALE,
+                        !asteroid::is_divided(asteroid) as u8 as f32,
+                    ]);
+                }
+                None => features.extend_from_slice(&[0.; ASTEROID_FEATURES]),
+            }
+        }
+
+        features
+    }
+
+    /// The game drawn as it would be in the window, HUD and all, and
+    /// shrunk to `width` by `height` greyscale bytes. Each one is the
+    /// brightest pixel it covers so thin lines don't fade away
+    pub fn frame(&self, width: u32, height: u32) -> Vec<u8> {
+        let full = capture::frame(&self.stars, &self.game, 0);
+        let (full_width, full_height) = (full.width() as usize, full.height() as usize);
+        let pixels = full.pixels();
+        let (width, height) = (width.max(1) as usize, height.max(1) as usize);
+
+        let mut frame = Vec::with_capacity(width * height);
+        for y in 0..height {
+            for x in 0..width {
+                let mut brightest = 0;
+                for row in covered(y, height, full_height) {
+                    for column in covered(x, width, full_width) {
+                        let i = (row * full_width + column) * 4;
+                        let [r, g, b] = [pixels[i], pixels[i + 1], pixels[i + 2]].map(u32::from);
+                        brightest = brightest.max((r * 299 + g * 587 + b * 114) / 1000);
+                    }
+                }
+                frame.push(brightest as u8);
+            }
+        }
+
+        frame
+    }
+}
+
+/// Pixels of a row or column `size` long covered by the `i`th of `count`
+fn covered(i: usize, count: usize, size: usize) -> Range<usize> {
+    let start = i * size / count;
+    start..((i + 1) * size / count).max(start + 1)
+}
+
+/// One of `ACTIONS` numbered actions as controls, for agents that pick
+/// from a list. Numbers past the end wrap around
+pub fn action(index: usize) -> Input {
+    let index = index % ACTIONS;
+
+    Input {
+        left: index % 3 == 1,
+        right: index % 3 == 2,
+        thrust: index / 3 % 2 == 1,
+        fire: index / 6 % 2 == 1,
+        hyperspace: index / 12 == 1,
+        ..Input::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        entity::world::{Entity, Kind},
+        game::rules::{Difficulty, GameMode},
+        math::playfield::Playfield,
+    };
+
+    fn rules() -> Rules {
+        Rules {
+            difficulty: Difficulty::Normal,
+            mode: GameMode::Classic,
+            players: Players::Coop,
+            field: Playfield::new(800., 600.),
+        }
+    }
+
+    /// Clear the field for just these asteroids
+    fn only_asteroids(env: &mut Env, asteroids: Vec<Entity>) {
+        env.game
+            .world
+            .retain(|entity| !matches!(entity.kind, Kind::Asteroid { .. }));
+        for asteroid in asteroids {
+            env.game.world.spawn(asteroid);
+        }
+    }
+
+    fn episode(env: &mut Env, seed: u64) -> Vec<(Vec<f32>, f32, bool)> {
+        env.reset(seed);
+        (0..200)
+            .map(|i| {
+                let step = env.step(action(i * 7));
+                (step.observation.features, step.reward, step.done)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn same_seed_same_episode() {
+        let mut env = Env::new(rules(), EnvSettings::default());
+        let first = episode(&mut env, 4);
+        let again = episode(&mut env, 4);
+
+        assert!(first == again);
+        assert!(first
+            .iter()
+            .all(|(features, ..)| features.len() == env.feature_count()));
+        assert_eq!(env.game().players.len(), 1);
+    }
+
+    #[test]
+    fn asteroids_are_seen_across_the_edge() {
+        let settings = EnvSettings {
+            nearest: 2,
+            ..EnvSettings::default()
+        };
+        let mut env = Env::new(rules(), settings);
+        env.reset(0);
+
+        // One just over the left edge from the ship on the right, and one
+        // further away in a straight line
+        let square = |x: f32, y: f32| {
+            let corners = [(-5., -5.), (5., -5.), (5., 5.), (-5., 5.)];
+            asteroid::outline(
+                corners
+                    .iter()
+                    .map(|(dx, dy)| Vec2::new(x + dx, y + dy))
+                    .collect(),
+            )
+        };
+        env.game
+            .world
+            .retain(|entity| entity.kind.player() != Some(0));
+        env.game.world.spawn(ship::at(&Vec2::new(780., 300.), 0));
+        only_asteroids(&mut env, vec![square(500., 300.), square(20., 300.)]);
+
+        let features = env.features();
+        let nearest = &features[SHIP_FEATURES..SHIP_FEATURES + ASTEROID_FEATURES];
+        let next = &features[SHIP_FEATURES + ASTEROID_FEATURES..];
+        assert_eq!(nearest[0], 1.);
+        assert!((nearest[1] * 800. - 40.).abs() < 1.);
+        assert!((next[1] * 800. + 280.).abs() < 1.);
+    }
+
+    #[test]
+    fn losing_a_life_costs() {
+        let settings = EnvSettings {
+            frame_skip: 1,
+            rewards: Rewards {
+                point: 0.,
+                death: -10.,
+                ..Rewards::default()
+            },
+            ..EnvSettings::default()
+        };
+        let mut env = Env::new(rules(), settings);
+        env.reset(0);
+
+        let ship = env.game.ship(0).unwrap().center();
+        let corners = [(-30., -30.), (30., -30.), (30., 30.), (-30., 30.)];
+        let asteroid = corners
+            .iter()
+            .map(|(dx, dy)| Vec2::new(ship.x + dx, ship.y + dy));
+        only_asteroids(&mut env, vec![asteroid::outline(asteroid.collect())]);
+
+        let step = env.step(Input::default());
+        assert_eq!(step.reward, -10.);
+        assert_eq!(env.game().players[0].lives, 2);
+    }
+
+    #[test]
+    fn frames_are_shrunk() {
+        let settings = EnvSettings {
+            frame: Some((84, 63)),
+            ..EnvSettings::default()
+        };
+        let mut env = Env::new(rules(), settings);
+        let frame = env.reset(1).frame.unwrap();
+
+        assert_eq!(frame.len(), 84 * 63);
+        // The ship in the middle shows up
+        assert!(frame[31 * 84 + 40..31 * 84 + 44]
+            .iter()
+            .any(|&pixel| pixel > 0));
+    }
+}
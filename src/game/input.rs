@@ -0,0 +1,78 @@
+use serde::{Deserialize, Serialize};
+use std::f32::consts::TAU;
+
+/// A whole turn in the units of `aim` and `heading`
+const AIM_TURN: f32 = 65_536.;
+const HEADING_TURN: f32 = 256.;
+
+/// The controls held down during one tick
+#[derive(Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Input {
+    pub left: bool,
+    pub right: bool,
+    pub thrust: bool,
+    pub fire: bool,
+    #[serde(default)]
+    pub hyperspace: bool,
+    /// Analog steering from -127 for full left to 127 for full right,
+    /// used instead of left and right when it isn't 0
+    #[serde(default)]
+    pub turn: i8,
+    /// Twin-stick controls, where the ship turns to face `aim` and thrust
+    /// pushes it towards `heading` rather than the way it faces
+    #[serde(default)]
+    pub twin_stick: bool,
+    /// Angles clockwise from the right, in 65536ths and 256ths of a turn
+    #[serde(default)]
+    pub aim: u16,
+    #[serde(default)]
+    pub heading: u8,
+}
+
+impl Input {
+    /// Pack the buttons into the low byte, the steering into the next one
+    /// and the twin-stick angles above that for replays. Anything added
+    /// goes higher up, so older replays still read the same
+    pub fn to_bits(self) -> u64 {
+        self.left as u64
+            | (self.right as u64) << 1
+            | (self.thrust as u64) << 2
+            | (self.fire as u64) << 3
+            | (self.hyperspace as u64) << 4
+            | (self.twin_stick as u64) << 5
+            | (self.turn as u8 as u64) << 8
+            | (self.heading as u64) << 16
+            | (self.aim as u64) << 24
+    }
+
+    pub fn from_bits(bits: u64) -> Self {
+        Self {
+            left: bits & 1 != 0,
+            right: bits & 1 << 1 != 0,
+            thrust: bits & 1 << 2 != 0,
+            fire: bits & 1 << 3 != 0,
+            hyperspace: bits & 1 << 4 != 0,
+            twin_stick: bits & 1 << 5 != 0,
+            turn: (bits >> 8) as u8 as i8,
+            heading: (bits >> 16) as u8,
+            aim: (bits >> 24) as u16,
+        }
+    }
+
+    pub fn aim_angle(self) -> f32 {
+        self.aim as f32 / AIM_TURN * TAU
+    }
+
+    pub fn heading_angle(self) -> f32 {
+        self.heading as f32 / HEADING_TURN * TAU
+    }
+
+    /// Aim at an angle in radians, to the nearest step
+    pub fn set_aim(&mut self, angle: f32) {
+        self.aim = (angle.rem_euclid(TAU) / TAU * AIM_TURN).round() as u32 as u16;
+    }
+
+    pub fn set_heading(&mut self, angle: f32) {
+        self.heading = (angle.rem_euclid(TAU) / TAU * HEADING_TURN).round() as u32 as u8;
+    }
+}
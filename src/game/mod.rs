@@ -0,0 +1,621 @@
+pub mod input;
+pub mod rules;
+
+use crate::{
+    entity::{
+        asteroid,
+        component::Layer,
+        ship, system,
+        world::{Entity, Id, Kind, World},
+    },
+    math::{
+        functions::{get_random_radius, rand_angle},
+        playfield::Playfield,
+        rng::GameRng,
+        vec2::Vec2,
+    },
+};
+use input::Input;
+use rand::Rng;
+use rules::{GameMode, Players, Rules};
+use serde::{Deserialize, Serialize};
+
+/// Length of one fixed simulation step in seconds
+pub const TICK: f32 = 1. / 60.;
+const EXTRA_LIFE_SCORE: u32 = 1000;
+const ASTEROID_SCORE: u32 = 10;
+/// Score for shooting the other ship in versus
+const VERSUS_SCORE: u32 = 100;
+const MAX_ASTEROIDS: usize = 11;
+/// What can hit what, in the order they are worked out. Lasers only hit
+/// ships in versus
+const COLLISIONS: [(Layer, Layer); 3] = [
+    (Layer::Laser, Layer::Asteroid),
+    (Layer::Laser, Layer::Ship),
+    (Layer::Ship, Layer::Asteroid),
+];
+
+/// Something that happened during an update, for the window to play sounds for
+#[derive(Clone)]
+pub enum GameEvent {
+    Fired,
+    /// Shot by `player`, centred `at`
+    AsteroidDestroyed {
+        large: bool,
+        at: Vec2,
+        player: usize,
+    },
+    ShipDestroyed {
+        player: usize,
+    },
+    /// Back at the start after being destroyed
+    ShipSpawned {
+        player: usize,
+    },
+    ExtraLife,
+    Hyperspace,
+    GameOver,
+}
+
+/// How one player is getting on, their ship is in the world
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Player {
+    pub score: u32,
+    pub lives: u32,
+    next_extra_life: u32,
+}
+
+impl Player {
+    /// Out of lives, the ship is gone for the rest of the game
+    pub fn is_out(&self) -> bool {
+        self.lives == 0
+    }
+}
+
+/// Everything needed to carry on a game exactly where it was left
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Game {
+    /// What the game was started from, for anything alongside it that
+    /// needs to be random in the same way every time
+    pub seed: u64,
+    pub players: Vec<Player>,
+    /// Ships, lasers and asteroids
+    pub world: World,
+    pub wave: u32,
+    pub rules: Rules,
+    speed_max: f32,
+    rng: GameRng,
+    /// Collision tests done so far, for benchmarking. Not part of the game
+    /// so it isn't saved
+    #[serde(skip)]
+    collision_checks: u64,
+}
+
+impl Game {
+    /// A new game, the same seed and rules always give the same game
+    pub fn new(seed: u64, rules: Rules) -> Game {
+        Game::with_players(seed, rules, rules.players.count())
+    }
+
+    /// A game for more players than the rules would have, for games over
+    /// the network
+    pub fn with_players(seed: u64, rules: Rules, count: usize) -> Game {
+        let mut rng = GameRng::new(seed);
+        let speed_max = rules.difficulty.speed_max();
+
+        let mut world = World::default();
+        let players = (0..count)
+            .map(|i| {
+                world.spawn(ship::at(&spawn_point(&rules.field, i, count), i));
+                Player {
+                    score: 0,
+                    lives: rules.difficulty.start_lives(),
+                    next_extra_life: EXTRA_LIFE_SCORE,
+                }
+            })
+            .collect();
+        for asteroid in asteroid::new_vec(speed_max, &rules.field, &mut rng) {
+            world.spawn(asteroid);
+        }
+
+        Game {
+            seed,
+            players,
+            world,
+            wave: 1,
+            rules,
+            speed_max,
+            rng,
+            collision_checks: 0,
+        }
+    }
+
+    /// Versus ends when only one player is left, otherwise it ends when
+    /// everyone is out
+    pub fn is_over(&self) -> bool {
+        let playing = self.players.iter().filter(|p| !p.is_out()).count();
+        match self.rules.players {
+            Players::Versus => playing < 2,
+            Players::Single | Players::Coop => playing == 0,
+        }
+    }
+
+    /// Everyone's scores added up
+    pub fn score(&self) -> u32 {
+        self.players.iter().map(|p| p.score).sum()
+    }
+
+    /// Average velocity of the ships still playing, for the starfield
+    pub fn velocity(&self) -> Vec2 {
+        let ships: Vec<_> = self.ships().map(Entity::velocity).collect();
+        if ships.is_empty() {
+            return Vec2::new(0., 0.);
+        }
+
+        let n = ships.len() as f32;
+        Vec2::new(
+            ships.iter().map(|v| v.x).sum::<f32>() / n,
+            ships.iter().map(|v| v.y).sum::<f32>() / n,
+        )
+    }
+
+    /// How many times something was tested against something else for a
+    /// hit, since the game was made or loaded
+    pub fn collision_checks(&self) -> u64 {
+        self.collision_checks
+    }
+
+    pub fn is_thrusting(&self) -> bool {
+        self.ships().any(ship::is_thrusting)
+    }
+
+    /// Players who still have lives
+    pub fn playing(&self) -> impl Iterator<Item = &Player> {
+        self.players.iter().filter(|p| !p.is_out())
+    }
+
+    /// A player's ship, none once they are out
+    pub fn ship(&self, player: usize) -> Option<&Entity> {
+        self.world
+            .iter()
+            .find(|entity| entity.kind == Kind::Ship { player })
+    }
+
+    /// Ships of the players still playing, in player order
+    pub fn ships(&self) -> impl Iterator<Item = &Entity> {
+        (0..self.players.len()).filter_map(move |player| self.ship(player))
+    }
+
+    /// A player's lasers in flight, oldest first
+    pub fn lasers(&self, player: usize) -> impl Iterator<Item = &Entity> {
+        self.world
+            .iter()
+            .filter(move |entity| entity.kind == Kind::Laser { player })
+    }
+
+    pub fn asteroids(&self) -> impl Iterator<Item = &Entity> {
+        self.world
+            .iter()
+            .filter(|entity| matches!(entity.kind, Kind::Asteroid { .. }))
+    }
+
+    fn ship_mut(&mut self, player: usize) -> Option<&mut Entity> {
+        self.world
+            .iter_mut()
+            .find(|entity| entity.kind == Kind::Ship { player })
+    }
+
+    /// Advance the game by one fixed step with each player's controls,
+    /// anyone missing holds nothing
+    pub fn tick(&mut self, inputs: &[Input]) -> Vec<GameEvent> {
+        let mut events = Vec::new();
+
+        for i in 0..self.players.len() {
+            let input = inputs.get(i).copied().unwrap_or_default();
+            let ship = match self.ship_mut(i) {
+                Some(ship) => ship,
+                None => continue,
+            };
+
+            let laser = ship::apply(ship, input, TICK);
+            let jump = ship::wants_jump(ship, input);
+            if let Some(laser) = laser {
+                self.world.spawn(laser);
+                events.push(GameEvent::Fired);
+            }
+
+            // Anywhere at all, even on top of an asteroid
+            if jump {
+                let field = &self.rules.field;
+                let pos = Vec2::new(
+                    self.rng.gen_range(0.0..field.width),
+                    self.rng.gen_range(0.0..field.height),
+                );
+                if let Some(ship) = self.ship_mut(i) {
+                    ship::jump_to(ship, &pos);
+                }
+                events.push(GameEvent::Hyperspace);
+            }
+        }
+
+        self.update(TICK, &mut events);
+
+        events
+    }
+
+    fn update(&mut self, dt: f32, events: &mut Vec<GameEvent>) {
+        // spawn asteroid randomly with low chance
+        let spawn_chance = self.rules.difficulty.spawn_chance();
+        if self.rng.gen::<f32>() < spawn_chance && self.asteroids().count() < MAX_ASTEROIDS {
+            let field = &self.rules.field;
+            let (x, y) = get_random_radius(&mut self.rng, field);
+            let asteroid = asteroid::new(40, 100, x, y, self.speed_max, field, &mut self.rng);
+            self.world.spawn(asteroid);
+        }
+
+        for &(hitters, targets) in COLLISIONS.iter() {
+            if hitters == Layer::Laser
+                && targets == Layer::Ship
+                && self.rules.players != Players::Versus
+            {
+                continue;
+            }
+
+            for hitter in system::hitters(&self.world, hitters) {
+                let hit =
+                    system::first_hit(&self.world, hitter, targets, &mut self.collision_checks);
+                if let Some(target) = hit {
+                    if self.hit(hitter, target, events) {
+                        return;
+                    }
+                }
+            }
+        }
+
+        system::thrust(&mut self.world, dt);
+        system::movement(&mut self.world, dt);
+        system::lifetime(&mut self.world, dt);
+        system::wrap(&mut self.world, &self.rules.field);
+    }
+
+    /// Give a player points, or take them away if `points` is negative,
+    /// from outside the game's own rules like a mod
+    pub fn add_score(&mut self, player: usize, points: i64) -> Vec<GameEvent> {
+        let mut events = Vec::new();
+        if player >= self.players.len() {
+            return events;
+        }
+
+        if points >= 0 {
+            self.award(player, points.min(u32::MAX as i64) as u32, &mut events);
+        } else {
+            let score = &mut self.players[player].score;
+            *score = score.saturating_sub(points.unsigned_abs().min(u32::MAX as u64) as u32);
+        }
+        events
+    }
+
+    /// Destroy a player's ship as if it had hit an asteroid, from outside
+    /// the game's own rules like a mod
+    pub fn destroy_ship(&mut self, player: usize) -> Vec<GameEvent> {
+        let mut events = Vec::new();
+        if !self.is_over() && self.players.get(player).is_some_and(|p| !p.is_out()) {
+            self.destroy(player, &mut events);
+        }
+        events
+    }
+
+    /// Work out what happens when `hitter` hits `target`, returns true if
+    /// that ended the game
+    fn hit(&mut self, hitter: Id, target: Id, events: &mut Vec<GameEvent>) -> bool {
+        let (hitter_kind, target) = match (self.world.get(hitter), self.world.get(target)) {
+            (Some(hitter), Some(target)) => (hitter.kind, target),
+            _ => return false,
+        };
+
+        match (hitter_kind, target.kind) {
+            (Kind::Laser { player }, Kind::Asteroid { divided }) => {
+                events.push(GameEvent::AsteroidDestroyed {
+                    large: !divided,
+                    at: target.center(),
+                    player,
+                });
+                self.break_up(target.id());
+                self.world.despawn(hitter);
+                self.award(player, ASTEROID_SCORE, events);
+                false
+            }
+            (Kind::Laser { player }, Kind::Ship { player: shot }) => {
+                self.world.despawn(hitter);
+                self.award(player, VERSUS_SCORE, events);
+                self.destroy(shot, events)
+            }
+            (Kind::Ship { player }, Kind::Asteroid { .. }) => {
+                let asteroid = target.id();
+                if self.destroy(player, events) {
+                    return true;
+                }
+                // Taking the asteroid that hit us with it
+                self.break_up(asteroid);
+                false
+            }
+            _ => false,
+        }
+    }
+
+    /// Add to a player's score, with an extra life every so often
+    fn award(&mut self, player: usize, points: u32, events: &mut Vec<GameEvent>) {
+        let player = &mut self.players[player];
+        player.score = player.score.saturating_add(points);
+
+        if player.score >= player.next_extra_life {
+            player.lives += 1;
+            player.next_extra_life = player.next_extra_life.saturating_add(EXTRA_LIFE_SCORE);
+            events.push(GameEvent::ExtraLife);
+        }
+    }
+
+    /// Take a life from a player and respawn them if they have any left.
+    /// Returns true if that ended the game
+    fn destroy(&mut self, player: usize, events: &mut Vec<GameEvent>) -> bool {
+        events.push(GameEvent::ShipDestroyed { player });
+        // Their lasers go with them
+        self.world
+            .retain(|entity| entity.kind.player() != Some(player));
+        if self.rules.mode == GameMode::Classic {
+            self.players[player].lives -= 1;
+        }
+
+        if self.is_over() {
+            events.push(GameEvent::GameOver);
+            return true;
+        }
+
+        // Back where they started, unless they are out
+        if !self.players[player].is_out() {
+            let count = self.players.len();
+            let at = spawn_point(&self.rules.field, player, count);
+            self.world.spawn(ship::at(&at, player));
+            events.push(GameEvent::ShipSpawned { player });
+        }
+
+        false
+    }
+
+    /// Break an asteroid into smaller pieces, or destroy it if it already
+    /// is one. Clearing the field starts the next wave
+    fn break_up(&mut self, id: Id) {
+        self.speed_max += 0.1;
+
+        let asteroid = match self.world.despawn(id) {
+            Some(asteroid) => asteroid,
+            None => return,
+        };
+
+        let field = &self.rules.field;
+        if !asteroid::is_divided(&asteroid) {
+            let Vec2 { x, y } = asteroid.center();
+            for _ in 0..self.rng.gen_range(2..4) {
+                let mut piece = asteroid::new(20, 50, x, y, self.speed_max, field, &mut self.rng);
+                piece.kind = Kind::Asteroid { divided: true };
+                if let Some(velocity) = piece.velocity.as_mut() {
+                    velocity.heading = rand_angle(&mut self.rng);
+                }
+                self.world.spawn(piece);
+            }
+        }
+
+        if self.asteroids().next().is_none() {
+            for _ in 0..2 {
+                let (x, y) = get_random_radius(&mut self.rng, field);
+                let asteroid = asteroid::new(40, 100, x, y, self.speed_max, field, &mut self.rng);
+                self.world.spawn(asteroid);
+            }
+            self.wave += 1;
+        }
+    }
+}
+
+/// The middle for one player, otherwise spread out across the middle
+fn spawn_point(field: &Playfield, player: usize, count: usize) -> Vec2 {
+    if count == 1 {
+        return field.center();
+    }
+
+    Vec2::new(
+        field.width * (player + 1) as f32 / (count + 1) as f32,
+        field.height / 2.,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{entity::ship::laser, game::rules::Difficulty};
+
+    fn game(players: Players) -> Game {
+        let mut game = Game::new(
+            1,
+            Rules {
+                difficulty: Difficulty::Normal,
+                mode: GameMode::Classic,
+                players,
+                field: Playfield::new(800., 800.),
+            },
+        );
+        // Nothing in the way unless a test puts it there
+        game.world
+            .retain(|entity| !matches!(entity.kind, Kind::Asteroid { .. }));
+        game
+    }
+
+    /// A laser of `player`'s that hits whatever is at `at` next tick
+    fn shoot(game: &mut Game, player: usize, at: Vec2) {
+        game.world.spawn(laser::new(at, 0., player));
+    }
+
+    #[test]
+    fn versus_hits_score_and_cost_a_life() {
+        let mut game = game(Players::Versus);
+        let lives = game.players[1].lives;
+        shoot(&mut game, 1, Vec2::new(100., 100.));
+        shoot(&mut game, 1, Vec2::new(700., 100.));
+        let target = game.ship(1).unwrap().center();
+        shoot(&mut game, 0, target);
+
+        let events = game.tick(&[]);
+
+        assert!(events
+            .iter()
+            .any(|e| matches!(e, GameEvent::ShipDestroyed { player: 1 })));
+        assert_eq!(game.players[0].score, VERSUS_SCORE);
+        assert_eq!(game.players[0].lives, lives);
+        assert_eq!(game.players[1].score, 0);
+        assert_eq!(game.players[1].lives, lives - 1);
+        // The shot player's lasers go with their ship, as does the laser
+        // that hit it
+        assert_eq!(game.lasers(1).count(), 0);
+        assert_eq!(game.lasers(0).count(), 0);
+        assert!(game.ship(1).is_some());
+        assert!(!game.is_over());
+    }
+
+    #[test]
+    fn versus_ends_when_one_player_is_out() {
+        let mut game = game(Players::Versus);
+        game.players[1].lives = 1;
+        let target = game.ship(1).unwrap().center();
+        shoot(&mut game, 0, target);
+
+        let events = game.tick(&[]);
+
+        assert!(events.iter().any(|e| matches!(e, GameEvent::GameOver)));
+        assert!(game.is_over());
+        assert!(!game.players[0].is_out());
+    }
+
+    #[test]
+    fn coop_carries_on_until_everyone_is_out() {
+        let mut game = game(Players::Coop);
+        game.players[0].lives = 1;
+
+        let events = game.destroy_ship(0);
+        assert!(!events.iter().any(|e| matches!(e, GameEvent::GameOver)));
+        assert!(game.players[0].is_out());
+        assert!(!game.is_over());
+        assert!(game.ship(0).is_none());
+        assert_eq!(game.ships().count(), 1);
+        for _ in 0..60 {
+            game.tick(&[]);
+        }
+        assert!(!game.is_over());
+
+        game.players[1].lives = 1;
+        let events = game.destroy_ship(1);
+        assert!(events.iter().any(|e| matches!(e, GameEvent::GameOver)));
+        assert!(game.is_over());
+    }
+
+    #[test]
+    fn scores_and_extra_lives_are_each_players_own() {
+        let mut game = game(Players::Coop);
+        let lives = game.players[0].lives;
+        let (x, y) = (100., 100.);
+        let target = asteroid::new(
+            40,
+            100,
+            x,
+            y,
+            game.speed_max,
+            &game.rules.field,
+            &mut game.rng,
+        );
+        let target = game.world.spawn(target);
+        let at = game.world.get(target).unwrap().center();
+        shoot(&mut game, 1, at);
+
+        game.tick(&[]);
+        assert_eq!(game.players[0].score, 0);
+        assert_eq!(game.players[1].score, ASTEROID_SCORE);
+
+        game.add_score(0, (EXTRA_LIFE_SCORE - 1).into());
+        assert_eq!(game.players[0].lives, lives);
+        let events = game.add_score(0, 1);
+        assert!(events.iter().any(|e| matches!(e, GameEvent::ExtraLife)));
+        assert_eq!(game.players[0].lives, lives + 1);
+        assert_eq!(game.players[1].lives, lives);
+
+        // Player two's next extra life is still their first
+        game.add_score(1, (EXTRA_LIFE_SCORE - ASTEROID_SCORE).into());
+        assert_eq!(game.players[1].lives, lives + 1);
+        game.add_score(0, (EXTRA_LIFE_SCORE - 1).into());
+        assert_eq!(game.players[0].lives, lives + 1);
+        assert_eq!(game.score(), 3 * EXTRA_LIFE_SCORE - 1);
+    }
+
+    #[test]
+    fn lasers_only_hit_ships_in_versus() {
+        let mut game = game(Players::Coop);
+        let lives = game.players[1].lives;
+        let target = game.ship(1).unwrap().center();
+        shoot(&mut game, 0, target);
+
+        let events = game.tick(&[]);
+
+        assert!(!events
+            .iter()
+            .any(|e| matches!(e, GameEvent::ShipDestroyed { .. })));
+        assert_eq!(game.players[1].lives, lives);
+        assert_eq!(game.players[0].score, 0);
+        assert_eq!(game.lasers(0).count(), 1);
+    }
+
+    /// What each player presses on each tick of the same game every time
+    fn scripted(player: usize, tick: usize) -> Input {
+        let phase = tick / (20 + player * 7) % 4;
+        Input {
+            left: phase == 0,
+            thrust: phase == 1,
+            right: phase == 2,
+            fire: tick.is_multiple_of(9 + player),
+            hyperspace: tick % 700 == 350,
+            twin_stick: player == 1,
+            aim: (tick * 97 % 65536) as u16,
+            heading: (tick / 30 * 37 % 256) as u8,
+            ..Input::default()
+        }
+    }
+
+    #[test]
+    fn scripted_games_end_the_same_as_ever() {
+        // As they ended before ships, lasers and asteroids were entities
+        let expected = [
+            (Players::Single, 2007, vec![480], vec![0], 4, 3),
+            (Players::Coop, 1052, vec![220, 160], vec![0, 0], 5, 3),
+            (Players::Versus, 1017, vec![220, 160], vec![2, 0], 5, 4),
+        ];
+
+        for (players, ticks, scores, lives, wave, asteroids) in expected {
+            let rules = Rules {
+                difficulty: Difficulty::Normal,
+                mode: GameMode::Classic,
+                players,
+                field: Playfield::new(800., 800.),
+            };
+            let mut game = Game::new(11, rules);
+            let mut tick = 0;
+            while tick < 6000 && !game.is_over() {
+                let inputs: Vec<_> = (0..players.count()).map(|p| scripted(p, tick)).collect();
+                game.tick(&inputs);
+                tick += 1;
+            }
+
+            assert_eq!(tick, ticks);
+            let players: Vec<_> = game.players.iter().map(|p| (p.score, p.lives)).collect();
+            let expected: Vec<_> = scores.into_iter().zip(lives).collect();
+            assert_eq!(players, expected);
+            assert_eq!(game.wave, wave);
+            assert_eq!(game.asteroids().count(), asteroids);
+        }
+    }
+}
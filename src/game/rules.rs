@@ -0,0 +1,155 @@
+use crate::math::playfield::Playfield;
+use serde::{Deserialize, Serialize};
+use std::{fmt, str::FromStr};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Difficulty {
+    Easy,
+    Normal,
+    Hard,
+}
+
+impl Difficulty {
+    pub fn start_lives(self) -> u32 {
+        match self {
+            Difficulty::Easy => 5,
+            Difficulty::Normal => 3,
+            Difficulty::Hard => 2,
+        }
+    }
+
+    /// Starting upper bound on asteroid speed
+    pub fn speed_max(self) -> f32 {
+        match self {
+            Difficulty::Easy => 1.4,
+            Difficulty::Normal => 1.7,
+            Difficulty::Hard => 2.2,
+        }
+    }
+
+    /// Chance each tick of a new asteroid drifting in
+    pub fn spawn_chance(self) -> f32 {
+        match self {
+            Difficulty::Easy => 0.003,
+            Difficulty::Normal => 0.005,
+            Difficulty::Hard => 0.008,
+        }
+    }
+}
+
+impl FromStr for Difficulty {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "easy" => Ok(Difficulty::Easy),
+            "normal" => Ok(Difficulty::Normal),
+            "hard" => Ok(Difficulty::Hard),
+            _ => Err(format!(
+                "Unknown difficulty '{}', expected easy, normal or hard",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Difficulty {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GameMode {
+    /// Lose a life when hit, game over when they run out
+    Classic,
+    /// The ship always respawns, for practice
+    Endless,
+}
+
+impl FromStr for GameMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "classic" => Ok(GameMode::Classic),
+            "endless" => Ok(GameMode::Endless),
+            _ => Err(format!(
+                "Unknown game mode '{}', expected classic or endless",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for GameMode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+/// Who is playing
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Players {
+    Single,
+    /// Two ships clearing the same asteroids, each with their own score and
+    /// lives, until both are out
+    Coop,
+    /// Two ships that can shoot each other, until one is out
+    Versus,
+}
+
+impl Players {
+    pub fn count(self) -> usize {
+        match self {
+            Players::Single => 1,
+            Players::Coop | Players::Versus => 2,
+        }
+    }
+}
+
+impl FromStr for Players {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "single" => Ok(Players::Single),
+            "coop" => Ok(Players::Coop),
+            "versus" => Ok(Players::Versus),
+            _ => Err(format!(
+                "Unknown players '{}', expected single, coop or versus",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Players {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Players::Single => write!(f, "Single"),
+            Players::Coop => write!(f, "Co-op"),
+            Players::Versus => write!(f, "Versus"),
+        }
+    }
+}
+
+/// What kind of game is being played, fixed when it starts
+#[derive(Clone, Copy, Serialize, Deserialize)]
+pub struct Rules {
+    pub difficulty: Difficulty,
+    pub mode: GameMode,
+    pub players: Players,
+    pub field: Playfield,
+}
+
+impl Rules {
+    /// Whether a game can be played by these, for rules from anywhere they
+    /// could be wrong like a file or another machine
+    pub fn check(&self) -> Result<(), String> {
+        self.field.check().map_err(|e| format!("playfield {}", e))
+    }
+}
@@ -0,0 +1,277 @@
+use crate::{
+    cli::Options,
+    config::Config,
+    controls::bot::Autopilot,
+    entity::star::Starfield,
+    game::{input::Input, Game, TICK},
+    math::rng::GameRng,
+    mods::Mods,
+    net::{self, lobby::Lobby},
+    render::{
+        capture::{self, GifWriter},
+        phosphor::Phosphor,
+    },
+    storage::replay::Replay,
+};
+use std::{fs, thread, time::Duration};
+
+/// How long to keep sending at the end so the others get every input
+const FLUSH_TIME: Duration = Duration::from_secs(2);
+
+/// Run a game with no window or sound, taking input from a replay or the
+/// autopilot if one was given, until it ends or the tick limit is reached
+pub fn run(options: &Options, config: &Config) -> Result<(), String> {
+    if let Some(lobby) = options.lobby()? {
+        return online(options, config, lobby);
+    }
+
+    let playback = match &options.replay {
+        Some(path) => Some(Replay::load(path)?),
+        None => None,
+    };
+
+    let (seed, rules, inputs) = match &playback {
+        Some(replay) => (replay.seed, replay.rules, replay.inputs()),
+        None => (
+            options.seed.unwrap_or_else(rand::random),
+            config.rules(),
+            Vec::new(),
+        ),
+    };
+
+    let mut mods = Mods::load(&options.mods);
+    if let Some(replay) = &playback {
+        replay.check_mods(&mods.signature())?;
+    }
+    let mut game = Game::new(seed, rules);
+    mods.start(&mut game, seed);
+    let mut autopilots: Vec<_> = (0..rules.players.count())
+        .map(|_| Autopilot::default())
+        .collect();
+    let mut recording = Replay::new(seed, rules, mods.signature());
+
+    // Stars come from the seed too so exported frames are the same every run
+    let mut stars = Starfield::new(&rules.field, config.stars(), &mut GameRng::new(seed));
+
+    if let Some(dir) = &options.frames {
+        fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    }
+    let mut gif = match &options.gif {
+        Some(path) => {
+            let delay = (options.every as f32 * TICK * 100.).round() as u16;
+            Some(GifWriter::create(
+                path,
+                rules.field.width as u32,
+                rules.field.height as u32,
+                delay,
+            )?)
+        }
+        None => None,
+    };
+
+    let mut phosphor = if config.glow {
+        Some(Phosphor::new(
+            rules.field.width as u32,
+            rules.field.height as u32,
+        ))
+    } else {
+        None
+    };
+
+    let mut ticks = 0;
+    while ticks < options.ticks && !game.is_over() {
+        if ticks % options.every == 0 && (options.frames.is_some() || gif.is_some()) {
+            let mut frame = capture::frame(&stars, &game, 0);
+            mods.draw(&mut frame, &rules.field);
+            mods.draw_hud(&mut frame, &rules.field);
+            if let Some(phosphor) = phosphor.as_mut() {
+                phosphor.apply(&mut frame, options.every as f32 * TICK);
+            }
+            if let Some(dir) = &options.frames {
+                let path = dir.join(format!("frame-{:06}.png", ticks / options.every));
+                capture::save_png(&path, &frame)?;
+            }
+            if let Some(gif) = gif.as_mut() {
+                gif.push(&frame)?;
+            }
+        }
+
+        // Idle once the replay runs out
+        let input = match inputs.get(ticks as usize) {
+            Some(input) => input.clone(),
+            None if options.bot => autopilots
+                .iter_mut()
+                .enumerate()
+                .map(|(i, autopilot)| autopilot.input(&game, i))
+                .collect(),
+            None => vec![Input::default(); rules.players.count()],
+        };
+        recording.push(&input);
+        let mut events = game.tick(&input);
+        mods.tick(&mut game, &mut events);
+        stars.update(&game.velocity(), TICK, &rules.field);
+        ticks += 1;
+    }
+
+    println!("{}", report(seed, &game, ticks));
+
+    if let Some(path) = &options.record {
+        recording.save(path)?;
+    }
+
+    Ok(())
+}
+
+/// Play over the network holding nothing, or flown by the autopilot, for
+/// trying the connection out.
+/// The host starts once enough have joined, and everyone prints the same
+/// result if the games stayed in sync
+fn online(options: &Options, config: &Config, mut lobby: Lobby) -> Result<(), String> {
+    let host = lobby.is_host();
+    let mut status = String::new();
+    loop {
+        if lobby.poll()? || host && lobby.players() >= options.net_players as usize {
+            break;
+        }
+        if lobby.status() != status {
+            status = lobby.status();
+            eprintln!("{}", status.replace('\n', ", "));
+        }
+        thread::sleep(Duration::from_millis(1));
+    }
+
+    let seed = options.seed.unwrap_or_else(rand::random);
+    let (mut session, mut game) = lobby.start(seed, config.rules())?;
+    eprintln!("Playing as player {}", session.player() + 1);
+    let mut autopilot = Autopilot::default();
+
+    let ticks = options.ticks as usize;
+    while session.confirmed().1 < ticks && !session.confirmed().0.is_over() {
+        let waiting = if session.tick() < ticks {
+            let input = if options.bot {
+                autopilot.input(&game, session.player())
+            } else {
+                Input::default()
+            };
+            session.advance(&mut game, input)?.is_none()
+        } else {
+            session.pump()?;
+            true
+        };
+        if waiting {
+            thread::sleep(Duration::from_millis(1));
+        }
+    }
+    session.flush(FLUSH_TIME)?;
+    session.leave();
+
+    let (game, ticks) = session.confirmed();
+    println!(
+        "{}, checksum {:016x}, {} rollbacks",
+        report(session.seed(), game, ticks as u64),
+        net::checksum(game),
+        session.rollbacks
+    );
+
+    Ok(())
+}
+
+/// One line on how a game went
+fn report(seed: u64, game: &Game, ticks: u64) -> String {
+    let rules = &game.rules;
+    let result = match game.players.as_slice() {
+        [player] => format!(
+            "score {} wave {} lives {}",
+            player.score, game.wave, player.lives
+        ),
+        players => {
+            let players = players
+                .iter()
+                .enumerate()
+                .map(|(i, player)| {
+                    format!("P{} score {} lives {}", i + 1, player.score, player.lives)
+                })
+                .collect::<Vec<_>>()
+                .join(", ");
+            format!("{} {}, wave {}", rules.players, players, game.wave)
+        }
+    };
+    format!(
+        "seed {} {} {}: {} after {} ticks{}",
+        seed,
+        rules.difficulty,
+        rules.mode,
+        result,
+        ticks,
+        if game.is_over() { " (game over)" } else { "" }
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{game::rules::GameMode, storage::test_dir};
+    use clap::Parser;
+    use std::fs::File;
+
+    #[test]
+    fn every_nth_tick_is_saved() {
+        let dir = test_dir("headless");
+        let frames = dir.join("frames");
+        let gif = dir.join("run.gif");
+        let (frames_arg, gif_arg) = (frames.to_str().unwrap(), gif.to_str().unwrap());
+        let args = [
+            "asteroids",
+            "--headless",
+            "--seed",
+            "1",
+            "--ticks",
+            "10",
+            "--every",
+            "4",
+            "--frames",
+            frames_arg,
+            "--gif",
+            gif_arg,
+            // Somewhere without any mods
+            "--mods",
+            frames_arg,
+        ];
+        let options = Options::try_parse_from(args).unwrap();
+        // Endless so a crowded little field can't end it early
+        let config = Config {
+            field_width: 160,
+            field_height: 120,
+            mode: GameMode::Endless,
+            ..Config::default()
+        };
+        run(&options, &config).unwrap();
+
+        // Ticks 0, 4 and 8
+        let mut saved: Vec<_> = fs::read_dir(&frames)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name())
+            .collect();
+        saved.sort();
+        assert_eq!(
+            saved,
+            ["frame-000000.png", "frame-000001.png", "frame-000002.png"]
+        );
+        for name in saved {
+            let decoder = png::Decoder::new(File::open(frames.join(name)).unwrap());
+            let info = decoder.read_info().unwrap().info().clone();
+            assert_eq!((info.width, info.height), (160, 120));
+        }
+
+        let mut decoder = gif::DecodeOptions::new()
+            .read_info(File::open(&gif).unwrap())
+            .unwrap();
+        let mut count = 0;
+        while let Some(frame) = decoder.read_next_frame().unwrap() {
+            // Four ticks is a fifteenth of a second
+            assert_eq!(frame.delay, 7);
+            count += 1;
+        }
+        assert_eq!(count, 3);
+    }
+}
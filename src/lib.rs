@@ -0,0 +1,19 @@
+//! The game as a library, for driving it from other programs like agent
+//! training. `env::Env` is the place to start
+
+pub mod assets;
+pub mod audio;
+pub mod cli;
+pub mod config;
+pub mod controls;
+pub mod entity;
+pub mod env;
+pub mod game;
+pub mod headless;
+pub mod math;
+pub mod mods;
+pub mod net;
+pub mod render;
+pub mod sim;
+pub mod storage;
+pub mod stream;
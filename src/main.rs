@@ -1,9 +1,13 @@
-mod entity;
-mod math;
-mod render;
-
-use render::window::Win;
+use asteroids::{cli::Options, headless, render::window::Win};
+use clap::Parser;
 
 fn main() -> Result<(), String> {
-    Win::new()?.reset()
+    let options = Options::parse();
+    let config = options.config()?;
+
+    if options.headless {
+        return headless::run(&options, &config);
+    }
+
+    Win::new(&config)?.run(&options)
 }
@@ -1,17 +1,19 @@
-use crate::render::window::MID_SIZE;
+use crate::math::playfield::Playfield;
 use rand::Rng;
 
-pub fn rand_angle() -> f32 {
-    2. * std::f32::consts::PI * rand::thread_rng().gen::<f32>()
+pub fn rand_angle<R: Rng>(rng: &mut R) -> f32 {
+    2. * std::f32::consts::PI * rng.gen::<f32>()
 }
 
-pub fn get_random_radius() -> (f32, f32) {
-    let angle: f64 = rand::thread_rng().gen::<f64>() * std::f64::consts::PI * 2.;
+/// A random point on the ellipse touching the edges of the playfield
+pub fn get_random_radius<R: Rng>(rng: &mut R, field: &Playfield) -> (f32, f32) {
+    let angle: f64 = rng.gen::<f64>() * std::f64::consts::PI * 2.;
 
-    let r = MID_SIZE as f64;
+    let rx = field.width as f64 / 2.;
+    let ry = field.height as f64 / 2.;
 
-    let x = r * angle.cos() + MID_SIZE as f64;
-    let y = r * angle.sin() + MID_SIZE as f64;
+    let x = rx * angle.cos() + rx;
+    let y = ry * angle.sin() + ry;
 
     (x as f32, y as f32)
 }
@@ -1,2 +1,4 @@
 pub mod functions;
+pub mod playfield;
+pub mod rng;
 pub mod vec2;
@@ -0,0 +1,45 @@
+use super::vec2::Vec2;
+use serde::{Deserialize, Serialize};
+
+/// Size of the area the game is played in, in logical units that are
+/// scaled to fit the window
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Playfield {
+    pub width: f32,
+    pub height: f32,
+}
+
+impl Playfield {
+    pub fn new(width: f32, height: f32) -> Self {
+        Self { width, height }
+    }
+
+    /// Whether games can be played in it, saying which side is wrong if
+    /// not
+    pub fn check(&self) -> Result<(), String> {
+        for (side, size) in [("width", self.width), ("height", self.height)] {
+            if !(size.is_finite() && size > 0.) {
+                return Err(format!("{} has to be more than 0, not {}", side, size));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn center(&self) -> Vec2 {
+        Vec2::new(self.width / 2., self.height / 2.)
+    }
+
+    /// Whether a point is strictly inside the playfield
+    pub fn contains(&self, point: &Vec2) -> bool {
+        point.x > 0. && point.x < self.width && point.y > 0. && point.y < self.height
+    }
+
+    /// The shortest way from `from` to `to`, which can be across an edge
+    pub fn offset(&self, from: &Vec2, to: &Vec2) -> Vec2 {
+        let wrap = |d: f32, size: f32| d - size * (d / size).round();
+        Vec2::new(
+            wrap(to.x - from.x, self.width),
+            wrap(to.y - from.y, self.height),
+        )
+    }
+}
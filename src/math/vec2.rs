@@ -1,6 +1,7 @@
-use crate::render::window::SIZE;
+use crate::math::playfield::Playfield;
+use serde::{Deserialize, Serialize};
 
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Vec2 {
     pub x: f32,
     pub y: f32,
@@ -15,63 +16,22 @@ impl Vec2 {
         (self.x.powf(2.) + self.y.powf(2.)).sqrt()
     }
 
-    pub fn wrap_point(&mut self) {
+    pub fn wrap_point(&mut self, field: &Playfield) {
         if self.y < 0. {
-            self.y += SIZE;
+            self.y += field.height;
         }
-        if self.y > SIZE {
-            self.y -= SIZE;
+        if self.y > field.height {
+            self.y -= field.height;
         }
         if self.x < 0. {
-            self.x += SIZE;
+            self.x += field.width;
         }
-        if self.x > SIZE {
-            self.x -= SIZE;
+        if self.x > field.width {
+            self.x -= field.width;
         }
     }
 }
 
-pub trait UpdateVerts {
-    fn get_verts(&mut self) -> &mut Vec<Vec2>;
-    fn get_ghost_verts(&mut self) -> &mut Vec<Vec2>;
-    fn swap(&mut self);
-}
-
-pub fn wrap_verts<T: UpdateVerts>(main: &mut T) {
-    let mut dx = 0.;
-    let mut dy = 0.;
-
-    for Vec2 { x, y } in main.get_verts().iter() {
-        if y < &0. {
-            dy = SIZE;
-        }
-        if y > &SIZE {
-            dy = -SIZE;
-        }
-        if x < &0. {
-            dx = SIZE;
-        }
-        if x > &SIZE {
-            dx = -SIZE;
-        }
-    }
-
-    if (dx - dy).abs() > 0. {
-        for i in 0..main.get_ghost_verts().len() {
-            main.get_ghost_verts()[i].x = main.get_verts()[i].x + dx;
-            main.get_ghost_verts()[i].y = main.get_verts()[i].y + dy;
-        }
-    }
-
-    if main
-        .get_verts()
-        .iter()
-        .all(|f| (f.y < 0. || f.y > SIZE) || (f.x < 0. || f.x > SIZE))
-    {
-        main.swap();
-    }
-}
-
 pub trait Vec2Vec {
     fn get_center(&self) -> Vec2;
     fn rotate(&mut self, angle: f32);
@@ -79,7 +39,7 @@ pub trait Vec2Vec {
     fn collision(&self, point: &Vec2) -> bool;
 }
 
-impl Vec2Vec for Vec<Vec2> {
+impl Vec2Vec for [Vec2] {
     fn get_center(&self) -> Vec2 {
         let sum_x: f32 = self.iter().map(|f| f.x).sum();
         let sum_y: f32 = self.iter().map(|f| f.y).sum();
@@ -0,0 +1,118 @@
+use super::entity;
+use crate::math::rng::GameRng;
+use rand::Rng;
+use rhai::{Engine, Map, FLOAT, INT};
+use std::{cell::RefCell, convert::TryFrom, rc::Rc};
+
+/// Most steps a script gets for one hook before it is stopped, so a loop
+/// that never ends can't hang the game
+const MAX_OPERATIONS: u64 = 1_000_000;
+/// How deep expressions can nest, at the top level and in functions. Rhai
+/// has lower limits in debug builds, which is too low for spawning an
+/// entity in a hook
+const MAX_EXPR_DEPTHS: (usize, usize) = (64, 32);
+
+/// Something a script asked for, done once its hook has returned
+pub enum Command {
+    Score { player: usize, points: i64 },
+    Destroy { player: usize },
+    Hud { script: usize, text: String },
+    Spawn(Map),
+    Despawn(INT),
+}
+
+/// What the functions scripts call share with the game
+pub struct Shared {
+    pub commands: Vec<Command>,
+    /// The script whose hook is running
+    pub script: usize,
+    pub rng: GameRng,
+    next_id: INT,
+}
+
+impl Shared {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            commands: Vec::new(),
+            script: 0,
+            rng: GameRng::new(seed),
+            next_id: 0,
+        }
+    }
+}
+
+/// Players are numbered from 0 in scripts, anything else matches nobody
+fn player(player: INT) -> usize {
+    usize::try_from(player).unwrap_or(usize::MAX)
+}
+
+/// An engine with the game's functions registered for scripts to call
+pub fn engine(shared: &Rc<RefCell<Shared>>) -> Engine {
+    let mut engine = Engine::new();
+    engine.set_max_operations(MAX_OPERATIONS);
+    engine.set_max_expr_depths(MAX_EXPR_DEPTHS.0, MAX_EXPR_DEPTHS.1);
+
+    let s = shared.clone();
+    engine.register_fn("add_score", move |p: INT, points: INT| {
+        s.borrow_mut().commands.push(Command::Score {
+            player: player(p),
+            points,
+        })
+    });
+
+    let s = shared.clone();
+    engine.register_fn("destroy_ship", move |p: INT| {
+        s.borrow_mut()
+            .commands
+            .push(Command::Destroy { player: player(p) })
+    });
+
+    let s = shared.clone();
+    engine.register_fn("hud", move |text: &str| {
+        let mut shared = s.borrow_mut();
+        let script = shared.script;
+        shared.commands.push(Command::Hud {
+            script,
+            text: text.to_string(),
+        })
+    });
+
+    let s = shared.clone();
+    engine.register_fn("spawn_entity", move |fields: Map| -> INT {
+        let mut shared = s.borrow_mut();
+        shared.next_id += 1;
+        let id = shared.next_id;
+        let entity = entity::new(fields, id, shared.script);
+        shared.commands.push(Command::Spawn(entity));
+        id
+    });
+
+    let s = shared.clone();
+    engine.register_fn("despawn_entity", move |id: INT| {
+        s.borrow_mut().commands.push(Command::Despawn(id))
+    });
+
+    // From the game's seed, so replays of modded games play out the same
+    let s = shared.clone();
+    engine.register_fn("random", move || -> FLOAT { s.borrow_mut().rng.gen() });
+    let s = shared.clone();
+    engine.register_fn("random", move |min: FLOAT, max: FLOAT| -> FLOAT {
+        // rand panics on ranges too wide to hold in a float, as well as on
+        // ones that aren't ranges at all
+        if min < max && min.is_finite() && max.is_finite() && (max - min).is_finite() {
+            s.borrow_mut().rng.gen_range(min..max)
+        } else {
+            min
+        }
+    });
+    let s = shared.clone();
+    engine.register_fn("random", move |min: INT, max: INT| -> INT {
+        if min < max {
+            s.borrow_mut().rng.gen_range(min..max)
+        } else {
+            min
+        }
+    });
+
+    engine
+}
@@ -0,0 +1,201 @@
+//! Entities scripts spawn are plain maps, so scripts can read and change
+//! any field and keep their own in them too. These are the fields the game
+//! looks at:
+//!
+//! - `x`, `y`: the centre, which can be just past an edge while the
+//!   entity is going over it
+//! - `vx`, `vy`: velocity in units a second
+//! - `angle`, `spin`: rotation in radians, and radians a second
+//! - `shape`: corners around the centre as `[x, y]` pairs
+//! - `color`: `[r, g, b]`, white if left out
+//! - `life`: seconds left before it goes, forever if left out
+//! - `deadly`: destroys ships that touch it
+//! - `shootable`: lasers destroy it, scoring `points` for the shooter
+//! - `update`: name of a function in the script to call every tick, with
+//!   the entity as `this`
+
+use crate::{
+    entity::{
+        component::{Collider, Layer, Lifetime, Renderable, Shape, Transform, Velocity, Wrap},
+        world::{Entity, Kind},
+    },
+    game::TICK,
+    math::vec2::{Vec2, Vec2Vec},
+};
+use rhai::{Array, Dynamic, Map, FLOAT, INT};
+use std::convert::TryFrom;
+
+/// Fill in what a script left out of a new entity
+pub fn new(mut entity: Map, id: INT, script: usize) -> Map {
+    for key in ["x", "y", "vx", "vy", "angle", "spin"] {
+        entity
+            .entry(key.into())
+            .or_insert_with(|| Dynamic::from_float(0.));
+    }
+    for key in ["deadly", "shootable"] {
+        entity.entry(key.into()).or_insert(Dynamic::FALSE);
+    }
+    entity.entry("points".into()).or_insert(Dynamic::ZERO);
+    entity.insert("id".into(), id.into());
+    entity.insert("script".into(), (script as INT).into());
+
+    entity
+}
+
+fn to_f32(value: &Dynamic) -> Option<f32> {
+    match value.as_float() {
+        Ok(value) => Some(value as f32),
+        Err(_) => value.as_int().ok().map(|value| value as f32),
+    }
+}
+
+/// A number field, 0 if it isn't one
+pub fn number(entity: &Map, key: &str) -> f32 {
+    entity.get(key).and_then(to_f32).unwrap_or(0.)
+}
+
+pub fn int(entity: &Map, key: &str) -> INT {
+    entity
+        .get(key)
+        .and_then(|value| value.as_int().ok())
+        .unwrap_or(0)
+}
+
+pub fn flag(entity: &Map, key: &str) -> bool {
+    entity
+        .get(key)
+        .and_then(|value| value.as_bool().ok())
+        .unwrap_or(false)
+}
+
+pub fn text(entity: &Map, key: &str) -> String {
+    entity
+        .get(key)
+        .and_then(|value| value.clone().into_string().ok())
+        .unwrap_or_default()
+}
+
+/// The script that spawned it
+pub fn script(entity: &Map) -> usize {
+    usize::try_from(int(entity, "script")).unwrap_or(usize::MAX)
+}
+
+pub fn center(entity: &Map) -> Vec2 {
+    Vec2::new(number(entity, "x"), number(entity, "y"))
+}
+
+/// Corners of the shape around the centre, unturned
+fn shape(entity: &Map) -> Vec<Vec2> {
+    let shape = match entity
+        .get("shape")
+        .and_then(|shape| shape.read_lock::<Array>())
+    {
+        Some(shape) => shape.clone(),
+        None => return Vec::new(),
+    };
+    shape
+        .iter()
+        .filter_map(|corner| {
+            let corner = corner.read_lock::<Array>()?;
+            Some(Vec2::new(to_f32(corner.first()?)?, to_f32(corner.get(1)?)?))
+        })
+        .collect()
+}
+
+/// `corner` turned by `angle` around the centre
+fn turn(corner: &Vec2, angle: f32) -> Vec2 {
+    let (sin, cos) = angle.sin_cos();
+    Vec2::new(
+        corner.x * cos - corner.y * sin,
+        corner.x * sin + corner.y * cos,
+    )
+}
+
+fn color(entity: &Map) -> [u8; 3] {
+    let rgb = entity
+        .get("color")
+        .and_then(|color| color.read_lock::<Array>());
+    match rgb.as_deref().map(Vec::as_slice) {
+        Some([r, g, b]) => {
+            let channel = |value: &Dynamic| to_f32(value).unwrap_or(255.).clamp(0., 255.) as u8;
+            [channel(r), channel(g), channel(b)]
+        }
+        _ => [255, 255, 255],
+    }
+}
+
+/// The entity the `script`th mod's map describes, for the systems to move,
+/// wrap and draw. One without a shape is a point that can't be seen or
+/// shot, but is still deadly
+pub fn build(entity: &Map, script: usize) -> Entity {
+    let center = center(entity);
+    let angle = number(entity, "angle");
+    let shape = shape(entity);
+
+    let (deadly, shootable) = (flag(entity, "deadly"), flag(entity, "shootable"));
+    let kind = Kind::Scripted {
+        script,
+        deadly,
+        shootable,
+    };
+    let mut built = if shape.is_empty() {
+        let mut point = Entity::new(kind, Transform::point(center), Shape::Point);
+        point.wrap = Some(Wrap::Point);
+        point
+    } else {
+        let verts = shape
+            .iter()
+            .map(|corner| {
+                let turned = turn(corner, angle);
+                Vec2::new(center.x + turned.x, center.y + turned.y)
+            })
+            .collect();
+        let mut polygon = Entity::new(kind, Transform::new(verts), Shape::Polygon);
+        polygon.wrap = Some(Wrap::Ghost { settle: true });
+        polygon.renderable = Some(Renderable::Stroke {
+            color: color(entity),
+        });
+        polygon
+    };
+
+    let velocity = Vec2::new(number(entity, "vx"), number(entity, "vy"));
+    built.velocity = Some(Velocity {
+        spin: Some(number(entity, "spin")),
+        ..Velocity::of(&velocity)
+    });
+    if deadly || shootable {
+        built.collider = Some(Collider {
+            layer: Layer::Scripted,
+        });
+    }
+    built.lifetime = entity
+        .get("life")
+        .and_then(to_f32)
+        .map(|left| Lifetime::Timer { left });
+
+    built
+}
+
+/// Put where a tick of the systems has moved `built` to back in the
+/// script's map
+pub fn sync(entity: &mut Map, built: &Entity) {
+    let angle = number(entity, "angle") + number(entity, "spin") * TICK;
+    // The outline turns around the middle of its corners, which needn't be
+    // where the script put the centre
+    let shape = shape(entity);
+    let center = match built.shape {
+        Shape::Polygon if !shape.is_empty() => {
+            let middle = turn(&shape.get_center(), angle);
+            let moved = built.center();
+            Vec2::new(moved.x - middle.x, moved.y - middle.y)
+        }
+        _ => built.center(),
+    };
+
+    entity.insert("x".into(), Dynamic::from_float(center.x as FLOAT));
+    entity.insert("y".into(), Dynamic::from_float(center.y as FLOAT));
+    entity.insert("angle".into(), Dynamic::from_float(angle as FLOAT));
+    if let Some(Lifetime::Timer { left }) = built.lifetime {
+        entity.insert("life".into(), Dynamic::from_float(left as FLOAT));
+    }
+}
@@ -0,0 +1,649 @@
+//! Scripts in Rhai that change how a game plays, loaded from a folder of
+//! `.rhai` files. Each script can have any of these hooks, called with the
+//! script's own map as `this` so it can keep state between them:
+//!
+//! - `on_start(game)`
+//! - `on_tick(game)`
+//! - `on_asteroid_destroyed(game, asteroid)`
+//! - `on_ship_spawned(game, player)`
+//! - `on_ship_destroyed(game, player)`
+//! - `on_entity_destroyed(game, entity, player)`
+
+pub mod api;
+pub mod entity;
+
+use crate::{
+    entity::{
+        asteroid,
+        component::Layer,
+        ship, system,
+        world::{Entity, Id, Kind, World},
+    },
+    game::{Game, GameEvent, TICK},
+    math::{playfield::Playfield, vec2::Vec2},
+    render::{
+        renderer::Renderer,
+        text::{Anchor, Label},
+    },
+    storage,
+};
+use api::{Command, Shared};
+use rhai::{Array, CallFnOptions, Dynamic, Engine, Map, Scope, AST, FLOAT, INT};
+use sdl2::pixels::Color;
+use std::{
+    cell::RefCell,
+    collections::{BTreeMap, HashSet},
+    fs, mem,
+    path::Path,
+    rc::Rc,
+};
+
+const HUD_SIZE: f32 = 18.;
+
+struct Script {
+    name: String,
+    /// Of the source, so a replay can tell it is the same script
+    hash: u64,
+    ast: AST,
+    /// Functions it has, so hooks it leaves out are skipped
+    hooks: HashSet<String>,
+    /// Its `this` in hooks
+    state: Dynamic,
+    hud: String,
+    /// Stopped after an error
+    failed: bool,
+}
+
+impl Script {
+    /// Call `hook` if the script has one, with its state as `this`
+    fn hook(&mut self, engine: &Engine, hook: &str, args: Vec<Dynamic>) {
+        if self.failed || !self.hooks.contains(hook) {
+            return;
+        }
+
+        let mut state = mem::take(&mut self.state);
+        self.call(engine, hook, &mut state, args);
+        self.state = state;
+    }
+
+    fn call(&mut self, engine: &Engine, name: &str, this: &mut Dynamic, args: Vec<Dynamic>) {
+        let options = CallFnOptions::new().eval_ast(false).bind_this_ptr(this);
+        let result = engine.call_fn_with_options::<Dynamic>(
+            options,
+            &mut Scope::new(),
+            &self.ast,
+            name,
+            args,
+        );
+
+        if let Err(e) = result {
+            eprintln!("Mod {} stopped: {}", self.name, e);
+            self.failed = true;
+        }
+    }
+}
+
+/// The loaded scripts and everything they have spawned
+pub struct Mods {
+    engine: Engine,
+    shared: Rc<RefCell<Shared>>,
+    scripts: Vec<Script>,
+    /// What the scripts have spawned. It is kept out of the game's world as
+    /// saves and replays don't have it
+    world: World,
+    /// Each entity in the world as its script sees it
+    maps: BTreeMap<Id, Map>,
+    /// Made by the game and not seen by the scripts yet
+    pending: Vec<GameEvent>,
+    ticks: u64,
+}
+
+impl Default for Mods {
+    /// No mods, the game as it comes
+    fn default() -> Self {
+        let shared = Rc::new(RefCell::new(Shared::new(0)));
+        Self {
+            engine: api::engine(&shared),
+            shared,
+            scripts: Vec::new(),
+            world: World::default(),
+            maps: BTreeMap::new(),
+            pending: Vec::new(),
+            ticks: 0,
+        }
+    }
+}
+
+impl Mods {
+    /// Every `.rhai` file in `dir`, in name order. Scripts that don't
+    /// compile are left out, and no folder means no mods
+    pub fn load(dir: &Path) -> Mods {
+        let mut mods = Mods::default();
+        let mut paths: Vec<_> = match fs::read_dir(dir) {
+            Ok(entries) => entries
+                .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                .filter(|path| path.extension().is_some_and(|ext| ext == "rhai"))
+                .collect(),
+            Err(_) => return mods,
+        };
+        paths.sort();
+
+        for path in paths {
+            let name = path.file_stem().unwrap_or_default().to_string_lossy();
+            let loaded = fs::read_to_string(&path)
+                .map_err(|e| e.to_string())
+                .and_then(|source| mods.add(&name, &source));
+            match loaded {
+                Ok(()) => eprintln!("Loaded mod {}", name),
+                Err(e) => eprintln!("Couldn't load mod {}: {}", name, e),
+            }
+        }
+        mods
+    }
+
+    /// Compile a script and add it after the others
+    pub fn add(&mut self, name: &str, source: &str) -> Result<(), String> {
+        let ast = self.engine.compile(source).map_err(|e| e.to_string())?;
+        let hooks = ast.iter_functions().map(|f| f.name.to_string()).collect();
+
+        self.scripts.push(Script {
+            name: name.to_string(),
+            hash: storage::fnv1a(source.as_bytes()),
+            ast,
+            hooks,
+            state: Dynamic::UNIT,
+            hud: String::new(),
+            failed: false,
+        });
+        Ok(())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.scripts.is_empty()
+    }
+
+    /// The name and source hash of every script in order, for telling
+    /// whether a replay was recorded with the same mods
+    pub fn signature(&self) -> Vec<(String, u64)> {
+        self.scripts
+            .iter()
+            .map(|script| (script.name.clone(), script.hash))
+            .collect()
+    }
+
+    /// Start the scripts over for a new game. Their random numbers come
+    /// from `seed` so a replay plays out the same
+    pub fn start(&mut self, game: &mut Game, seed: u64) {
+        if self.is_empty() {
+            return;
+        }
+
+        *self.shared.borrow_mut() = Shared::new(seed);
+        self.world = World::default();
+        self.maps.clear();
+        self.pending.clear();
+        self.ticks = 0;
+        for script in self.scripts.iter_mut() {
+            script.state = Dynamic::from_map(Map::new());
+            script.hud.clear();
+            script.failed = false;
+        }
+
+        let view = view(game, 0);
+        self.hook("on_start", || vec![view.clone()]);
+        for player in 0..game.players.len() {
+            self.hook("on_ship_spawned", || {
+                vec![view.clone(), (player as INT).into()]
+            });
+        }
+        let mut events = Vec::new();
+        self.apply(game, &mut events);
+        self.pending = events;
+    }
+
+    /// Run the scripts for the tick the game just had, which made
+    /// `events`. Anything the scripts do to the game adds to them
+    pub fn tick(&mut self, game: &mut Game, events: &mut Vec<GameEvent>) {
+        if self.is_empty() || game.is_over() {
+            return;
+        }
+        self.ticks += 1;
+        let view = view(game, self.ticks);
+
+        // What scripts did last tick first, then what the game did
+        let happened: Vec<_> = self
+            .pending
+            .drain(..)
+            .chain(events.iter().cloned())
+            .collect();
+        for event in happened {
+            match event {
+                GameEvent::AsteroidDestroyed { large, at, player } => {
+                    let mut asteroid = Map::new();
+                    asteroid.insert("x".into(), (at.x as FLOAT).into());
+                    asteroid.insert("y".into(), (at.y as FLOAT).into());
+                    asteroid.insert("large".into(), large.into());
+                    asteroid.insert("player".into(), (player as INT).into());
+                    let asteroid = Dynamic::from_map(asteroid);
+                    self.hook("on_asteroid_destroyed", || {
+                        vec![view.clone(), asteroid.clone()]
+                    });
+                }
+                GameEvent::ShipDestroyed { player } => self.hook("on_ship_destroyed", || {
+                    vec![view.clone(), (player as INT).into()]
+                }),
+                GameEvent::ShipSpawned { player } => self.hook("on_ship_spawned", || {
+                    vec![view.clone(), (player as INT).into()]
+                }),
+                _ => {}
+            }
+        }
+        self.hook("on_tick", || vec![view.clone()]);
+
+        self.update_entities(&game.rules.field);
+        self.collide(game, &view);
+
+        let mut made = Vec::new();
+        self.apply(game, &mut made);
+        self.pending.extend(made.iter().cloned());
+        events.extend(made);
+    }
+
+    /// Call `hook` on every script that has it
+    fn hook(&mut self, hook: &str, args: impl Fn() -> Vec<Dynamic>) {
+        for (i, script) in self.scripts.iter_mut().enumerate() {
+            self.shared.borrow_mut().script = i;
+            script.hook(&self.engine, hook, args());
+        }
+    }
+
+    /// Move every entity and run their own update functions
+    fn update_entities(&mut self, field: &Playfield) {
+        system::movement(&mut self.world, TICK);
+        system::lifetime(&mut self.world, TICK);
+        system::wrap(&mut self.world, field);
+
+        // A stopped script's entities go with it
+        let scripts = &self.scripts;
+        self.world.retain(|entity| match entity.kind {
+            Kind::Scripted { script, .. } => scripts.get(script).is_some_and(|s| !s.failed),
+            _ => false,
+        });
+        let world = &self.world;
+        self.maps.retain(|id, _| world.get(*id).is_some());
+
+        let ids: Vec<Id> = self.world.iter().map(Entity::id).collect();
+        for id in ids {
+            let (built, mut map) = match (self.world.get(id), self.maps.remove(&id)) {
+                (Some(built), Some(map)) => (built, map),
+                _ => continue,
+            };
+            entity::sync(&mut map, built);
+
+            let update = entity::text(&map, "update");
+            let index = entity::script(&map);
+            let script = match self.scripts.get_mut(index) {
+                Some(script) if !update.is_empty() => script,
+                _ => {
+                    self.maps.insert(id, map);
+                    continue;
+                }
+            };
+
+            self.shared.borrow_mut().script = index;
+            let mut this = Dynamic::from_map(map);
+            script.call(&self.engine, &update, &mut this, Vec::new());
+            // Unless the script put something else in `this`
+            match this.try_cast::<Map>() {
+                Some(map) if map.contains_key("id") => {
+                    self.world.replace(id, entity::build(&map, index));
+                    self.maps.insert(id, map);
+                }
+                _ => {
+                    self.world.despawn(id);
+                }
+            }
+        }
+    }
+
+    /// Lasers against shootable entities, and deadly entities against ships
+    fn collide(&mut self, game: &mut Game, view: &Dynamic) {
+        let mut destroyed = Vec::new();
+
+        for p in 0..game.players.len() {
+            // Newest first, as the game does
+            let lasers: Vec<_> = game.lasers(p).map(Entity::id).collect();
+            for &id in lasers.iter().rev() {
+                let laser = match game.world.get(id) {
+                    Some(laser) => laser,
+                    None => continue,
+                };
+                let hit = self.hit(|entity| {
+                    matches!(
+                        entity.kind,
+                        Kind::Scripted {
+                            shootable: true,
+                            ..
+                        }
+                    ) && system::touches(laser, entity)
+                });
+                if let Some(entity) = hit {
+                    game.world.despawn(id);
+                    let points = entity::int(&entity, "points");
+                    self.shared
+                        .borrow_mut()
+                        .commands
+                        .push(Command::Score { player: p, points });
+                    destroyed.push((entity, p));
+                }
+            }
+
+            let ship = match game.ship(p) {
+                Some(ship) => ship,
+                None => continue,
+            };
+            let hit = self.hit(|entity| {
+                matches!(entity.kind, Kind::Scripted { deadly: true, .. })
+                    && (system::touches(ship, entity) || system::touches(entity, ship))
+            });
+            if let Some(entity) = hit {
+                // Taking the entity with it, like an asteroid
+                self.shared
+                    .borrow_mut()
+                    .commands
+                    .push(Command::Destroy { player: p });
+                destroyed.push((entity, p));
+            }
+        }
+
+        for (entity, player) in destroyed {
+            if let Some(script) = self.scripts.get_mut(entity::script(&entity)) {
+                self.shared.borrow_mut().script = entity::script(&entity);
+                let args = vec![
+                    view.clone(),
+                    Dynamic::from_map(entity),
+                    (player as INT).into(),
+                ];
+                script.hook(&self.engine, "on_entity_destroyed", args);
+            }
+        }
+    }
+
+    /// Remove the first entity that can be hit and `touches`, returning its
+    /// map
+    fn hit(&mut self, touches: impl Fn(&Entity) -> bool) -> Option<Map> {
+        let id = self
+            .world
+            .iter()
+            .filter(|entity| entity.on_layer(Layer::Scripted))
+            .find(|entity| touches(entity))
+            .map(Entity::id)?;
+
+        self.world.despawn(id);
+        self.maps.remove(&id)
+    }
+
+    /// Do what the scripts asked for
+    fn apply(&mut self, game: &mut Game, events: &mut Vec<GameEvent>) {
+        let commands = mem::take(&mut self.shared.borrow_mut().commands);
+
+        for command in commands {
+            match command {
+                Command::Score { player, points } => events.extend(game.add_score(player, points)),
+                Command::Destroy { player } => events.extend(game.destroy_ship(player)),
+                Command::Hud { script, text } => {
+                    if let Some(script) = self.scripts.get_mut(script) {
+                        script.hud = text;
+                    }
+                }
+                Command::Spawn(map) => {
+                    let id = self.world.spawn(entity::build(&map, entity::script(&map)));
+                    self.maps.insert(id, map);
+                }
+                Command::Despawn(id) => {
+                    let found = self
+                        .maps
+                        .iter()
+                        .find(|(_, map)| entity::int(map, "id") == id)
+                        .map(|(&found, _)| found);
+                    if let Some(found) = found {
+                        self.world.despawn(found);
+                        self.maps.remove(&found);
+                    }
+                }
+            }
+        }
+    }
+
+    pub fn draw(&self, renderer: &mut dyn Renderer, field: &Playfield) {
+        system::render(&self.world, renderer, field, |_| Color::WHITE);
+    }
+
+    /// Text the scripts have put up, along the bottom
+    pub fn draw_hud(&self, renderer: &mut dyn Renderer, field: &Playfield) {
+        let hud: Vec<&str> = self
+            .scripts
+            .iter()
+            .map(|script| script.hud.as_str())
+            .filter(|hud| !hud.is_empty())
+            .collect();
+        if !hud.is_empty() {
+            Label::new(&hud.join("\n"), Anchor::Bottom)
+                .size(HUD_SIZE)
+                .draw(renderer, field);
+        }
+    }
+}
+
+/// The game as scripts see it, a copy they can read but not change
+fn view(game: &Game, tick: u64) -> Dynamic {
+    let field = &game.rules.field;
+
+    let players: Array = game
+        .players
+        .iter()
+        .enumerate()
+        .map(|(i, player)| {
+            // Once out there is no ship, it reads as still in the middle
+            let (center, velocity, facing) = match game.ship(i) {
+                Some(ship) => (ship.center(), ship.velocity(), ship::facing(ship)),
+                None => (field.center(), Vec2::new(0., 0.), 0.),
+            };
+            let mut map = Map::new();
+            map.insert("x".into(), (center.x as FLOAT).into());
+            map.insert("y".into(), (center.y as FLOAT).into());
+            map.insert("angle".into(), (facing as FLOAT).into());
+            map.insert("vx".into(), (velocity.x as FLOAT).into());
+            map.insert("vy".into(), (velocity.y as FLOAT).into());
+            map.insert("score".into(), (player.score as INT).into());
+            map.insert("lives".into(), (player.lives as INT).into());
+            map.insert("out".into(), player.is_out().into());
+            Dynamic::from_map(map)
+        })
+        .collect();
+
+    let asteroids: Array = game
+        .asteroids()
+        .map(|asteroid| {
+            let (center, velocity) = (asteroid.center(), asteroid.velocity());
+            let mut map = Map::new();
+            map.insert("x".into(), (center.x as FLOAT).into());
+            map.insert("y".into(), (center.y as FLOAT).into());
+            map.insert("vx".into(), (velocity.x as FLOAT).into());
+            map.insert("vy".into(), (velocity.y as FLOAT).into());
+            map.insert(
+                "radius".into(),
+                (asteroid::radius(asteroid) as FLOAT).into(),
+            );
+            map.insert("large".into(), (!asteroid::is_divided(asteroid)).into());
+            Dynamic::from_map(map)
+        })
+        .collect();
+
+    let mut map = Map::new();
+    map.insert("tick".into(), (tick as INT).into());
+    map.insert("wave".into(), (game.wave as INT).into());
+    map.insert("score".into(), (game.score() as INT).into());
+    map.insert("width".into(), (field.width as FLOAT).into());
+    map.insert("height".into(), (field.height as FLOAT).into());
+    map.insert("players".into(), players.into());
+    map.insert("asteroids".into(), asteroids.into());
+    Dynamic::from_map(map)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        entity::world::Kind,
+        game::{
+            input::Input,
+            rules::{Difficulty, GameMode, Players, Rules},
+        },
+    };
+
+    fn game() -> Game {
+        let rules = Rules {
+            difficulty: Difficulty::Normal,
+            mode: GameMode::Classic,
+            players: Players::Single,
+            field: Playfield::new(800., 600.),
+        };
+        let mut game = Game::new(0, rules);
+        game.world
+            .retain(|entity| !matches!(entity.kind, Kind::Asteroid { .. }));
+        game
+    }
+
+    fn mods(source: &str) -> Mods {
+        let mut mods = Mods::default();
+        mods.add("test", source).unwrap();
+        mods
+    }
+
+    fn run(mods: &mut Mods, game: &mut Game, ticks: usize, input: Input) {
+        for _ in 0..ticks {
+            let mut events = game.tick(&[input]);
+            mods.tick(game, &mut events);
+        }
+    }
+
+    #[test]
+    fn hooks_change_the_score_and_hud() {
+        let mut mods = mods(
+            r#"
+            fn on_start(game) { this.ticks = 0; }
+            fn on_tick(game) {
+                this.ticks += 1;
+                if game.tick % 10 == 0 { add_score(0, 5); }
+                hud(`Ticks ${this.ticks}`);
+            }
+            "#,
+        );
+        let mut game = game();
+        mods.start(&mut game, 0);
+        run(&mut mods, &mut game, 30, Input::default());
+
+        assert_eq!(game.score(), 15);
+        assert_eq!(mods.scripts[0].hud, "Ticks 30");
+    }
+
+    #[test]
+    fn entities_can_be_shot_and_can_kill() {
+        let source = r#"
+            fn on_start(game) {
+                let ship = game.players[0];
+                // One to shoot straight ahead, and one coming in from the
+                // side after the shot lands
+                spawn_entity(#{ x: ship.x, y: ship.y - 120.0, shootable: true, points: 50,
+                                shape: [[-20, -20], [20, -20], [20, 20], [-20, 20]] });
+                spawn_entity(#{ x: ship.x + 200.0, y: ship.y, vx: -300.0, deadly: true,
+                                shape: [[-10, -10], [10, -10], [10, 10], [-10, 10]] });
+            }
+            fn on_entity_destroyed(game, entity, player) {
+                this.destroyed = (this.destroyed ?? 0) + 1;
+            }
+        "#;
+        let mut mods = mods(source);
+        let mut game = game();
+        mods.start(&mut game, 0);
+        let lives = game.players[0].lives;
+
+        let fire = Input {
+            fire: true,
+            ..Input::default()
+        };
+        run(&mut mods, &mut game, 1, fire);
+        run(&mut mods, &mut game, 60, Input::default());
+
+        assert_eq!(game.players[0].lives, lives - 1);
+        assert_eq!(game.score(), 50);
+        assert!(mods.world.is_empty() && mods.maps.is_empty());
+        let state = mods.scripts[0].state.read_lock::<Map>().unwrap();
+        assert_eq!(state["destroyed"].as_int(), Ok(2));
+    }
+
+    #[test]
+    fn entities_move_and_wrap() {
+        let mut mods = mods(
+            r#"
+            fn on_start(game) {
+                spawn_entity(#{ x: 790.0, y: 300.0, vx: 60.0, update: "grow" });
+            }
+            fn grow() { this.size = (this.size ?? 0) + 1; }
+            "#,
+        );
+        let mut game = game();
+        mods.start(&mut game, 0);
+        run(&mut mods, &mut game, 60, Input::default());
+
+        let entity = mods.maps.values().next().unwrap();
+        assert!((entity::center(entity).x - 50.).abs() < 0.01);
+        assert_eq!(entity::center(entity).y, 300.);
+        assert_eq!(entity::int(entity, "size"), 60);
+    }
+
+    #[test]
+    fn runaway_scripts_are_stopped() {
+        let mut mods = mods("fn on_tick(game) { loop { } }");
+        let mut game = game();
+        mods.start(&mut game, 0);
+        run(&mut mods, &mut game, 2, Input::default());
+
+        assert!(mods.scripts[0].failed);
+    }
+
+    #[test]
+    fn random_takes_any_bounds() {
+        let mut mods = mods(
+            r#"
+            fn on_start(game) {
+                this.wide = random(-1.0e308, 1.0e308);
+                this.infinite = random(0.0, parse_float("inf"));
+                this.nan = random(parse_float("NaN"), 1.0);
+                this.fine = random(2.0, 3.0);
+            }
+            "#,
+        );
+        let mut game = game();
+        mods.start(&mut game, 0);
+
+        assert!(!mods.scripts[0].failed);
+        let state = mods.scripts[0].state.read_lock::<Map>().unwrap();
+        assert_eq!(state["wide"].as_float(), Ok(-1.0e308));
+        assert_eq!(state["infinite"].as_float(), Ok(0.));
+        assert!(state["nan"].as_float().unwrap().is_nan());
+        assert!((2.0..3.).contains(&state["fine"].as_float().unwrap()));
+    }
+
+    #[test]
+    fn huge_scores_stop_at_the_top() {
+        let mut mods = mods("fn on_tick(game) { add_score(0, 4294967295); }");
+        let mut game = game();
+        mods.start(&mut game, 0);
+        run(&mut mods, &mut game, 2, Input::default());
+
+        assert!(!mods.scripts[0].failed);
+        assert_eq!(game.players[0].score, u32::MAX);
+    }
+}
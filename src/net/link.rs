@@ -0,0 +1,105 @@
+use super::protocol::Message;
+use crate::math::rng::GameRng;
+use rand::Rng;
+use std::{
+    io::ErrorKind,
+    net::{SocketAddr, ToSocketAddrs, UdpSocket},
+    time::{Duration, Instant},
+};
+
+/// Biggest datagram read, messages are kept well under this
+const MAX_DATAGRAM: usize = 8192;
+
+/// Pretend the network is worse than it is, for trying things out on one
+/// machine. Only what is sent is held back or dropped
+#[derive(Clone, Copy, Default)]
+pub struct Conditions {
+    pub latency: Duration,
+    /// Extra random delay on top of the latency, up to this
+    pub jitter: Duration,
+    /// Chance of a message being dropped, from 0 to 1
+    pub loss: f32,
+}
+
+/// A non-blocking UDP socket sending and receiving whole messages
+pub struct Link {
+    socket: UdpSocket,
+    conditions: Conditions,
+    /// Messages held back by the latency, with when they go
+    delayed: Vec<(Instant, SocketAddr, Vec<u8>)>,
+    rng: GameRng,
+}
+
+impl Link {
+    pub fn bind(addr: impl ToSocketAddrs, conditions: Conditions) -> Result<Link, String> {
+        let socket = UdpSocket::bind(addr).map_err(|e| e.to_string())?;
+        socket.set_nonblocking(true).map_err(|e| e.to_string())?;
+
+        Ok(Link {
+            socket,
+            conditions,
+            delayed: Vec::new(),
+            rng: GameRng::new(rand::random()),
+        })
+    }
+
+    pub fn local_addr(&self) -> Result<SocketAddr, String> {
+        self.socket.local_addr().map_err(|e| e.to_string())
+    }
+
+    pub fn send(&mut self, to: SocketAddr, message: &Message) {
+        if self.conditions.loss > 0. && self.rng.gen::<f32>() < self.conditions.loss {
+            return;
+        }
+
+        let bytes = message.encode();
+        let Conditions {
+            latency, jitter, ..
+        } = self.conditions;
+        if latency.is_zero() && jitter.is_zero() {
+            self.send_now(to, &bytes);
+        } else {
+            let jitter = jitter.mul_f32(self.rng.gen());
+            self.delayed
+                .push((Instant::now() + latency + jitter, to, bytes));
+        }
+    }
+
+    /// Everything that has arrived, after sending anything held back that
+    /// is due
+    pub fn receive(&mut self) -> Vec<(SocketAddr, Message)> {
+        let now = Instant::now();
+        let (due, held) = self.delayed.drain(..).partition(|(at, _, _)| *at <= now);
+        self.delayed = held;
+        for (_, to, bytes) in due {
+            self.send_now(to, &bytes);
+        }
+
+        let mut messages = Vec::new();
+        let mut buffer = [0; MAX_DATAGRAM];
+        loop {
+            match self.socket.recv_from(&mut buffer) {
+                Ok((len, from)) => {
+                    if let Some(message) = Message::decode(&buffer[..len]) {
+                        messages.push((from, message));
+                    }
+                }
+                Err(e) if e.kind() == ErrorKind::WouldBlock => break,
+                // Windows reports sends to closed ports on the next read,
+                // the peer timing out covers that
+                Err(e) if e.kind() == ErrorKind::ConnectionReset => continue,
+                Err(e) => {
+                    eprintln!("Network error: {}", e);
+                    break;
+                }
+            }
+        }
+
+        messages
+    }
+
+    fn send_now(&self, to: SocketAddr, bytes: &[u8]) {
+        // Lost messages are resent, so a failed send is just a lost one
+        self.socket.send_to(bytes, to).ok();
+    }
+}
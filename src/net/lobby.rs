@@ -0,0 +1,237 @@
+use super::{
+    link::{Conditions, Link},
+    protocol::{Message, VERSION},
+    Peer, Session,
+};
+use crate::game::{
+    rules::{Players, Rules},
+    Game,
+};
+use std::{
+    net::{SocketAddr, ToSocketAddrs},
+    time::{Duration, Instant},
+};
+
+/// Most players in a game over the network
+pub const MAX_PLAYERS: usize = 4;
+/// How often to ask the host again while joining
+const JOIN_EVERY: Duration = Duration::from_millis(250);
+
+/// Getting players together before a game over the network. The host
+/// listens on a port and hands out places, everyone else joins it by
+/// address and waits for it to start
+pub struct Lobby {
+    link: Link,
+    role: Role,
+}
+
+enum Role {
+    /// Addresses of the players who joined, in the order they did
+    Host { clients: Vec<SocketAddr> },
+    Join {
+        host: SocketAddr,
+        /// The place the host gave, once it has answered
+        player: Option<usize>,
+        asked: Option<Instant>,
+        /// The host's start, with the place, players, seed and rules
+        start: Option<(usize, usize, u64, Rules)>,
+    },
+}
+
+impl Lobby {
+    /// Host a game on `port` of every interface, 0 picks any free one
+    pub fn host(port: u16, conditions: Conditions) -> Result<Lobby, String> {
+        Ok(Lobby {
+            link: Link::bind(("0.0.0.0", port), conditions)?,
+            role: Role::Host {
+                clients: Vec::new(),
+            },
+        })
+    }
+
+    /// Join the game hosted at `addr`, like `192.168.1.20:7777`
+    pub fn join(addr: &str, conditions: Conditions) -> Result<Lobby, String> {
+        let host = addr
+            .to_socket_addrs()
+            .map_err(|e| format!("{}: {}", addr, e))?
+            .next()
+            .ok_or_else(|| format!("{}: no address found", addr))?;
+        let any = if host.is_ipv4() {
+            "0.0.0.0:0"
+        } else {
+            "[::]:0"
+        };
+
+        Ok(Lobby {
+            link: Link::bind(any, conditions)?,
+            role: Role::Join {
+                host,
+                player: None,
+                asked: None,
+                start: None,
+            },
+        })
+    }
+
+    pub fn is_host(&self) -> bool {
+        matches!(self.role, Role::Host { .. })
+    }
+
+    pub fn local_addr(&self) -> Result<SocketAddr, String> {
+        self.link.local_addr()
+    }
+
+    /// Players in the game so far, including the host. Someone joining
+    /// only knows they are in
+    pub fn players(&self) -> usize {
+        match &self.role {
+            Role::Host { clients } => clients.len() + 1,
+            Role::Join { player, .. } => player.map_or(0, |player| player + 1),
+        }
+    }
+
+    /// What is going on, for showing while waiting
+    pub fn status(&self) -> String {
+        match &self.role {
+            Role::Host { clients } => format!(
+                "Hosting on port {}\n{} of {} players",
+                self.local_addr().map_or(0, |addr| addr.port()),
+                clients.len() + 1,
+                MAX_PLAYERS
+            ),
+            Role::Join {
+                host, player: None, ..
+            } => format!("Joining {}", host),
+            Role::Join {
+                player: Some(player),
+                ..
+            } => format!(
+                "Joined as player {}\nWaiting for the host to start",
+                player + 1
+            ),
+        }
+    }
+
+    /// Answer anyone joining, or wait for the host. Returns true once the
+    /// host has started the game and it can be taken with `start`
+    pub fn poll(&mut self) -> Result<bool, String> {
+        let messages = self.link.receive();
+
+        match &mut self.role {
+            Role::Host { clients } => {
+                for (from, message) in messages {
+                    let version = match message {
+                        Message::Join { version } => version,
+                        _ => continue,
+                    };
+
+                    let reason = if version != VERSION {
+                        format!("The host is on version {}, not {}", VERSION, version)
+                    } else if let Some(i) = clients.iter().position(|&addr| addr == from) {
+                        // Asked again, the welcome must have been lost
+                        self.link.send(from, &Message::Welcome { player: i + 1 });
+                        continue;
+                    } else if clients.len() + 1 < MAX_PLAYERS {
+                        clients.push(from);
+                        let player = clients.len();
+                        self.link.send(from, &Message::Welcome { player });
+                        continue;
+                    } else {
+                        "The game is full".to_string()
+                    };
+                    self.link.send(from, &Message::Refused { reason });
+                }
+
+                Ok(false)
+            }
+            Role::Join {
+                host,
+                player,
+                asked,
+                start,
+            } => {
+                let host = *host;
+                for (from, message) in messages {
+                    if from != host {
+                        continue;
+                    }
+
+                    match message {
+                        Message::Welcome { player: given } => *player = Some(given),
+                        Message::Refused { reason } => return Err(reason),
+                        Message::Start {
+                            player,
+                            players,
+                            seed,
+                            rules,
+                        } => *start = Some((player, players, seed, rules)),
+                        _ => (),
+                    }
+                }
+
+                if player.is_none() && asked.is_none_or(|at| at.elapsed() > JOIN_EVERY) {
+                    self.link.send(host, &Message::Join { version: VERSION });
+                    *asked = Some(Instant::now());
+                }
+
+                Ok(start.is_some())
+            }
+        }
+    }
+
+    /// Start the game for everyone who has joined, with co-op unless the
+    /// rules say versus as there is nobody to play alone against. Players
+    /// joining get the host's seed and rules instead, once it has started
+    pub fn start(self, seed: u64, rules: Rules) -> Result<(Session, Game), String> {
+        let clients = match self.role {
+            Role::Host { clients } if !clients.is_empty() => clients,
+            Role::Host { .. } => return Err("Nobody has joined yet".to_string()),
+            Role::Join {
+                host,
+                start: Some((player, players, seed, rules)),
+                ..
+            } => {
+                if players == 0 || players > MAX_PLAYERS || player >= players {
+                    return Err(format!(
+                        "The host started a game as player {} of {}",
+                        player + 1,
+                        players
+                    ));
+                }
+                rules
+                    .check()
+                    .map_err(|e| format!("The host started a game that can't be played: {}", e))?;
+                let game = Game::with_players(seed, rules, players);
+                let peers = vec![Peer::new(host, 0, players, None)];
+                return Ok((Session::new(self.link, player, peers, seed, &game), game));
+            }
+            Role::Join { .. } => return Err("The host hasn't started yet".to_string()),
+        };
+
+        let rules = Rules {
+            players: match rules.players {
+                Players::Single => Players::Coop,
+                players => players,
+            },
+            ..rules
+        };
+        let players = clients.len() + 1;
+        let game = Game::with_players(seed, rules, players);
+
+        let peers = clients
+            .into_iter()
+            .enumerate()
+            .map(|(i, addr)| {
+                let start = Message::Start {
+                    player: i + 1,
+                    players,
+                    seed,
+                    rules,
+                };
+                Peer::new(addr, i + 1, players, Some(start))
+            })
+            .collect();
+
+        Ok((Session::new(self.link, 0, peers, seed, &game), game))
+    }
+}
@@ -0,0 +1,606 @@
+pub mod link;
+pub mod lobby;
+pub mod protocol;
+
+use crate::{
+    game::{input::Input, Game, GameEvent},
+    storage,
+};
+use link::Link;
+use protocol::{Message, Run};
+use std::{
+    collections::{BTreeMap, VecDeque},
+    net::SocketAddr,
+    thread,
+    time::{Duration, Instant},
+};
+
+/// Most ticks the game runs ahead of the last one everyone's inputs are in
+/// for, past that it waits for them
+const MAX_ROLLBACK: usize = 12;
+/// Ticks between checksums of the game, for catching it going out of sync
+const CHECKSUM_EVERY: usize = 30;
+const KEEP_CHECKSUMS: usize = 16;
+/// Most of one player's inputs sent in a message
+const MAX_RUN: usize = 64;
+/// How long a player can go unheard before they are given up on
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Another machine in the game. Clients only talk to the host, which
+/// passes everyone's inputs on
+struct Peer {
+    addr: SocketAddr,
+    /// The player playing there
+    player: usize,
+    /// How many ticks of each player's inputs it has
+    acks: Vec<u32>,
+    last_heard: Instant,
+    /// The host sends the start until the player is heard from in game
+    start: Option<Message>,
+}
+
+impl Peer {
+    fn new(addr: SocketAddr, player: usize, players: usize, start: Option<Message>) -> Peer {
+        Peer {
+            addr,
+            player,
+            acks: vec![0; players],
+            last_heard: Instant::now(),
+            start,
+        }
+    }
+}
+
+/// A game over the network with rollback. The game shown runs ahead with
+/// everyone else's inputs guessed, and when the real ones arrive and the
+/// guess was wrong it is run again from the last tick everyone agreed on
+pub struct Session {
+    link: Link,
+    player: usize,
+    peers: Vec<Peer>,
+    seed: u64,
+    /// Every player's inputs from the first tick, as far as they have
+    /// arrived
+    inputs: Vec<Vec<Input>>,
+    /// Players who left, with how many ticks of inputs they left after
+    gone: Vec<Option<u32>>,
+    /// The game as of `confirmed_tick`, with everyone's real inputs
+    confirmed: Game,
+    confirmed_tick: usize,
+    /// The inputs the shown game was run with for each tick after that
+    guesses: VecDeque<Vec<Input>>,
+    /// Some guess turned out wrong, so the shown game needs running again
+    mispredicted: bool,
+    checksums: BTreeMap<u32, u64>,
+    /// Checksums from others waiting for the game to get that far here
+    remote: Vec<(u32, u64)>,
+    /// How many times the game has had to be run again
+    pub rollbacks: u32,
+}
+
+impl Session {
+    fn new(link: Link, player: usize, peers: Vec<Peer>, seed: u64, game: &Game) -> Session {
+        let players = game.players.len();
+        Session {
+            link,
+            player,
+            peers,
+            seed,
+            inputs: vec![Vec::new(); players],
+            gone: vec![None; players],
+            confirmed: game.clone(),
+            confirmed_tick: 0,
+            guesses: VecDeque::new(),
+            mispredicted: false,
+            checksums: BTreeMap::new(),
+            remote: Vec::new(),
+            rollbacks: 0,
+        }
+    }
+
+    /// The player playing on this machine
+    pub fn player(&self) -> usize {
+        self.player
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Ticks the shown game has run
+    pub fn tick(&self) -> usize {
+        self.confirmed_tick + self.guesses.len()
+    }
+
+    /// The game as far as everyone's inputs are in
+    pub fn confirmed(&self) -> (&Game, usize) {
+        (&self.confirmed, self.confirmed_tick)
+    }
+
+    /// Run `game` on a tick with `local` for this player. Returns None
+    /// without running it if the others are too far behind, and an error
+    /// if the game can't go on
+    pub fn advance(
+        &mut self,
+        game: &mut Game,
+        local: Input,
+    ) -> Result<Option<Vec<GameEvent>>, String> {
+        self.receive()?;
+        if self.player != 0 && self.gone[0].is_some() {
+            return Err("The host left the game".to_string());
+        }
+
+        self.confirm()?;
+        if self.mispredicted {
+            self.resimulate(game);
+        }
+        if self.guesses.len() >= MAX_ROLLBACK {
+            self.send();
+            return Ok(None);
+        }
+
+        let tick = self.tick();
+        self.inputs[self.player].push(local);
+        let inputs = self.guess(tick);
+        let events = game.tick(&inputs);
+        self.guesses.push_back(inputs);
+
+        self.send();
+        Ok(Some(events))
+    }
+
+    /// Send and receive without running the game, for while it isn't
+    pub fn pump(&mut self) -> Result<(), String> {
+        self.receive()?;
+        self.confirm()?;
+        self.send();
+        Ok(())
+    }
+
+    /// Keep sending until everyone has all the inputs there are or
+    /// `timeout` passes, so nobody is left waiting on one that was lost
+    pub fn flush(&mut self, timeout: Duration) -> Result<(), String> {
+        let start = Instant::now();
+        while start.elapsed() < timeout {
+            self.pump()?;
+
+            let inputs = &self.inputs;
+            let done = self.peers.iter().all(|peer| {
+                (0..inputs.len())
+                    .all(|p| p == peer.player || peer.acks[p] as usize >= inputs[p].len())
+            });
+            if done {
+                break;
+            }
+            thread::sleep(Duration::from_millis(1));
+        }
+
+        Ok(())
+    }
+
+    /// Tell everyone this player is going. Sent a few times as there is
+    /// nothing to say it arrived
+    pub fn leave(&mut self) {
+        let at = self.inputs[self.player].len() as u32;
+        for _ in 0..3 {
+            for peer in &self.peers {
+                self.link.send(peer.addr, &Message::Leave { at });
+            }
+        }
+        self.link.receive();
+    }
+
+    /// A player's input for a tick if it is known. Players who left hold
+    /// nothing from then on
+    fn input(&self, player: usize, tick: usize) -> Option<Input> {
+        let inputs = &self.inputs[player];
+        match (inputs.get(tick), self.gone[player]) {
+            (Some(&input), _) => Some(input),
+            (None, Some(at)) if tick >= at as usize && inputs.len() >= at as usize => {
+                Some(Input::default())
+            }
+            _ => None,
+        }
+    }
+
+    /// Everyone's inputs for a tick if they are all in
+    fn known(&self, tick: usize) -> Option<Vec<Input>> {
+        (0..self.inputs.len())
+            .map(|p| self.input(p, tick))
+            .collect()
+    }
+
+    /// Everyone's inputs for a tick, guessing anyone who isn't in yet is
+    /// still holding what they held last
+    fn guess(&self, tick: usize) -> Vec<Input> {
+        (0..self.inputs.len())
+            .map(|p| {
+                self.input(p, tick)
+                    .or_else(|| self.inputs[p].last().copied())
+                    .unwrap_or_default()
+            })
+            .collect()
+    }
+
+    /// Move the confirmed game on as far as everyone's inputs go, noting
+    /// if any guess turned out wrong
+    fn confirm(&mut self) -> Result<(), String> {
+        while let Some(inputs) = self.known(self.confirmed_tick) {
+            // This player's input is only known for ticks that have run
+            let guess = match self.guesses.pop_front() {
+                Some(guess) => guess,
+                None => break,
+            };
+            self.mispredicted |= guess != inputs;
+
+            self.confirmed.tick(&inputs);
+            self.confirmed_tick += 1;
+            if self.confirmed_tick.is_multiple_of(CHECKSUM_EVERY) {
+                let tick = self.confirmed_tick as u32;
+                self.checksums.insert(tick, checksum(&self.confirmed));
+                if self.checksums.len() > KEEP_CHECKSUMS {
+                    self.checksums
+                        .remove(&(tick - (KEEP_CHECKSUMS * CHECKSUM_EVERY) as u32));
+                }
+            }
+        }
+
+        // Inputs can be in for some players on later ticks already
+        for (i, guess) in self.guesses.iter().enumerate() {
+            let tick = self.confirmed_tick + i;
+            for (p, &guessed) in guess.iter().enumerate() {
+                if self.input(p, tick).is_some_and(|input| input != guessed) {
+                    self.mispredicted = true;
+                }
+            }
+        }
+
+        self.check_remote()
+    }
+
+    /// Go back to the confirmed game and run every tick since again with
+    /// the inputs as they are known now
+    fn resimulate(&mut self, game: &mut Game) {
+        *game = self.confirmed.clone();
+        for i in 0..self.guesses.len() {
+            let inputs = self.guess(self.confirmed_tick + i);
+            game.tick(&inputs);
+            self.guesses[i] = inputs;
+        }
+        self.mispredicted = false;
+        self.rollbacks += 1;
+    }
+
+    fn check_remote(&mut self) -> Result<(), String> {
+        let (checksums, confirmed) = (&self.checksums, self.confirmed_tick as u32);
+        for &(tick, sum) in &self.remote {
+            match checksums.get(&tick) {
+                Some(&mine) if mine != sum => {
+                    return Err(format!(
+                        "Out of sync with the other players at tick {}",
+                        tick
+                    ))
+                }
+                _ => (),
+            }
+        }
+        self.remote.retain(|&(tick, _)| tick > confirmed);
+
+        Ok(())
+    }
+
+    fn receive(&mut self) -> Result<(), String> {
+        for (from, message) in self.link.receive() {
+            let index = match self.peers.iter().position(|peer| peer.addr == from) {
+                Some(index) => index,
+                None => {
+                    if let Message::Join { .. } = message {
+                        let reason = "The game has already started".to_string();
+                        self.link.send(from, &Message::Refused { reason });
+                    }
+                    continue;
+                }
+            };
+            let peer = &mut self.peers[index];
+            peer.last_heard = Instant::now();
+            let from_host = peer.player == 0;
+
+            match message {
+                Message::Inputs {
+                    acks,
+                    runs,
+                    gone,
+                    checksum,
+                } => {
+                    peer.start = None;
+                    for (ack, theirs) in peer.acks.iter_mut().zip(acks) {
+                        *ack = (*ack).max(theirs);
+                    }
+                    // The host only takes each client's own inputs, and
+                    // passes them on to the rest
+                    let player = peer.player;
+                    for run in runs {
+                        if from_host || run.player == player {
+                            self.merge(run);
+                        }
+                    }
+                    // Only the host says who has gone
+                    for (player, at) in gone {
+                        if from_host && player < self.gone.len() && player != self.player {
+                            self.gone[player] = Some(at);
+                        }
+                    }
+                    self.remote.extend(checksum);
+                }
+                Message::Leave { at } => {
+                    let player = peer.player;
+                    self.drop_peer(index, at);
+                    eprintln!("Player {} left", player + 1);
+                }
+                _ => (),
+            }
+        }
+
+        let now = Instant::now();
+        while let Some(index) = self
+            .peers
+            .iter()
+            .position(|peer| now - peer.last_heard > TIMEOUT)
+        {
+            if self.player != 0 {
+                return Err("Lost connection to the host".to_string());
+            }
+            eprintln!("Lost connection to player {}", self.peers[index].player + 1);
+            self.drop_peer(index, u32::MAX);
+        }
+
+        Ok(())
+    }
+
+    /// Stop talking to a peer and have their player hold nothing after
+    /// `at` ticks, or after what has arrived of theirs if that is less.
+    /// Inputs only reach the clients through the host, so none of them
+    /// can have more
+    fn drop_peer(&mut self, index: usize, at: u32) {
+        let peer = self.peers.remove(index);
+        let at = at.min(self.inputs[peer.player].len() as u32);
+        self.gone[peer.player] = Some(at);
+    }
+
+    /// Add inputs that arrived to what is known, runs can overlap what is
+    /// already here or come out of order
+    fn merge(&mut self, run: Run) {
+        if run.player >= self.inputs.len() || run.player == self.player {
+            return;
+        }
+
+        let inputs = &mut self.inputs[run.player];
+        let (first, have) = (run.first as usize, inputs.len());
+        if first <= have && first + run.inputs.len() > have {
+            inputs.extend(
+                run.inputs[have - first..]
+                    .iter()
+                    .map(|&bits| Input::from_bits(bits)),
+            );
+        }
+    }
+
+    /// Send each peer the inputs it is missing, with what this player has
+    fn send(&mut self) {
+        let acks: Vec<u32> = self
+            .inputs
+            .iter()
+            .map(|inputs| inputs.len() as u32)
+            .collect();
+        let gone: Vec<(usize, u32)> = self
+            .gone
+            .iter()
+            .enumerate()
+            .filter_map(|(player, at)| at.map(|at| (player, at)))
+            .collect();
+        let checksum = self
+            .checksums
+            .iter()
+            .next_back()
+            .map(|(&tick, &sum)| (tick, sum));
+
+        let Session {
+            link,
+            peers,
+            inputs,
+            ..
+        } = self;
+        for peer in peers.iter() {
+            if let Some(start) = &peer.start {
+                link.send(peer.addr, start);
+            }
+
+            let runs = inputs
+                .iter()
+                .enumerate()
+                .filter(|&(player, inputs)| inputs.len() > peer.acks[player] as usize)
+                .map(|(player, inputs)| {
+                    let first = peer.acks[player] as usize;
+                    let last = inputs.len().min(first + MAX_RUN);
+                    Run {
+                        player,
+                        first: first as u32,
+                        inputs: inputs[first..last]
+                            .iter()
+                            .map(|input| input.to_bits())
+                            .collect(),
+                    }
+                })
+                .collect();
+
+            let message = Message::Inputs {
+                acks: acks.clone(),
+                runs,
+                gone: gone.clone(),
+                checksum,
+            };
+            link.send(peer.addr, &message);
+        }
+    }
+}
+
+/// A hash of the saved form of the game, the same on every machine as
+/// long as the games are
+pub fn checksum(game: &Game) -> u64 {
+    storage::fnv1a(&serde_json::to_vec(game).unwrap_or_default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        game::rules::{Difficulty, GameMode, Players, Rules},
+        math::playfield::Playfield,
+    };
+    use link::Conditions;
+    use lobby::Lobby;
+
+    const TICKS: usize = 240;
+
+    fn rules() -> Rules {
+        Rules {
+            difficulty: Difficulty::Normal,
+            mode: GameMode::Classic,
+            players: Players::Coop,
+            field: Playfield::new(800., 800.),
+        }
+    }
+
+    /// Turning, thrusting and firing in a pattern of each player's own
+    fn scripted(player: usize, tick: usize) -> Input {
+        let phase = tick / (20 + player * 7) % 4;
+        Input {
+            left: phase == 0,
+            thrust: phase == 1,
+            right: phase == 2,
+            fire: tick.is_multiple_of(9 + player),
+            ..Input::default()
+        }
+    }
+
+    /// Everyone in a game hosted on localhost, running as fast as the
+    /// network lets them
+    fn connect(players: usize, conditions: Conditions) -> Vec<(Session, Game)> {
+        let mut host = Lobby::host(0, conditions).unwrap();
+        let addr = format!("127.0.0.1:{}", host.local_addr().unwrap().port());
+        let mut joining: Vec<_> = (1..players)
+            .map(|_| Lobby::join(&addr, conditions).unwrap())
+            .collect();
+
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while host.players() < players {
+            assert!(Instant::now() < deadline, "only {} joined", host.players());
+            host.poll().unwrap();
+            for lobby in joining.iter_mut() {
+                lobby.poll().unwrap();
+            }
+            thread::sleep(Duration::from_millis(1));
+        }
+
+        let mut sessions = vec![host.start(7, rules()).unwrap()];
+        while !joining.is_empty() {
+            assert!(Instant::now() < deadline, "the start didn't arrive");
+            sessions[0].0.pump().unwrap();
+            for i in (0..joining.len()).rev() {
+                if joining[i].poll().unwrap() {
+                    sessions.push(joining.remove(i).start(0, rules()).unwrap());
+                }
+            }
+            thread::sleep(Duration::from_millis(1));
+        }
+
+        sessions
+    }
+
+    /// Run every session until they all have everyone's inputs for every
+    /// tick, or one of them fails
+    fn play(sessions: &mut [(Session, Game)]) -> Result<(), String> {
+        let deadline = Instant::now() + Duration::from_secs(20);
+        while sessions
+            .iter()
+            .any(|(session, _)| session.confirmed().1 < TICKS)
+        {
+            assert!(Instant::now() < deadline, "the game stalled");
+            for (session, game) in sessions.iter_mut() {
+                if session.tick() < TICKS {
+                    let input = scripted(session.player(), session.tick());
+                    session.advance(game, input)?;
+                } else {
+                    session.pump()?;
+                }
+            }
+            thread::sleep(Duration::from_millis(1));
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn games_agree_over_a_bad_network() {
+        let conditions = Conditions {
+            latency: Duration::from_millis(20),
+            jitter: Duration::from_millis(15),
+            loss: 0.2,
+        };
+        let mut sessions = connect(3, conditions);
+        play(&mut sessions).unwrap();
+
+        // The same as playing the same inputs on one machine
+        let mut local = Game::with_players(7, rules(), 3);
+        for tick in 0..TICKS {
+            let inputs: Vec<_> = (0..3).map(|player| scripted(player, tick)).collect();
+            local.tick(&inputs);
+        }
+        for (session, _) in &sessions {
+            assert_eq!(checksum(session.confirmed().0), checksum(&local));
+        }
+
+        let rollbacks: u32 = sessions.iter().map(|(session, _)| session.rollbacks).sum();
+        assert!(rollbacks > 0, "late inputs never needed a rollback");
+    }
+
+    #[test]
+    fn going_out_of_sync_is_caught() {
+        let mut sessions = connect(2, Conditions::default());
+        sessions[1].0.confirmed.wave += 1;
+
+        let error = play(&mut sessions).unwrap_err();
+        assert!(error.starts_with("Out of sync"), "{}", error);
+    }
+
+    #[test]
+    fn the_host_only_takes_each_clients_own_inputs() {
+        let mut sessions = connect(3, Conditions::default());
+        let host = sessions[1].0.peers[0].addr;
+        // Clients join in any order, so find out who this one is
+        let (client, other) = match sessions[1].0.player {
+            1 => (1, 2),
+            _ => (2, 1),
+        };
+        let run = |player| Run {
+            player,
+            first: 0,
+            inputs: vec![scripted(player, 0).to_bits(); 5],
+        };
+        let forged = Message::Inputs {
+            acks: Vec::new(),
+            runs: vec![run(client), run(other)],
+            gone: Vec::new(),
+            checksum: None,
+        };
+        sessions[1].0.link.send(host, &forged);
+
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while sessions[0].0.inputs[client].len() < 5 {
+            assert!(Instant::now() < deadline, "the inputs never arrived");
+            sessions[0].0.pump().unwrap();
+            thread::sleep(Duration::from_millis(1));
+        }
+        assert!(sessions[0].0.inputs[other].is_empty());
+    }
+}
@@ -0,0 +1,55 @@
+use crate::game::rules::Rules;
+use serde::{Deserialize, Serialize};
+
+/// Bumped whenever messages or the simulation change, players on different
+/// versions can't play together
+pub const VERSION: u32 = 2;
+
+/// Everything sent between players, one message per datagram
+#[derive(Serialize, Deserialize)]
+pub enum Message {
+    /// Asking the host for a place, sent until it answers
+    Join { version: u32 },
+    /// The place the host gave
+    Welcome { player: usize },
+    /// The host said no, with why
+    Refused { reason: String },
+    /// The game is starting, everyone makes the same game from this
+    Start {
+        player: usize,
+        players: usize,
+        seed: u64,
+        rules: Rules,
+    },
+    /// Inputs the receiver doesn't have yet and what the sender has
+    Inputs {
+        /// How many ticks of each player's inputs the sender has
+        acks: Vec<u32>,
+        runs: Vec<Run>,
+        /// Players who left and after how many ticks, from the host
+        gone: Vec<(usize, u32)>,
+        /// The sender's latest checksum of the game, as (tick, sum)
+        checksum: Option<(u32, u64)>,
+    },
+    /// The sender is leaving after `at` ticks of inputs
+    Leave { at: u32 },
+}
+
+/// A player's inputs as bits for the ticks from `first` on
+#[derive(Serialize, Deserialize)]
+pub struct Run {
+    pub player: usize,
+    pub first: u32,
+    pub inputs: Vec<u64>,
+}
+
+impl Message {
+    pub fn encode(&self) -> Vec<u8> {
+        // Nothing in a message can fail to serialize
+        serde_json::to_vec(self).unwrap_or_default()
+    }
+
+    pub fn decode(bytes: &[u8]) -> Option<Message> {
+        serde_json::from_slice(bytes).ok()
+    }
+}
@@ -0,0 +1,110 @@
+use sdl2::{
+    pixels::{Color, PixelFormatEnum},
+    rect::Rect,
+    render::{BlendMode, Canvas, Texture, TextureCreator},
+    surface::Surface,
+    ttf::Font,
+    video::{Window, WindowContext},
+};
+
+/// Printable ASCII goes in the atlas, anything else is drawn as `?`
+const FIRST: char = ' ';
+const LAST: char = '~';
+/// Widest a row of glyphs gets before starting the next one
+const ROW_WIDTH: u32 = 1024;
+
+struct Glyph {
+    /// Where the glyph is in the atlas texture
+    src: Rect,
+    advance: i32,
+}
+
+/// Every glyph of a font at one size, rendered once into a texture so text
+/// can be drawn by copying glyphs out of it
+pub struct Atlas<'tc> {
+    texture: Texture<'tc>,
+    glyphs: Vec<Glyph>,
+}
+
+impl<'tc> Atlas<'tc> {
+    pub fn new(
+        font: &Font,
+        texture_creator: &'tc TextureCreator<WindowContext>,
+    ) -> Result<Atlas<'tc>, String> {
+        let height = font.height().max(1) as u32;
+
+        let mut surfaces = Vec::new();
+        for c in FIRST..=LAST {
+            let surface = font
+                .render_char(c)
+                .blended(Color::WHITE)
+                .map_err(|e| e.to_string())?;
+            let advance = font
+                .find_glyph_metrics(c)
+                .map_or(surface.width() as i32, |metrics| metrics.advance);
+            surfaces.push((surface, advance));
+        }
+
+        // Pack the glyphs left to right in rows
+        let mut glyphs = Vec::new();
+        let (mut x, mut y) = (0, 0);
+        for (surface, advance) in surfaces.iter() {
+            if x + surface.width() > ROW_WIDTH {
+                x = 0;
+                y += height;
+            }
+            glyphs.push(Glyph {
+                src: Rect::new(x as i32, y as i32, surface.width(), surface.height()),
+                advance: *advance,
+            });
+            x += surface.width();
+        }
+
+        let mut sheet = Surface::new(ROW_WIDTH, y + height, PixelFormatEnum::ARGB8888)?;
+        for ((surface, _), glyph) in surfaces.iter_mut().zip(&glyphs) {
+            // Copy the glyph's alpha as is rather than blending onto nothing
+            surface.set_blend_mode(BlendMode::None)?;
+            surface.blit(None, &mut sheet, glyph.src)?;
+        }
+
+        let mut texture = texture_creator
+            .create_texture_from_surface(&sheet)
+            .map_err(|e| e.to_string())?;
+        texture.set_blend_mode(BlendMode::Blend);
+
+        Ok(Atlas { texture, glyphs })
+    }
+
+    fn glyph(&self, c: char) -> &Glyph {
+        let c = if (FIRST..=LAST).contains(&c) { c } else { '?' };
+        &self.glyphs[c as usize - FIRST as usize]
+    }
+
+    /// Draw a line of text with its top left corner at `x`, `y`
+    pub fn draw(
+        &mut self,
+        canvas: &mut Canvas<Window>,
+        text: &str,
+        x: i32,
+        y: i32,
+        color: Color,
+    ) -> Result<(), String> {
+        // The glyphs are white so tinting them gives the colour
+        self.texture.set_color_mod(color.r, color.g, color.b);
+        self.texture.set_alpha_mod(color.a);
+
+        let mut pen = x;
+        for c in text.chars() {
+            let glyph = self.glyph(c);
+            let target = Rect::new(pen, y, glyph.src.width(), glyph.src.height());
+            canvas.copy(&self.texture, glyph.src, target)?;
+            pen += glyph.advance;
+        }
+
+        Ok(())
+    }
+
+    pub fn width(&self, text: &str) -> i32 {
+        text.chars().map(|c| self.glyph(c).advance).sum()
+    }
+}
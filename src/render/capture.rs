@@ -0,0 +1,167 @@
+use super::{hud, scene, software::SoftwareRenderer};
+use crate::{entity::star::Starfield, game::Game, storage::civil_date};
+use std::{
+    fs::File,
+    io::BufWriter,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// Draw a frame of the game at playfield size, the same way every time
+pub fn frame(stars: &Starfield, game: &Game, high_score: u32) -> SoftwareRenderer {
+    let field = &game.rules.field;
+    let mut buffer = SoftwareRenderer::new(field.width as u32, field.height as u32);
+
+    scene::draw(&mut buffer, stars, game);
+    hud::draw(&mut buffer, game, high_score);
+
+    buffer
+}
+
+/// Save rows of RGBA bytes to a new file, reporting where it went
+pub fn screenshot(width: u32, height: u32, pixels: &[u8]) {
+    let path = screenshot_path();
+
+    match write_png(&path, width, height, pixels) {
+        Ok(()) => eprintln!("Saved screenshot to {}", path.display()),
+        Err(e) => eprintln!("Could not save screenshot: {}", e),
+    }
+}
+
+pub fn save_png(path: &Path, buffer: &SoftwareRenderer) -> Result<(), String> {
+    write_png(path, buffer.width(), buffer.height(), buffer.pixels())
+}
+
+/// Write rows of RGBA bytes to a PNG file
+pub fn write_png(path: &Path, width: u32, height: u32, pixels: &[u8]) -> Result<(), String> {
+    let file = File::create(path).map_err(|e| e.to_string())?;
+
+    let mut encoder = png::Encoder::new(BufWriter::new(file), width, height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+
+    let mut writer = encoder.write_header().map_err(|e| e.to_string())?;
+    writer.write_image_data(pixels).map_err(|e| e.to_string())
+}
+
+/// A new file in the pictures directory named after the current time,
+/// or in the working directory if there is no pictures directory
+fn screenshot_path() -> PathBuf {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs());
+    let (year, month, day) = civil_date(now);
+    let secs = now % 86_400;
+
+    let name = format!(
+        "asteroids-{:04}-{:02}-{:02}-{:02}{:02}{:02}",
+        year,
+        month,
+        day,
+        secs / 3600,
+        secs / 60 % 60,
+        secs % 60
+    );
+    let dir = dirs::picture_dir().unwrap_or_default();
+
+    // Several screenshots in the same second get numbered
+    let mut path = dir.join(format!("{}.png", name));
+    let mut n = 1;
+    while path.exists() {
+        n += 1;
+        path = dir.join(format!("{}-{}.png", name, n));
+    }
+
+    path
+}
+
+/// Writes frames to a looping animated GIF
+pub struct GifWriter {
+    encoder: gif::Encoder<BufWriter<File>>,
+    /// Time each frame is shown for, in hundredths of a second
+    delay: u16,
+}
+
+impl GifWriter {
+    pub fn create(path: &Path, width: u32, height: u32, delay: u16) -> Result<GifWriter, String> {
+        let file = File::create(path).map_err(|e| e.to_string())?;
+
+        let mut encoder = gif::Encoder::new(BufWriter::new(file), width as u16, height as u16, &[])
+            .map_err(|e| e.to_string())?;
+        encoder
+            .set_repeat(gif::Repeat::Infinite)
+            .map_err(|e| e.to_string())?;
+
+        Ok(GifWriter { encoder, delay })
+    }
+
+    pub fn push(&mut self, buffer: &SoftwareRenderer) -> Result<(), String> {
+        let mut pixels = buffer.pixels().to_vec();
+        let mut frame = gif::Frame::from_rgba_speed(
+            buffer.width() as u16,
+            buffer.height() as u16,
+            &mut pixels,
+            10,
+        );
+        frame.delay = self.delay;
+
+        self.encoder.write_frame(&frame).map_err(|e| e.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::test_dir;
+
+    /// A black frame with a white square `at` pixels in
+    fn square(at: usize) -> SoftwareRenderer {
+        let mut buffer = SoftwareRenderer::new(16, 12);
+        for (i, pixel) in buffer.pixels_mut().chunks_mut(4).enumerate() {
+            let (x, y) = (i % 16, i / 16);
+            let lit = (at..at + 4).contains(&x) && (at..at + 4).contains(&y);
+            let level = if lit { 255 } else { 0 };
+            pixel.copy_from_slice(&[level, level, level, 255]);
+        }
+        buffer
+    }
+
+    #[test]
+    fn pngs_decode_back_the_same() {
+        let path = test_dir("png").join("frame.png");
+        let buffer = square(2);
+        save_png(&path, &buffer).unwrap();
+
+        let mut reader = png::Decoder::new(File::open(&path).unwrap())
+            .read_info()
+            .unwrap();
+        let mut pixels = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut pixels).unwrap();
+        assert_eq!((info.width, info.height), (16, 12));
+        assert_eq!(info.color_type, png::ColorType::Rgba);
+        assert_eq!(&pixels[..info.buffer_size()], buffer.pixels());
+    }
+
+    #[test]
+    fn gifs_get_every_frame() {
+        let path = test_dir("gif").join("run.gif");
+        let mut gif = GifWriter::create(&path, 16, 12, 7).unwrap();
+        gif.push(&square(1)).unwrap();
+        gif.push(&square(8)).unwrap();
+        // The end of the file is written when it is dropped
+        drop(gif);
+
+        let mut options = gif::DecodeOptions::new();
+        options.set_color_output(gif::ColorOutput::RGBA);
+        let mut decoder = options.read_info(File::open(&path).unwrap()).unwrap();
+        assert_eq!((decoder.width(), decoder.height()), (16, 12));
+        let mut lit = Vec::new();
+        while let Some(frame) = decoder.read_next_frame().unwrap() {
+            assert_eq!(frame.delay, 7);
+            // Where the first bright pixel is, colours can shift a little
+            let first = frame.buffer.chunks(4).position(|pixel| pixel[0] > 200);
+            lit.push(first.map(|i| (i % 16, i / 16)));
+        }
+        assert_eq!(lit, [Some((1, 1)), Some((8, 8))]);
+    }
+}
@@ -0,0 +1,230 @@
+use super::renderer::Renderer;
+use crate::{
+    game::GameEvent,
+    math::{playfield::Playfield, vec2::Vec2},
+};
+use rand::Rng;
+use sdl2::pixels::Color;
+
+/// Shake added by each kind of hit, shake is capped at 1
+const SHAKE_LARGE: f32 = 0.5;
+const SHAKE_SMALL: f32 = 0.25;
+const SHAKE_DEATH: f32 = 0.9;
+/// Shake lost a second
+const SHAKE_DECAY: f32 = 1.5;
+/// Furthest the playfield moves at full shake, in playfield units
+const MAX_SHAKE: f32 = 14.;
+/// Seconds the game freezes for when the ship is destroyed
+const HIT_STOP: f32 = 0.2;
+/// Seconds a flash takes to fade out and how opaque it starts
+const FLASH_TIME: f32 = 0.35;
+const FLASH_ALPHA: f32 = 140.;
+
+/// Which effects are on, they can all be turned off for anyone who finds
+/// them uncomfortable
+#[derive(Clone, Copy)]
+pub struct EffectSettings {
+    pub shake: bool,
+    pub hit_stop: bool,
+    pub flash: bool,
+}
+
+/// Screen shake, hit-stop and flashes set off by game events, each fading
+/// over time independently of the others
+pub struct Effects {
+    settings: EffectSettings,
+    /// From 0 to 1, the offset grows with its square so small hits stay subtle
+    shake: f32,
+    offset: Vec2,
+    frozen: f32,
+    flash: f32,
+}
+
+impl Effects {
+    pub fn new(settings: EffectSettings) -> Self {
+        Self {
+            settings,
+            shake: 0.,
+            offset: Vec2::new(0., 0.),
+            frozen: 0.,
+            flash: 0.,
+        }
+    }
+
+    pub fn event(&mut self, event: &GameEvent) {
+        match event {
+            GameEvent::AsteroidDestroyed { large, .. } => {
+                self.add_shake(if *large { SHAKE_LARGE } else { SHAKE_SMALL })
+            }
+            GameEvent::ShipDestroyed { .. } => {
+                self.add_shake(SHAKE_DEATH);
+                if self.settings.hit_stop {
+                    self.frozen = HIT_STOP;
+                }
+            }
+            GameEvent::ExtraLife | GameEvent::Hyperspace => {
+                if self.settings.flash {
+                    self.flash = FLASH_TIME;
+                }
+            }
+            GameEvent::Fired | GameEvent::ShipSpawned { .. } | GameEvent::GameOver => (),
+        }
+    }
+
+    fn add_shake(&mut self, amount: f32) {
+        if self.settings.shake {
+            self.shake = (self.shake + amount).min(1.);
+        }
+    }
+
+    /// Let everything fade by `dt` seconds and pick this frame's shake
+    pub fn update<R: Rng>(&mut self, dt: f32, rng: &mut R) {
+        self.shake = (self.shake - SHAKE_DECAY * dt).max(0.);
+        self.frozen = (self.frozen - dt).max(0.);
+        self.flash = (self.flash - dt).max(0.);
+
+        let distance = MAX_SHAKE * self.shake * self.shake;
+        self.offset = Vec2::new(
+            rng.gen_range(-1.0..1.) * distance,
+            rng.gen_range(-1.0..1.) * distance,
+        );
+    }
+
+    /// Whether the game should stand still for a hit-stop
+    pub fn is_frozen(&self) -> bool {
+        self.frozen > 0.
+    }
+
+    /// How far to move the playfield this frame
+    pub fn offset(&self) -> &Vec2 {
+        &self.offset
+    }
+
+    /// Cover everything in a fading white flash, if there is one
+    pub fn draw_flash(&self, renderer: &mut dyn Renderer, field: &Playfield) {
+        if self.flash <= 0. {
+            return;
+        }
+
+        let alpha = FLASH_ALPHA * self.flash / FLASH_TIME;
+        let screen = [
+            Vec2::new(0., 0.),
+            Vec2::new(field.width, 0.),
+            Vec2::new(field.width, field.height),
+            Vec2::new(0., field.height),
+        ];
+        renderer.filled_polygon(&screen, Color::RGBA(255, 255, 255, alpha as u8));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    const DT: f32 = 1. / 60.;
+    const ALL: EffectSettings = EffectSettings {
+        shake: true,
+        hit_stop: true,
+        flash: true,
+    };
+    const NONE: EffectSettings = EffectSettings {
+        shake: false,
+        hit_stop: false,
+        flash: false,
+    };
+
+    fn asteroid(large: bool) -> GameEvent {
+        GameEvent::AsteroidDestroyed {
+            large,
+            at: Vec2::new(0., 0.),
+            player: 0,
+        }
+    }
+
+    /// Updates until `done`, up to a minute's worth
+    fn ticks_until(effects: &mut Effects, done: impl Fn(&Effects) -> bool) -> u32 {
+        let mut rng = StdRng::seed_from_u64(1);
+        (0..3600)
+            .find(|_| {
+                effects.update(DT, &mut rng);
+                done(effects)
+            })
+            .map_or(3600, |ticks| ticks + 1)
+    }
+
+    #[test]
+    fn shake_builds_up_and_decays() {
+        let mut effects = Effects::new(ALL);
+        effects.event(&asteroid(false));
+        assert_eq!(effects.shake, SHAKE_SMALL);
+        effects.event(&asteroid(true));
+        assert_eq!(effects.shake, SHAKE_SMALL + SHAKE_LARGE);
+        effects.event(&GameEvent::ShipDestroyed { player: 0 });
+        assert_eq!(effects.shake, 1.);
+
+        let mut rng = StdRng::seed_from_u64(1);
+        effects.update(DT, &mut rng);
+        let max = MAX_SHAKE * effects.shake * effects.shake;
+        assert!(effects.offset().x.abs() <= max && effects.offset().y.abs() <= max);
+
+        // A full shake is gone in two thirds of a second, 40 ticks
+        let ticks = 1 + ticks_until(&mut effects, |effects| effects.shake == 0.);
+        assert!((40..=41).contains(&ticks), "{}", ticks);
+        assert_eq!(effects.offset().x, 0.);
+        assert_eq!(effects.offset().y, 0.);
+    }
+
+    #[test]
+    fn hit_stop_freezes_for_a_fifth_of_a_second() {
+        let mut effects = Effects::new(ALL);
+        effects.event(&asteroid(true));
+        assert!(!effects.is_frozen());
+
+        effects.event(&GameEvent::ShipDestroyed { player: 1 });
+        assert!(effects.is_frozen());
+        let ticks = ticks_until(&mut effects, |effects| !effects.is_frozen());
+        assert!((12..=13).contains(&ticks), "{}", ticks);
+    }
+
+    #[test]
+    fn flash_fades_out() {
+        let mut effects = Effects::new(ALL);
+        effects.event(&GameEvent::Fired);
+        assert_eq!(effects.flash, 0.);
+
+        effects.event(&GameEvent::ExtraLife);
+        assert_eq!(effects.flash, FLASH_TIME);
+        let ticks = ticks_until(&mut effects, |effects| effects.flash == 0.);
+        assert!((21..=22).contains(&ticks), "{}", ticks);
+
+        effects.event(&GameEvent::Hyperspace);
+        assert_eq!(effects.flash, FLASH_TIME);
+    }
+
+    #[test]
+    fn settings_turn_effects_off() {
+        let mut effects = Effects::new(NONE);
+        for event in [
+            asteroid(true),
+            GameEvent::ShipDestroyed { player: 0 },
+            GameEvent::ExtraLife,
+            GameEvent::Hyperspace,
+        ] {
+            effects.event(&event);
+        }
+
+        assert_eq!(effects.shake, 0.);
+        assert!(!effects.is_frozen());
+        assert_eq!(effects.flash, 0.);
+
+        // Each setting only turns off its own effect
+        let mut effects = Effects::new(EffectSettings {
+            shake: false,
+            ..ALL
+        });
+        effects.event(&GameEvent::ShipDestroyed { player: 0 });
+        assert_eq!(effects.shake, 0.);
+        assert!(effects.is_frozen());
+    }
+}
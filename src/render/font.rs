@@ -0,0 +1,90 @@
+use super::atlas::Atlas;
+use crate::assets::FontSource;
+use sdl2::{
+    render::TextureCreator,
+    rwops::RWops,
+    ttf::{Font, Sdl2TtfContext},
+    video::WindowContext,
+};
+use std::collections::HashMap;
+
+/// Most measured strings to remember at each size before starting over,
+/// so a counter ticking up forever doesn't grow the cache forever
+const MAX_WIDTHS: usize = 1024;
+
+pub fn load_font<'ttf>(
+    ttf_context: &'ttf Sdl2TtfContext,
+    source: &FontSource,
+    size: u16,
+) -> Result<Font<'ttf, 'static>, String> {
+    let mut font = match source {
+        FontSource::File(path) => ttf_context
+            .load_font(path, size)
+            .map_err(|e| format!("{}: {}", path.display(), e))?,
+        FontSource::Embedded(bytes) => {
+            ttf_context.load_font_from_rwops(RWops::from_bytes(bytes)?, size)?
+        }
+    };
+    font.set_style(sdl2::ttf::FontStyle::BOLD);
+
+    Ok(font)
+}
+
+/// The game font, rendered into a glyph atlas the first time each size is
+/// asked for, along with the widths of strings already measured
+pub struct Fonts<'ttf, 'tc> {
+    ttf_context: &'ttf Sdl2TtfContext,
+    texture_creator: &'tc TextureCreator<WindowContext>,
+    source: &'ttf FontSource,
+    atlases: HashMap<u16, Atlas<'tc>>,
+    widths: HashMap<u16, HashMap<String, f32>>,
+}
+
+impl<'ttf, 'tc> Fonts<'ttf, 'tc> {
+    pub fn new(
+        ttf_context: &'ttf Sdl2TtfContext,
+        texture_creator: &'tc TextureCreator<WindowContext>,
+        source: &'ttf FontSource,
+    ) -> Self {
+        Self {
+            ttf_context,
+            texture_creator,
+            source,
+            atlases: HashMap::new(),
+            widths: HashMap::new(),
+        }
+    }
+
+    /// The atlas for `size` pixels, rounded to a whole size
+    pub fn atlas(&mut self, size: f32) -> Result<&mut Atlas<'tc>, String> {
+        let size = round(size);
+
+        if !self.atlases.contains_key(&size) {
+            let font = load_font(self.ttf_context, self.source, size)?;
+            self.atlases
+                .insert(size, Atlas::new(&font, self.texture_creator)?);
+        }
+
+        Ok(self.atlases.get_mut(&size).unwrap())
+    }
+
+    pub fn text_width(&mut self, text: &str, size: f32) -> Result<f32, String> {
+        if let Some(&width) = self.widths.get(&round(size)).and_then(|w| w.get(text)) {
+            return Ok(width);
+        }
+
+        let width = self.atlas(size)?.width(text) as f32;
+
+        let widths = self.widths.entry(round(size)).or_default();
+        if widths.len() >= MAX_WIDTHS {
+            widths.clear();
+        }
+        widths.insert(text.to_string(), width);
+
+        Ok(width)
+    }
+}
+
+fn round(size: f32) -> u16 {
+    size.round().max(1.) as u16
+}
@@ -0,0 +1,183 @@
+//! Golden image tests for the software renderer. Each test draws a fixed
+//! scene and compares it with a reference PNG in `tests/golden`, run with
+//! `UPDATE_GOLDEN=1` to write new references after an intended change
+
+use super::{capture, hud, renderer::Renderer, software::SoftwareRenderer};
+use crate::{
+    entity::{asteroid, ship, system, world::World},
+    game::{
+        input::Input,
+        rules::{Difficulty, GameMode, Players, Rules},
+        Game, TICK,
+    },
+    math::{playfield::Playfield, rng::GameRng},
+};
+use sdl2::pixels::Color;
+use std::{env, fs, path::PathBuf};
+
+const SIZE: f32 = 300.;
+/// Largest difference in any channel before a pixel counts as changed
+const CHANNEL_TOLERANCE: u8 = 16;
+/// Fraction of pixels allowed to change, for tiny rounding differences
+const PIXEL_TOLERANCE: f32 = 0.001;
+
+fn field() -> Playfield {
+    Playfield::new(SIZE, SIZE)
+}
+
+fn buffer(field: &Playfield) -> SoftwareRenderer {
+    let mut buffer = SoftwareRenderer::new(field.width as u32, field.height as u32);
+    buffer.clear(Color::BLACK);
+    buffer
+}
+
+fn golden_dir() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/golden")
+}
+
+fn read_png(path: &PathBuf) -> Result<(u32, u32, Vec<u8>), String> {
+    let file = fs::File::open(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    let mut reader = png::Decoder::new(file)
+        .read_info()
+        .map_err(|e| e.to_string())?;
+
+    let mut pixels = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut pixels).map_err(|e| e.to_string())?;
+    if info.color_type != png::ColorType::Rgba || info.bit_depth != png::BitDepth::Eight {
+        return Err(format!("{} is not 8 bit RGBA", path.display()));
+    }
+    pixels.truncate(info.buffer_size());
+
+    Ok((info.width, info.height, pixels))
+}
+
+/// Compare a frame with its reference image. On a mismatch the frame and
+/// a diff, changed pixels in red over a dimmed reference, are written to
+/// `target/golden` before failing
+fn assert_golden(name: &str, buffer: &SoftwareRenderer) {
+    let reference = golden_dir().join(format!("{}.png", name));
+
+    if env::var_os("UPDATE_GOLDEN").is_some() {
+        fs::create_dir_all(golden_dir()).unwrap();
+        capture::save_png(&reference, buffer).unwrap();
+        return;
+    }
+
+    let (width, height, expected) = read_png(&reference).unwrap();
+    assert_eq!(
+        (width, height),
+        (buffer.width(), buffer.height()),
+        "{} has changed size",
+        name
+    );
+
+    let mut diff = Vec::with_capacity(expected.len());
+    let mut changed = 0;
+    for (want, got) in expected
+        .chunks_exact(4)
+        .zip(buffer.pixels().chunks_exact(4))
+    {
+        let distance = want
+            .iter()
+            .zip(got)
+            .map(|(a, b)| a.abs_diff(*b))
+            .max()
+            .unwrap();
+
+        if distance > CHANNEL_TOLERANCE {
+            changed += 1;
+            diff.extend_from_slice(&[255, 0, 0, 255]);
+        } else {
+            diff.extend(want[..3].iter().map(|c| c / 4));
+            diff.push(255);
+        }
+    }
+
+    let allowed = ((width * height) as f32 * PIXEL_TOLERANCE) as usize;
+    if changed > allowed {
+        let out = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("target/golden");
+        fs::create_dir_all(&out).unwrap();
+        capture::save_png(&out.join(format!("{}-actual.png", name)), buffer).unwrap();
+        capture::write_png(
+            &out.join(format!("{}-diff.png", name)),
+            width,
+            height,
+            &diff,
+        )
+        .unwrap();
+
+        panic!(
+            "{} differs from its reference in {} pixels (allowed {}), see {}",
+            name,
+            changed,
+            allowed,
+            out.display()
+        );
+    }
+}
+
+#[test]
+fn ship_turned() {
+    let field = field();
+    let mut world = World::default();
+    let id = world.spawn(ship::at(&field.center(), 0));
+
+    // A quarter second of turning left
+    let left = Input {
+        left: true,
+        ..Input::default()
+    };
+    for _ in 0..15 {
+        ship::apply(world.get_mut(id).unwrap(), left, TICK);
+        system::thrust(&mut world, TICK);
+        system::movement(&mut world, TICK);
+    }
+
+    let mut buffer = buffer(&field);
+    system::render(&world, &mut buffer, &field, |_| Color::WHITE);
+
+    assert_golden("ship_turned", &buffer);
+}
+
+#[test]
+fn asteroid_wrapped() {
+    let field = field();
+
+    // A radius range of one gives a regular polygon, here straddling the
+    // left edge so its ghost shows on the right
+    let mut world = World::default();
+    world.spawn(asteroid::new(
+        40,
+        41,
+        10.,
+        150.,
+        2.,
+        &field,
+        &mut GameRng::new(1),
+    ));
+    system::wrap(&mut world, &field);
+
+    let mut buffer = buffer(&field);
+    system::render(&world, &mut buffer, &field, |_| Color::WHITE);
+
+    assert_golden("asteroid_wrapped", &buffer);
+}
+
+#[test]
+fn hud_layout() {
+    let rules = Rules {
+        difficulty: Difficulty::Normal,
+        mode: GameMode::Classic,
+        players: Players::Single,
+        // Wide enough for the whole HUD to fit without overlapping
+        field: Playfield::new(SIZE * 2., SIZE),
+    };
+    let mut game = Game::new(1, rules);
+    game.players[0].score = 1230;
+    game.wave = 4;
+
+    let mut buffer = buffer(&rules.field);
+    hud::draw(&mut buffer, &game, 5000);
+
+    assert_golden("hud", &buffer);
+}
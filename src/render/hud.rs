@@ -0,0 +1,134 @@
+use super::{
+    renderer::Renderer,
+    text::{line_height, Anchor, Label, MARGIN},
+};
+use crate::{game::Game, math::vec2::Vec2};
+use sdl2::pixels::Color;
+
+const SIZE: f32 = 24.;
+const SMALL: f32 = 16.;
+/// Spacing between the ships showing the lives left
+const LIFE_SPACING: f32 = 18.;
+
+/// Height of a player's score and lives when there is more than one
+const PANEL: f32 = 56.;
+
+/// Ships in multiplayer games are told apart by colour
+const PLAYER_COLORS: [Color; 4] = [
+    Color::RGB(110, 200, 255),
+    Color::RGB(255, 170, 80),
+    Color::RGB(140, 255, 120),
+    Color::RGB(255, 120, 220),
+];
+
+/// Colour of a player's ship, plain white when playing alone
+pub fn player_color(game: &Game, player: usize) -> Color {
+    if game.players.len() == 1 {
+        Color::WHITE
+    } else {
+        PLAYER_COLORS[player % PLAYER_COLORS.len()]
+    }
+}
+
+/// Score, high score, wave, lives, rules and lasers around the edge of the
+/// playfield. With more players each gets a panel in their own colour down
+/// the top corners instead
+pub fn draw(renderer: &mut dyn Renderer, game: &Game, high_score: u32) {
+    let field = &game.rules.field;
+
+    let player = match game.players.as_slice() {
+        [player] => player,
+        _ => return draw_players(renderer, game),
+    };
+
+    Label::new(&format!("Score {}", player.score), Anchor::TopLeft)
+        .size(SIZE)
+        .draw(renderer, field);
+    Label::new(&format!("Hi {}", high_score.max(player.score)), Anchor::Top)
+        .size(SMALL)
+        .color(Color::GRAY)
+        .draw(renderer, field);
+    Label::new(&format!("Wave {}", game.wave), Anchor::TopRight)
+        .size(SIZE)
+        .draw(renderer, field);
+    Label::new(
+        &format!("{} {}", game.rules.mode, game.rules.difficulty),
+        Anchor::BottomLeft,
+    )
+    .size(SMALL)
+    .color(Color::GRAY)
+    .draw(renderer, field);
+    Label::new(
+        &format!("Laser  {} in flight", game.lasers(0).count()),
+        Anchor::BottomRight,
+    )
+    .size(SMALL)
+    .color(Color::GRAY)
+    .draw(renderer, field);
+
+    // A little ship for each life under the score
+    lives(
+        renderer,
+        player.lives,
+        MARGIN + 5.,
+        0.,
+        LIFE_SPACING,
+        Color::WHITE,
+    );
+}
+
+fn draw_players(renderer: &mut dyn Renderer, game: &Game) {
+    let field = &game.rules.field;
+
+    for (i, player) in game.players.iter().enumerate() {
+        let color = if player.is_out() {
+            Color::GRAY
+        } else {
+            player_color(game, i)
+        };
+        // Odd players on the left, even on the right, three and four
+        // under one and two
+        let (anchor, x, step) = if i % 2 == 0 {
+            (Anchor::TopLeft, MARGIN + 5., LIFE_SPACING)
+        } else {
+            (Anchor::TopRight, field.width - MARGIN - 5., -LIFE_SPACING)
+        };
+        let y = (i / 2) as f32 * PANEL;
+
+        Label::new(&format!("P{}  {}", i + 1, player.score), anchor)
+            .size(SIZE)
+            .color(color)
+            .offset(0., y)
+            .draw(renderer, field);
+        lives(renderer, player.lives, x, y, step, color);
+    }
+
+    Label::new(&format!("Wave {}", game.wave), Anchor::Top)
+        .size(SMALL)
+        .color(Color::GRAY)
+        .draw(renderer, field);
+    Label::new(
+        &format!(
+            "{} {} {}",
+            game.rules.players, game.rules.mode, game.rules.difficulty
+        ),
+        Anchor::BottomLeft,
+    )
+    .size(SMALL)
+    .color(Color::GRAY)
+    .draw(renderer, field);
+}
+
+/// A row of little ships under a score `y` down, from `x` along by `step`
+fn lives(renderer: &mut dyn Renderer, count: u32, x: f32, y: f32, step: f32, color: Color) {
+    let top = MARGIN + y + line_height(SIZE) + 4.;
+    for i in 0..count {
+        let x = x + i as f32 * step;
+        let ship = [
+            Vec2::new(x, top),
+            Vec2::new(x + 5., top + 14.),
+            Vec2::new(x - 5., top + 14.),
+        ];
+        renderer.filled_polygon(&ship, color);
+    }
+}
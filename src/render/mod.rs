@@ -1,2 +1,14 @@
-mod text;
+mod atlas;
+pub mod capture;
+pub mod effects;
+pub mod font;
+#[cfg(test)]
+mod golden;
+pub mod hud;
+pub mod phosphor;
+pub mod renderer;
+pub mod scene;
+pub mod software;
+pub mod text;
+mod vector_font;
 pub mod window;
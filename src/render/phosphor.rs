@@ -0,0 +1,228 @@
+use super::software::SoftwareRenderer;
+
+/// Seconds for a trail to fade to half its brightness
+const HALF_LIFE: f32 = 0.05;
+/// Trails dimmer than this are cleared
+const BLACK: f32 = 1. / 512.;
+/// Radius of the halo, in the half size pixels it is worked out at
+const GLOW_RADIUS: usize = 2;
+/// How bright the halo is next to the line, for each channel. It spreads
+/// a little bluer than the beam, like a P7 phosphor
+const GLOW: [f32; 3] = [1.1, 1.35, 1.6];
+
+/// A vector monitor look for software rendered frames: lines leave fading
+/// trails and light up a soft halo around themselves. Each channel glows
+/// on its own so coloured lines keep their colour
+pub struct Phosphor {
+    width: usize,
+    height: usize,
+    /// Brightness still glowing at each pixel, red, green and blue in turn
+    lit: Vec<f32>,
+    /// The halo for each channel at half the size each way, since it is
+    /// blurry anyway
+    glow: [Vec<f32>; 3],
+    scratch: Vec<f32>,
+}
+
+impl Phosphor {
+    pub fn new(width: u32, height: u32) -> Self {
+        let (width, height) = (width as usize, height as usize);
+        let half = half(width) * half(height);
+
+        Self {
+            width,
+            height,
+            lit: vec![0.; width * height * 3],
+            glow: [vec![0.; half], vec![0.; half], vec![0.; half]],
+            scratch: vec![0.; half],
+        }
+    }
+
+    /// Mix a freshly drawn frame into what is still glowing from earlier
+    /// ones, `dt` seconds ago, and replace it with the result
+    pub fn apply(&mut self, buffer: &mut SoftwareRenderer, dt: f32) {
+        if buffer.width() as usize != self.width || buffer.height() as usize != self.height {
+            *self = Phosphor::new(buffer.width(), buffer.height());
+        }
+
+        let decay = 0.5_f32.powf(dt / HALF_LIFE);
+        let pixels = buffer.pixels_mut();
+        let (width, glow_width) = (self.width, half(self.width));
+
+        // The beam lights a pixel fully, then it fades over the next frames.
+        // Anything too dim to see goes straight to black, as floats fading
+        // towards zero forever get very slow. The halo is shrunk to half
+        // size on the way by averaging each 2x2 block
+        for glow in self.glow.iter_mut() {
+            glow.iter_mut().for_each(|v| *v = 0.);
+        }
+        for (y, (lit, pixels)) in self
+            .lit
+            .chunks_exact_mut(width * 3)
+            .zip(pixels.chunks_exact(width * 4))
+            .enumerate()
+        {
+            let row = y / 2 * glow_width;
+
+            for (x, (lit, pixel)) in lit
+                .chunks_exact_mut(3)
+                .zip(pixels.chunks_exact(4))
+                .enumerate()
+            {
+                for (c, lit) in lit.iter_mut().enumerate() {
+                    let faded = *lit * decay;
+                    let faded = if faded < BLACK { 0. } else { faded };
+                    let beam = pixel[c] as f32 / 255.;
+
+                    *lit = beam.max(faded);
+                    self.glow[c][row + x / 2] += *lit * 0.25;
+                }
+            }
+        }
+
+        // Two box blurs make a rough gaussian halo that falls off smoothly
+        for glow in self.glow.iter_mut() {
+            for _ in 0..2 {
+                box_blur(glow, &mut self.scratch, glow_width, half(self.height));
+            }
+        }
+
+        for (y, (lit, pixels)) in self
+            .lit
+            .chunks_exact(width * 3)
+            .zip(pixels.chunks_exact_mut(width * 4))
+            .enumerate()
+        {
+            let row = y / 2 * glow_width;
+
+            for (x, (lit, pixel)) in lit
+                .chunks_exact(3)
+                .zip(pixels.chunks_exact_mut(4))
+                .enumerate()
+            {
+                for c in 0..3 {
+                    let halo = self.glow[c][row + x / 2];
+                    pixel[c] = ((lit[c] + halo * GLOW[c]).min(1.) * 255.) as u8;
+                }
+            }
+        }
+    }
+}
+
+/// Pixels needed to cover `n` at half size
+fn half(n: usize) -> usize {
+    n.div_ceil(2)
+}
+
+/// Blur over a square of `GLOW_RADIUS` each way, using `tmp` for the pass
+/// between rows and columns. Anything past the edges counts as black
+fn box_blur(data: &mut [f32], tmp: &mut [f32], width: usize, height: usize) {
+    let scale = 1. / (2 * GLOW_RADIUS + 1) as f32;
+
+    // Along each row with a running sum
+    for (src, dst) in data.chunks_exact(width).zip(tmp.chunks_exact_mut(width)) {
+        let mut sum: f32 = src.iter().take(GLOW_RADIUS).sum();
+
+        for x in 0..width {
+            if x + GLOW_RADIUS < width {
+                sum += src[x + GLOW_RADIUS];
+            }
+            dst[x] = sum * scale;
+            if x >= GLOW_RADIUS {
+                sum -= src[x - GLOW_RADIUS];
+            }
+        }
+    }
+
+    // Down the columns a whole row at a time, which keeps to memory order
+    let mut sums = vec![0.; width];
+    for src in tmp.chunks_exact(width).take(GLOW_RADIUS) {
+        sums.iter_mut().zip(src).for_each(|(sum, v)| *sum += v);
+    }
+    for y in 0..height {
+        if y + GLOW_RADIUS < height {
+            let src = &tmp[(y + GLOW_RADIUS) * width..][..width];
+            sums.iter_mut().zip(src).for_each(|(sum, v)| *sum += v);
+        }
+
+        let dst = &mut data[y * width..][..width];
+        dst.iter_mut()
+            .zip(&sums)
+            .for_each(|(d, sum)| *d = sum * scale);
+
+        if y >= GLOW_RADIUS {
+            let src = &tmp[(y - GLOW_RADIUS) * width..][..width];
+            sums.iter_mut().zip(src).for_each(|(sum, v)| *sum -= v);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{math::vec2::Vec2, render::renderer::Renderer};
+    use sdl2::pixels::Color;
+
+    const SIZE: u32 = 40;
+
+    fn pixel(buffer: &SoftwareRenderer, x: u32, y: u32) -> [u8; 3] {
+        let i = ((y * SIZE + x) * 4) as usize;
+        let p = &buffer.pixels()[i..i + 3];
+        [p[0], p[1], p[2]]
+    }
+
+    fn brightness(buffer: &SoftwareRenderer, x: u32, y: u32) -> u32 {
+        pixel(buffer, x, y).iter().map(|&c| c as u32).sum()
+    }
+
+    fn dot(color: Color) -> SoftwareRenderer {
+        let mut buffer = SoftwareRenderer::new(SIZE, SIZE);
+        buffer.clear(Color::BLACK);
+        buffer.circle(&Vec2::new(20., 20.), 3., color);
+        buffer
+    }
+
+    #[test]
+    fn lit_pixels_spread_and_fade() {
+        let mut phosphor = Phosphor::new(SIZE, SIZE);
+        let mut frame = dot(Color::WHITE);
+        phosphor.apply(&mut frame, 1. / 60.);
+
+        // The halo lights up what was black around the dot
+        assert_eq!(brightness(&dot(Color::WHITE), 26, 20), 0);
+        let halo = brightness(&frame, 26, 20);
+        assert!(halo > 0);
+        assert!(brightness(&frame, 20, 20) > halo);
+
+        // Then with nothing drawn it fades away to black
+        let mut last = brightness(&frame, 20, 20);
+        for _ in 0..30 {
+            let mut blank = SoftwareRenderer::new(SIZE, SIZE);
+            blank.clear(Color::BLACK);
+            phosphor.apply(&mut blank, 1. / 60.);
+            let now = brightness(&blank, 20, 20);
+            assert!(now <= last);
+            last = now;
+        }
+        assert_eq!(last, 0);
+    }
+
+    #[test]
+    fn colours_keep_their_hue() {
+        let orange = Color::RGB(255, 170, 80);
+        let blue = Color::RGB(110, 200, 255);
+
+        let glowing = |color| {
+            let mut frame = dot(color);
+            Phosphor::new(SIZE, SIZE).apply(&mut frame, 1. / 60.);
+            (pixel(&frame, 20, 20), pixel(&frame, 25, 20))
+        };
+
+        for (center, halo) in [glowing(orange)] {
+            assert!(center[0] > center[2] && halo[0] > halo[2]);
+        }
+        for (center, halo) in [glowing(blue)] {
+            assert!(center[2] > center[0] && halo[2] > halo[0]);
+        }
+    }
+}
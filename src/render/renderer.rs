@@ -0,0 +1,139 @@
+use super::font::Fonts;
+use crate::math::vec2::{Vec2, Vec2Vec};
+use sdl2::{gfx::primitives::DrawRenderer, pixels::Color, render::Canvas, video::Window};
+
+/// Drawing operations the game needs, so a frame can be drawn to a window
+/// or to a buffer in memory
+pub trait Renderer {
+    fn clear(&mut self, color: Color);
+
+    /// Outline of a closed polygon
+    fn polygon(&mut self, points: &[Vec2], color: Color);
+
+    fn filled_polygon(&mut self, points: &[Vec2], color: Color);
+
+    /// Filled circle
+    fn circle(&mut self, center: &Vec2, radius: f32, color: Color);
+
+    fn line(&mut self, from: &Vec2, to: &Vec2, color: Color);
+
+    /// A line of text with its top left corner at `pos`, at a font size of
+    /// `size` pixels
+    fn text(&mut self, text: &str, pos: &Vec2, size: f32, color: Color);
+
+    /// How wide a line of text would be drawn
+    fn text_width(&mut self, text: &str, size: f32) -> f32;
+}
+
+/// Draws straight to the window with SDL_gfx, and SDL_ttf for text
+pub struct SdlRenderer<'a, 'ttf, 'tc> {
+    canvas: &'a mut Canvas<Window>,
+    fonts: &'a mut Fonts<'ttf, 'tc>,
+}
+
+impl<'a, 'ttf, 'tc> SdlRenderer<'a, 'ttf, 'tc> {
+    pub fn new(canvas: &'a mut Canvas<Window>, fonts: &'a mut Fonts<'ttf, 'tc>) -> Self {
+        Self { canvas, fonts }
+    }
+}
+
+impl Renderer for SdlRenderer<'_, '_, '_> {
+    fn clear(&mut self, color: Color) {
+        self.canvas.set_draw_color(color);
+        self.canvas.clear();
+    }
+
+    fn polygon(&mut self, points: &[Vec2], color: Color) {
+        let (x, y) = points.convert_to_xy_vec();
+        let _ = self.canvas.aa_polygon(&x, &y, color);
+    }
+
+    fn filled_polygon(&mut self, points: &[Vec2], color: Color) {
+        let (x, y) = points.convert_to_xy_vec();
+        let _ = self.canvas.filled_polygon(&x, &y, color);
+    }
+
+    fn circle(&mut self, center: &Vec2, radius: f32, color: Color) {
+        let _ = self
+            .canvas
+            .filled_circle(center.x as i16, center.y as i16, radius as i16, color);
+    }
+
+    fn line(&mut self, from: &Vec2, to: &Vec2, color: Color) {
+        let _ = self.canvas.aa_line(
+            from.x as i16,
+            from.y as i16,
+            to.x as i16,
+            to.y as i16,
+            color,
+        );
+    }
+
+    fn text(&mut self, text: &str, pos: &Vec2, size: f32, color: Color) {
+        if let Ok(atlas) = self.fonts.atlas(size) {
+            let _ = atlas.draw(self.canvas, text, pos.x as i32, pos.y as i32, color);
+        }
+    }
+
+    fn text_width(&mut self, text: &str, size: f32) -> f32 {
+        self.fonts.text_width(text, size).unwrap_or(0.)
+    }
+}
+
+/// Draws through to another renderer with everything moved by an offset
+pub struct Shifted<'a> {
+    inner: &'a mut dyn Renderer,
+    by: Vec2,
+}
+
+impl<'a> Shifted<'a> {
+    pub fn new(inner: &'a mut dyn Renderer, by: &Vec2) -> Self {
+        Self {
+            inner,
+            by: by.clone(),
+        }
+    }
+
+    fn shift(&self, point: &Vec2) -> Vec2 {
+        Vec2::new(point.x + self.by.x, point.y + self.by.y)
+    }
+
+    fn shift_all(&self, points: &[Vec2]) -> Vec<Vec2> {
+        points.iter().map(|p| self.shift(p)).collect()
+    }
+}
+
+impl Renderer for Shifted<'_> {
+    fn clear(&mut self, color: Color) {
+        self.inner.clear(color);
+    }
+
+    fn polygon(&mut self, points: &[Vec2], color: Color) {
+        let points = self.shift_all(points);
+        self.inner.polygon(&points, color);
+    }
+
+    fn filled_polygon(&mut self, points: &[Vec2], color: Color) {
+        let points = self.shift_all(points);
+        self.inner.filled_polygon(&points, color);
+    }
+
+    fn circle(&mut self, center: &Vec2, radius: f32, color: Color) {
+        let center = self.shift(center);
+        self.inner.circle(&center, radius, color);
+    }
+
+    fn line(&mut self, from: &Vec2, to: &Vec2, color: Color) {
+        let (from, to) = (self.shift(from), self.shift(to));
+        self.inner.line(&from, &to, color);
+    }
+
+    fn text(&mut self, text: &str, pos: &Vec2, size: f32, color: Color) {
+        let pos = self.shift(pos);
+        self.inner.text(text, &pos, size, color);
+    }
+
+    fn text_width(&mut self, text: &str, size: f32) -> f32 {
+        self.inner.text_width(text, size)
+    }
+}
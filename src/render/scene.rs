@@ -0,0 +1,36 @@
+use super::{
+    effects::Effects,
+    hud,
+    renderer::{Renderer, Shifted},
+};
+use crate::{
+    entity::{star::Starfield, system},
+    game::Game,
+};
+use sdl2::pixels::Color;
+
+/// Draw the stars, ships and asteroids over a cleared frame
+pub fn draw(renderer: &mut dyn Renderer, stars: &Starfield, game: &Game) {
+    let field = &game.rules.field;
+
+    renderer.clear(Color::BLACK);
+
+    stars.draw(renderer);
+    system::render(&game.world, renderer, field, |player| {
+        hud::player_color(game, player)
+    });
+}
+
+/// A whole frame of a game in progress: the playfield moved by any shake,
+/// the HUD kept still and any flash over the top
+pub fn draw_game(
+    renderer: &mut dyn Renderer,
+    stars: &Starfield,
+    game: &Game,
+    high_score: u32,
+    effects: &Effects,
+) {
+    draw(&mut Shifted::new(renderer, effects.offset()), stars, game);
+    hud::draw(renderer, game, high_score);
+    effects.draw_flash(renderer, &game.rules.field);
+}
@@ -0,0 +1,176 @@
+use super::{renderer::Renderer, vector_font};
+use crate::math::vec2::Vec2;
+use sdl2::pixels::Color;
+
+/// Where capital letters sit in a line of the game font, as fractions of
+/// the font size, so text lays out the same as in the window
+const CAP_TOP: f32 = 0.36;
+const CAP_HEIGHT: f32 = 0.71;
+
+/// Rasterises into an RGBA buffer in memory, needs no window or GPU
+pub struct SoftwareRenderer {
+    width: u32,
+    height: u32,
+    pixels: Vec<u8>,
+}
+
+impl SoftwareRenderer {
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            width,
+            height,
+            pixels: vec![0; (width * height * 4) as usize],
+        }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Rows of RGBA bytes, top to bottom
+    pub fn pixels(&self) -> &[u8] {
+        &self.pixels
+    }
+
+    pub fn pixels_mut(&mut self) -> &mut [u8] {
+        &mut self.pixels
+    }
+
+    /// Alpha blend a colour onto a pixel, ignoring anything off the buffer
+    fn blend(&mut self, x: i32, y: i32, color: Color) {
+        if x < 0 || y < 0 || x >= self.width as i32 || y >= self.height as i32 {
+            return;
+        }
+
+        let i = ((y as u32 * self.width + x as u32) * 4) as usize;
+        let a = color.a as u32;
+        for (c, src) in [color.r, color.g, color.b].iter().enumerate() {
+            let dst = self.pixels[i + c] as u32;
+            self.pixels[i + c] = ((*src as u32 * a + dst * (255 - a)) / 255) as u8;
+        }
+        self.pixels[i + 3] = 255;
+    }
+
+    /// Fill the pixels between two x positions on a row, by pixel centres
+    fn span(&mut self, y: i32, from: f32, to: f32, color: Color) {
+        let start = (from - 0.5).ceil() as i32;
+        let end = (to - 0.5).floor() as i32;
+        for x in start.max(0)..=end.min(self.width as i32 - 1) {
+            self.blend(x, y, color);
+        }
+    }
+}
+
+impl Renderer for SoftwareRenderer {
+    fn clear(&mut self, color: Color) {
+        for pixel in self.pixels.chunks_exact_mut(4) {
+            pixel.copy_from_slice(&[color.r, color.g, color.b, 255]);
+        }
+    }
+
+    fn polygon(&mut self, points: &[Vec2], color: Color) {
+        for (i, from) in points.iter().enumerate() {
+            self.line(from, &points[(i + 1) % points.len()], color);
+        }
+    }
+
+    /// Even-odd scanline fill sampled at pixel centres
+    fn filled_polygon(&mut self, points: &[Vec2], color: Color) {
+        if points.len() < 3 {
+            return;
+        }
+
+        let min_y = points.iter().map(|p| p.y).fold(f32::MAX, f32::min);
+        let max_y = points.iter().map(|p| p.y).fold(f32::MIN, f32::max);
+
+        let mut crossings = Vec::new();
+        for y in (min_y.floor() as i32).max(0)..=(max_y.ceil() as i32).min(self.height as i32 - 1) {
+            let yc = y as f32 + 0.5;
+
+            crossings.clear();
+            for (i, a) in points.iter().enumerate() {
+                let b = &points[(i + 1) % points.len()];
+                if (a.y <= yc) != (b.y <= yc) {
+                    crossings.push(a.x + (yc - a.y) * (b.x - a.x) / (b.y - a.y));
+                }
+            }
+            crossings.sort_by(|a, b| a.total_cmp(b));
+
+            for pair in crossings.chunks_exact(2) {
+                self.span(y, pair[0], pair[1], color);
+            }
+        }
+    }
+
+    fn circle(&mut self, center: &Vec2, radius: f32, color: Color) {
+        let top = (center.y - radius).floor() as i32;
+        let bottom = (center.y + radius).ceil() as i32;
+
+        for y in top..=bottom {
+            let dy = y as f32 + 0.5 - center.y;
+            let half = radius * radius - dy * dy;
+            if half >= 0. {
+                let half = half.sqrt();
+                self.span(y, center.x - half, center.x + half, color);
+            }
+        }
+    }
+
+    /// Bresenham line between the pixels containing each end
+    fn line(&mut self, from: &Vec2, to: &Vec2, color: Color) {
+        let (mut x0, mut y0) = (from.x.floor() as i32, from.y.floor() as i32);
+        let (x1, y1) = (to.x.floor() as i32, to.y.floor() as i32);
+
+        let dx = (x1 - x0).abs();
+        let dy = -(y1 - y0).abs();
+        let sx = if x0 < x1 { 1 } else { -1 };
+        let sy = if y0 < y1 { 1 } else { -1 };
+        let mut err = dx + dy;
+
+        loop {
+            self.blend(x0, y0, color);
+            if x0 == x1 && y0 == y1 {
+                break;
+            }
+
+            let e2 = 2 * err;
+            if e2 >= dy {
+                err += dy;
+                x0 += sx;
+            }
+            if e2 <= dx {
+                err += dx;
+                y0 += sy;
+            }
+        }
+    }
+
+    /// Text in a simple built in stroke font
+    fn text(&mut self, text: &str, pos: &Vec2, size: f32, color: Color) {
+        let scale = size * CAP_HEIGHT / vector_font::HEIGHT;
+        let top = pos.y + size * CAP_TOP;
+
+        for (i, c) in text.chars().enumerate() {
+            let left = pos.x + i as f32 * vector_font::ADVANCE * scale;
+
+            for stroke in vector_font::strokes(c) {
+                let points: Vec<Vec2> = stroke
+                    .iter()
+                    .map(|(x, y)| Vec2::new(left + x * scale, top + y * scale))
+                    .collect();
+
+                for pair in points.windows(2) {
+                    self.line(&pair[0], &pair[1], color);
+                }
+            }
+        }
+    }
+
+    fn text_width(&mut self, text: &str, size: f32) -> f32 {
+        vector_font::width(text, size * CAP_HEIGHT)
+    }
+}
@@ -1,71 +1,121 @@
-use crate::render::window::SIZE;
-use sdl2::{
-    pixels::Color,
-    rect::Rect,
-    render::{Texture, TextureCreator, TextureQuery},
-    ttf::Font,
-    video::WindowContext,
-};
-
-pub struct Text<'a> {
-    pub texture: Texture<'a>,
-    pub target: Rect,
+use super::renderer::Renderer;
+use crate::math::{playfield::Playfield, vec2::Vec2};
+use sdl2::pixels::Color;
+
+/// Gap between anchored text and the edge of the playfield
+pub const MARGIN: f32 = 20.;
+/// Height of a line of text as a multiple of its size, from the game font
+const LINE_HEIGHT: f32 = 1.36;
+
+/// How the lines of a block line up with each other
+#[derive(Clone, Copy)]
+pub enum Align {
+    Left,
+    Centre,
+    Right,
 }
 
-impl<'a> Text<'_> {
-    pub fn new(
-        score: u32,
-        font: &Font,
-        texture_creator: &'a TextureCreator<WindowContext>,
-    ) -> Result<Text<'a>, String> {
-        let text = "Score: ".to_string() + &score.to_string();
-
-        let surface = font
-            .render(&text)
-            .blended(Color::WHITE)
-            .map_err(|e| e.to_string())?;
-
-        let texture = texture_creator
-            .create_texture_from_surface(&surface)
-            .map_err(|e| e.to_string())?;
-
-        let TextureQuery { width, height, .. } = texture.query();
-
-        // If the example text is too big for the screen, downscale it (and center irregardless)
-        let padding = 64;
-        Ok(Text {
-            texture,
-            target: get_centered_rect(width, height, SIZE as u32 - padding, SIZE as u32 - padding),
-        })
-    }
+/// The point of the playfield a block of text is placed from
+#[derive(Clone, Copy)]
+pub enum Anchor {
+    TopLeft,
+    Top,
+    TopRight,
+    Centre,
+    BottomLeft,
+    Bottom,
+    BottomRight,
+}
+
+/// Height of one line of text at `size`
+pub fn line_height(size: f32) -> f32 {
+    size * LINE_HEIGHT
+}
+
+/// A block of text, a line for each `\n`, placed against an edge or corner
+/// of the playfield
+pub struct Label<'a> {
+    text: &'a str,
+    anchor: Anchor,
+    offset: Vec2,
+    size: f32,
+    color: Color,
+    align: Align,
 }
 
-/// Handle the annoying Rect i32
-macro_rules! rect(
-    ($x:expr, $y:expr, $w:expr, $h:expr) => (
-        Rect::new($x as i32, $y as i32, $w as u32, $h as u32)
-    )
-);
-
-/// Scale fonts to a reasonable size when they're too big (though they might look less smooth)
-fn get_centered_rect(rect_width: u32, rect_height: u32, cons_width: u32, cons_height: u32) -> Rect {
-    let wr = rect_width as f32 / cons_width as f32;
-    let hr = rect_height as f32 / cons_height as f32;
-
-    let (w, h) = if wr > 1f32 || hr > 1f32 {
-        println!("Scaling down! The text will look worse!");
-        if wr > hr {
-            let h = (rect_height as f32 / wr) as i32;
-            (cons_width as i32, h)
-        } else {
-            let w = (rect_width as f32 / hr) as i32;
-            (w, cons_height as i32)
+impl<'a> Label<'a> {
+    /// White text at the default size, lined up with the side it is
+    /// anchored to
+    pub fn new(text: &'a str, anchor: Anchor) -> Self {
+        let align = match anchor {
+            Anchor::TopLeft | Anchor::BottomLeft => Align::Left,
+            Anchor::Top | Anchor::Centre | Anchor::Bottom => Align::Centre,
+            Anchor::TopRight | Anchor::BottomRight => Align::Right,
+        };
+
+        Self {
+            text,
+            anchor,
+            offset: Vec2::new(0., 0.),
+            size: 24.,
+            color: Color::WHITE,
+            align,
         }
-    } else {
-        (rect_width as i32, rect_height as i32)
-    };
+    }
 
-    let cx = (SIZE as i32 - w) / 2;
-    let cy = (SIZE as i32 - h) / 2;
-    rect!(cx, cy - (SIZE * 0.9) as i32 / 2, w, h)
+    pub fn size(mut self, size: f32) -> Self {
+        self.size = size;
+        self
+    }
+
+    pub fn color(mut self, color: Color) -> Self {
+        self.color = color;
+        self
+    }
+
+    pub fn align(mut self, align: Align) -> Self {
+        self.align = align;
+        self
+    }
+
+    /// Move the block from its anchor, right and down are positive
+    pub fn offset(mut self, x: f32, y: f32) -> Self {
+        self.offset = Vec2::new(x, y);
+        self
+    }
+
+    pub fn draw(&self, renderer: &mut dyn Renderer, field: &Playfield) {
+        let lines: Vec<(&str, f32)> = self
+            .text
+            .lines()
+            .map(|line| (line, renderer.text_width(line, self.size)))
+            .collect();
+
+        let width = lines.iter().map(|(_, w)| *w).fold(0., f32::max);
+        let height = lines.len() as f32 * line_height(self.size);
+
+        let x = match self.anchor {
+            Anchor::TopLeft | Anchor::BottomLeft => MARGIN,
+            Anchor::Top | Anchor::Centre | Anchor::Bottom => (field.width - width) / 2.,
+            Anchor::TopRight | Anchor::BottomRight => field.width - MARGIN - width,
+        } + self.offset.x;
+        let y = match self.anchor {
+            Anchor::TopLeft | Anchor::Top | Anchor::TopRight => MARGIN,
+            Anchor::Centre => (field.height - height) / 2.,
+            Anchor::BottomLeft | Anchor::Bottom | Anchor::BottomRight => {
+                field.height - MARGIN - height
+            }
+        } + self.offset.y;
+
+        for (i, (line, line_width)) in lines.iter().enumerate() {
+            let indent = match self.align {
+                Align::Left => 0.,
+                Align::Centre => (width - line_width) / 2.,
+                Align::Right => width - line_width,
+            };
+            let pos = Vec2::new(x + indent, y + i as f32 * line_height(self.size));
+
+            renderer.text(line, &pos, self.size, self.color);
+        }
+    }
 }
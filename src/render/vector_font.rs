@@ -0,0 +1,91 @@
+/// Glyphs on a 4 wide, 6 tall grid with y going down. Strokes are split
+/// by `;` and each point is a pair of digits
+const GLYPHS: [(char, &str); 54] = [
+    ('A', "06 02 20 42 46;03 43"),
+    ('B', "06 00 30 41 42 33 03;33 44 45 36 06"),
+    ('C', "40 00 06 46"),
+    ('D', "00 30 42 44 36 06 00"),
+    ('E', "40 00 06 46;03 33"),
+    ('F', "40 00 06;03 33"),
+    ('G', "40 00 06 46 43 23"),
+    ('H', "00 06;40 46;03 43"),
+    ('I', "00 40;20 26;06 46"),
+    ('J', "40 46 06 04"),
+    ('K', "00 06;40 03 46"),
+    ('L', "00 06 46"),
+    ('M', "06 00 23 40 46"),
+    ('N', "06 00 46 40"),
+    ('O', "00 40 46 06 00"),
+    ('P', "06 00 40 43 03"),
+    ('Q', "00 40 44 26 06 00;24 46"),
+    ('R', "06 00 40 43 03 46"),
+    ('S', "40 00 03 43 46 06"),
+    ('T', "00 40;20 26"),
+    ('U', "00 06 46 40"),
+    ('V', "00 26 40"),
+    ('W', "00 06 23 46 40"),
+    ('X', "00 46;40 06"),
+    ('Y', "00 23 40;23 26"),
+    ('Z', "00 40 06 46"),
+    ('0', "00 40 46 06 00;06 40"),
+    ('1', "11 20 26;06 46"),
+    ('2', "00 40 43 03 06 46"),
+    ('3', "00 40 46 06;03 43"),
+    ('4', "00 03 43;40 46"),
+    ('5', "40 00 03 43 46 06"),
+    ('6', "40 00 06 46 43 03"),
+    ('7', "00 40 46"),
+    ('8', "00 40 46 06 00;03 43"),
+    ('9', "43 03 00 40 46 06"),
+    (':', "21 22;24 25"),
+    ('.', "25 26"),
+    (',', "25 16"),
+    ('-', "03 43"),
+    ('+', "03 43;21 25"),
+    ('=', "02 42;04 44"),
+    ('/', "06 40"),
+    ('!', "20 23;25 26"),
+    ('?', "00 40 42 22 24;26 26"),
+    ('(', "30 21 25 36"),
+    (')', "10 21 25 16"),
+    ('<', "40 03 46"),
+    ('>', "00 43 06"),
+    ('_', "06 46"),
+    ('\'', "20 21"),
+    ('"', "10 11;30 31"),
+    ('*', "02 44;42 04;21 25"),
+    ('#', "10 16;30 36;02 42;04 44"),
+];
+
+/// Width of a glyph cell including spacing, in grid units
+pub const ADVANCE: f32 = 6.;
+/// Height of a glyph in grid units
+pub const HEIGHT: f32 = 6.;
+
+/// The strokes for a character as lists of grid points, lowercase letters
+/// use the uppercase glyph and unknown characters are blank
+pub fn strokes(c: char) -> Vec<Vec<(f32, f32)>> {
+    let c = c.to_ascii_uppercase();
+    let glyph = match GLYPHS.iter().find(|(g, _)| *g == c) {
+        Some((_, glyph)) => glyph,
+        None => return Vec::new(),
+    };
+
+    glyph
+        .split(';')
+        .map(|stroke| {
+            stroke
+                .split_whitespace()
+                .map(|point| {
+                    let b = point.as_bytes();
+                    ((b[0] - b'0') as f32, (b[1] - b'0') as f32)
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// Width of a line of text drawn `height` tall
+pub fn width(text: &str, height: f32) -> f32 {
+    text.chars().count() as f32 * ADVANCE * height / HEIGHT
+}
@@ -1,164 +1,1131 @@
 use crate::{
-    entity::{
-        asteroid::{Asteroid, RemoveAsteroid},
-        ship::body::Ship,
-        star::Star,
+    assets::Assets,
+    audio::{null::NullAudio, sdl::SdlAudio, Audio, AudioSettings, Heartbeat, Sound},
+    cli::Options,
+    config::Config,
+    controls::{
+        aim::TwinStick,
+        bot::Autopilot,
+        pad::{Pads, Sticks},
+        Action, Bindings, Control, Scheme,
     },
-    math::functions::get_random_radius,
-    math::vec2::wrap_verts,
-    render::text::Text,
+    entity::star::{StarSettings, Starfield},
+    game::{
+        input::Input,
+        rules::{Players, Rules},
+        Game, GameEvent, TICK,
+    },
+    math::{playfield::Playfield, vec2::Vec2},
+    mods::Mods,
+    net::{lobby::Lobby, Session},
+    render::{
+        capture,
+        effects::{EffectSettings, Effects},
+        font::{load_font, Fonts},
+        phosphor::Phosphor,
+        renderer::{Renderer, SdlRenderer, Shifted},
+        scene,
+        software::SoftwareRenderer,
+        text::{line_height, Align, Anchor, Label},
+    },
+    storage::{
+        highscore::{HighScore, HighScores, INITIALS_LEN},
+        replay::{self, Replay},
+        save,
+    },
+    stream::{client::Spectator, server::Broadcaster},
 };
-use rand::Rng;
 use sdl2::{
+    controller::Button,
     event::Event,
     keyboard::Keycode,
-    pixels::Color,
+    pixels::{Color, PixelFormatEnum},
     render::{Canvas, TextureCreator},
     ttf::Sdl2TtfContext,
-    video::{Window, WindowContext},
+    video::{FullscreenType, Window, WindowContext},
     EventPump, TimerSubsystem,
 };
 use std::path::Path;
 
-pub const SIZE: f32 = 800.;
-pub const MID_SIZE: f32 = SIZE / 2.;
-const FILE_PATH: &str = "../../assets/open-sans/OpenSans-ExtraBold.ttf";
+/// How long to let the death sound play out before leaving the game, in ms
+const GAME_OVER_DELAY: u32 = 1500;
+const HIGHLIGHT: Color = Color::RGB(255, 200, 0);
+/// Most simulation steps to catch up on in one frame after a stall
+const MAX_TICKS_PER_FRAME: u32 = 5;
+/// How long the title screen waits for a key before showing a demo, in ms
+const ATTRACT_AFTER: u32 = 20_000;
 
-pub trait GetKey {
-    fn get_keycode(&self) -> (Keycode, &Event);
+/// Keys for finding the way around menus, with the controller's d-pad and
+/// face buttons standing in for the arrows, return, backspace and escape
+fn menu_key(event: &Event) -> Option<Keycode> {
+    match *event {
+        Event::KeyDown {
+            keycode: Some(key), ..
+        } => Some(key),
+        Event::ControllerButtonDown { button, .. } => match button {
+            Button::DPadUp => Some(Keycode::Up),
+            Button::DPadDown => Some(Keycode::Down),
+            Button::DPadLeft => Some(Keycode::Left),
+            Button::DPadRight => Some(Keycode::Right),
+            Button::A | Button::Start => Some(Keycode::Return),
+            Button::B => Some(Keycode::Backspace),
+            Button::X => Some(Keycode::Tab),
+            Button::Back => Some(Keycode::Escape),
+            _ => None,
+        },
+        _ => None,
+    }
 }
 
-impl GetKey for Event {
-    fn get_keycode(&self) -> (Keycode, &Event) {
-        if let Event::KeyDown { keycode, .. } | Event::KeyUp { keycode, .. } = self {
-            return (keycode.unwrap(), self);
-        }
-        (Keycode::T, self)
-    }
+/// Dim the game and say so while it is paused
+fn draw_paused(renderer: &mut dyn Renderer, field: &Playfield) {
+    let screen = [
+        Vec2::new(0., 0.),
+        Vec2::new(field.width, 0.),
+        Vec2::new(field.width, field.height),
+        Vec2::new(0., field.height),
+    ];
+    renderer.filled_polygon(&screen, Color::RGBA(0, 0, 0, 140));
+    Label::new("PAUSED", Anchor::Centre)
+        .size(48.)
+        .color(HIGHLIGHT)
+        .draw(renderer, field);
+}
+
+/// Draw in playfield units, scaled to fit the window with black bars
+/// on the sides that don't match its aspect ratio
+fn use_playfield(canvas: &mut Canvas<Window>, field: &Playfield) -> Result<(), String> {
+    canvas
+        .set_logical_size(field.width as u32, field.height as u32)
+        .map_err(|e| e.to_string())
+}
+
+fn toggle_fullscreen(canvas: &mut Canvas<Window>) -> Result<(), String> {
+    let window = canvas.window_mut();
+    let state = match window.fullscreen_state() {
+        FullscreenType::Off => FullscreenType::Desktop,
+        _ => FullscreenType::Off,
+    };
+
+    window.set_fullscreen(state)
+}
+
+/// What the player picked on the title screen
+enum Choice {
+    New(Players),
+    Resume,
+    Controls,
+    Quit,
+}
+
+/// How a game left the main loop
+enum Outcome {
+    /// The player ran out of lives
+    Over,
+    /// The player left mid game and it was saved to come back to
+    Saved,
+    /// The window was closed, the game was saved too
+    Closed,
 }
 
 pub struct Win {
     canvas: Canvas<Window>,
+    assets: Assets,
     event_pump: EventPump,
     ttf_context: Sdl2TtfContext,
     texture_creator: TextureCreator<WindowContext>,
     timer_subsystem: TimerSubsystem,
+    audio: Box<dyn Audio>,
+    rules: Rules,
+    /// Draw games on the CPU and copy the finished frame to the window
+    software_render: bool,
+    /// Vector monitor glow, kept between games once toggled
+    glow: bool,
+    stars: StarSettings,
+    effects: EffectSettings,
+    scheme: Scheme,
+    /// Player one's and player two's
+    bindings: [Bindings; 2],
+    pads: Pads,
+    rumble: bool,
+    /// Where games are streamed to spectators, if anywhere
+    stream: Option<Broadcaster>,
+    mods: Mods,
 }
 
 impl Win {
-    pub fn new() -> Result<Win, String> {
+    pub fn new(config: &Config) -> Result<Win, String> {
         let sdl_context = sdl2::init()?;
         let video_subsystem = sdl_context.video()?;
 
-        let window = video_subsystem
-            .window("Asteroids", SIZE as u32, SIZE as u32)
-            .position_centered()
-            .build()
-            .unwrap();
+        let mut window = video_subsystem.window("Asteroids", config.width, config.height);
+        window.position_centered().resizable();
+        if config.fullscreen {
+            window.fullscreen_desktop();
+        }
+        let window = window.build().map_err(|e| e.to_string())?;
 
-        let canvas = window.into_canvas().present_vsync().build().unwrap();
+        let mut canvas = window.into_canvas();
+        if config.vsync {
+            canvas = canvas.present_vsync();
+        }
+        let canvas = canvas.build().map_err(|e| e.to_string())?;
 
         let texture_creator = canvas.texture_creator();
 
+        let settings = AudioSettings {
+            volume: config.volume.clamp(0., 1.),
+            muted: config.muted,
+        };
+        let audio: Box<dyn Audio> = match SdlAudio::new(&sdl_context, settings) {
+            Ok(audio) => Box::new(audio),
+            Err(e) => {
+                eprintln!("No audio device, running silent: {}", e);
+                let mut audio = NullAudio::default();
+                audio.set_settings(settings);
+                Box::new(audio)
+            }
+        };
+
+        // Fonts are loaded as they are needed, so check for one up front
+        let ttf_context = sdl2::ttf::init().map_err(|e| e.to_string())?;
+        let assets = Assets::load();
+        load_font(&ttf_context, &assets.font, 12)?;
+
         Ok(Win {
             canvas,
+            assets,
             event_pump: sdl_context.event_pump()?,
-            ttf_context: sdl2::ttf::init().map_err(|e| e.to_string())?,
+            ttf_context,
             texture_creator,
             timer_subsystem: sdl_context.timer()?,
+            audio,
+            rules: config.rules(),
+            software_render: config.software_render,
+            glow: config.glow,
+            stars: config.stars(),
+            effects: config.effects(),
+            scheme: config.control_scheme,
+            bindings: [config.controls.clone(), config.controls_two.clone()],
+            pads: Pads::new(&sdl_context),
+            rumble: config.rumble,
+            stream: None,
+            mods: Mods::default(),
         })
     }
 
-    pub fn reset(&mut self) -> Result<(), String> {
-        // let mut dt = 0.;
+    /// Alternate between the title screen and games until the window is
+    /// closed, or just watch the replay, play the network game or watch
+    /// the stream if one was given
+    pub fn run(&mut self, options: &Options) -> Result<(), String> {
+        if let Some(addr) = &options.spectate {
+            return self.spectate(Spectator::connect(addr)?);
+        }
+        if let Some(port) = options.stream {
+            let stream = Broadcaster::bind(port)?;
+            eprintln!("Streaming games on {}", stream.local_addr()?);
+            self.stream = Some(stream);
+        }
+        self.mods = Mods::load(&options.mods);
 
-        // Create Entities
-        let stars = &mut Star::new_vec();
-        let ship = &mut Ship::new();
-        let asteroids = &mut Asteroid::new_vec();
+        if let Some(path) = &options.replay {
+            let replay = Replay::load(path)?;
+            replay.check_mods(&self.mods.signature())?;
+            let mut game = Game::new(replay.seed, replay.rules);
+            self.mods.start(&mut game, replay.seed);
+            self.reset(
+                &mut game,
+                HighScores::load().best(),
+                Some(&replay.inputs()),
+                None,
+                None,
+            )?;
+            return Ok(());
+        }
 
-        // Load a font
-        let path: &Path = Path::new(FILE_PATH);
-        let mut font = self.ttf_context.load_font(path, 28)?;
-        font.set_style(sdl2::ttf::FontStyle::BOLD);
+        if let Some(lobby) = options.lobby()? {
+            let seed = options.seed.unwrap_or_else(rand::random);
+            if let Some((mut session, mut game)) = self.lobby(lobby, seed)? {
+                let outcome = self.reset(&mut game, 0, None, None, Some(&mut session));
+                session.leave();
+                outcome?;
+            }
+            return Ok(());
+        }
 
-        let mut score: u32 = 0;
-        let mut text = Text::new(score, &font, &self.texture_creator)?;
+        let mut high_scores = HighScores::load();
+        let mut recorded = 0;
 
+        loop {
+            let mut recording = None;
+            let mut game = match self.title(&high_scores)? {
+                Choice::Quit => break,
+                Choice::Controls => {
+                    if self.controls(options.config.as_deref())? {
+                        continue;
+                    }
+                    break;
+                }
+                Choice::New(players) => {
+                    let rules = Rules {
+                        players,
+                        ..self.rules
+                    };
+                    let seed = options.seed.unwrap_or_else(rand::random);
+                    if options.record.is_some() {
+                        recording = Some(Replay::new(seed, rules, self.mods.signature()));
+                    }
+                    let mut game = Game::new(seed, rules);
+                    self.mods.start(&mut game, seed);
+                    game
+                }
+                // Saves don't keep what mods were up to, so they start over
+                Choice::Resume => match save::take() {
+                    Ok(mut game) => {
+                        let seed = game.seed;
+                        self.mods.start(&mut game, seed);
+                        game
+                    }
+                    Err(e) => {
+                        eprintln!("Could not resume the saved game: {}", e);
+                        continue;
+                    }
+                },
+            };
+
+            let outcome = self.reset(
+                &mut game,
+                high_scores.best(),
+                None,
+                recording.as_mut(),
+                None,
+            )?;
+
+            if let (Some(path), Some(recording)) = (&options.record, &recording) {
+                recorded += 1;
+                if let Err(e) = recording.save(&replay::numbered(path, recorded)) {
+                    eprintln!("Could not save the replay: {}", e);
+                }
+            }
+
+            match outcome {
+                Outcome::Closed => break,
+                Outcome::Saved => continue,
+                Outcome::Over => (),
+            }
+
+            // Two player games are a different thing, so they don't count
+            if game.rules.players == Players::Single && high_scores.qualifies(game.score()) {
+                let initials = match self.enter_initials(game.score())? {
+                    Some(initials) => initials,
+                    None => break,
+                };
+
+                high_scores.insert(HighScore::new(&initials, game.score(), game.wave));
+                if let Err(e) = high_scores.save() {
+                    eprintln!("Could not save high scores: {}", e);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Show the title and high score table until the player picks what to do
+    fn title(&mut self, high_scores: &HighScores) -> Result<Choice, String> {
+        let can_resume = save::exists();
+
+        let field = self.rules.field;
+        use_playfield(&mut self.canvas, &field)?;
+        let mut stars = Starfield::new(&field, self.stars, &mut rand::thread_rng());
         let mut last_update = self.timer_subsystem.ticks();
 
-        // Main loop
-        'running: loop {
-            let start = self.timer_subsystem.performance_counter();
+        let mut fonts = Fonts::new(&self.ttf_context, &self.texture_creator, &self.assets.font);
 
-            // Do event loop
+        let table = if high_scores.entries().is_empty() {
+            "No scores yet".to_string()
+        } else {
+            high_scores
+                .entries()
+                .iter()
+                .enumerate()
+                .map(|(i, entry)| {
+                    format!(
+                        "{:>2}.  {}  {:>7}  wave {:<3} {}",
+                        i + 1,
+                        entry.initials,
+                        entry.score,
+                        entry.wave,
+                        entry.date_string()
+                    )
+                })
+                .collect::<Vec<_>>()
+                .join("\n")
+        };
+
+        let mut idle_since = last_update;
+
+        loop {
             for event in self.event_pump.poll_iter() {
+                self.pads.event(&event);
                 match event {
-                    Event::Quit { .. }
-                    | Event::KeyDown {
-                        keycode: Some(Keycode::Escape),
-                        ..
-                    } => break 'running,
-                    _ => {
-                        ship.do_action(&event);
+                    Event::Quit { .. } => return Ok(Choice::Quit),
+                    Event::KeyDown { .. }
+                    | Event::ControllerButtonDown { .. }
+                    | Event::MouseButtonDown { .. } => idle_since = self.timer_subsystem.ticks(),
+                    _ => (),
+                }
+
+                match self.bindings[0].event(&event) {
+                    Some((Action::Leave, true)) => return Ok(Choice::Quit),
+                    Some((Action::Fullscreen, true)) => toggle_fullscreen(&mut self.canvas)?,
+                    _ => (),
+                }
+
+                match menu_key(&event) {
+                    Some(Keycode::Space | Keycode::Return) => {
+                        return Ok(Choice::New(self.rules.players))
                     }
+                    Some(Keycode::Num2) => return Ok(Choice::New(Players::Coop)),
+                    Some(Keycode::V) => return Ok(Choice::New(Players::Versus)),
+                    Some(Keycode::R) if can_resume => return Ok(Choice::Resume),
+                    Some(Keycode::C) => return Ok(Choice::Controls),
+                    _ => (),
                 }
             }
 
-            // spawn asteroid randomly with low chance
-            if rand::thread_rng().gen::<f32>() < 0.005 && asteroids.len() < 11 {
-                let (x, y) = get_random_radius();
-                asteroids.push(Asteroid::new(40, 100, x, y));
+            if self.timer_subsystem.ticks() - idle_since > ATTRACT_AFTER {
+                // The demo needs the fonts to itself
+                drop(fonts);
+                if !self.attract()? {
+                    return Ok(Choice::Quit);
+                }
+                use_playfield(&mut self.canvas, &field)?;
+                fonts = Fonts::new(&self.ttf_context, &self.texture_creator, &self.assets.font);
+                idle_since = self.timer_subsystem.ticks();
+                last_update = idle_since;
+            }
+
+            let mut renderer = SdlRenderer::new(&mut self.canvas, &mut fonts);
+            renderer.clear(Color::BLACK);
+            // Only twinkling here, there is no ship to scroll with
+            let current = self.timer_subsystem.ticks();
+            stars.update(
+                &Vec2::new(0., 0.),
+                (current - last_update) as f32 / 1000.,
+                &field,
+            );
+            last_update = current;
+            stars.draw(&mut renderer);
+
+            Label::new("ASTEROIDS", Anchor::Top)
+                .size(72.)
+                .offset(0., 60.)
+                .draw(&mut renderer, &field);
+            Label::new("HIGH SCORES", Anchor::Top)
+                .size(22.)
+                .color(HIGHLIGHT)
+                .offset(0., 200.)
+                .draw(&mut renderer, &field);
+            Label::new(&table, Anchor::Top)
+                .size(22.)
+                .align(Align::Left)
+                .offset(0., 250.)
+                .draw(&mut renderer, &field);
+
+            let mut prompt = Label::new(
+                "Press Space to start, 2 for co-op or V for versus",
+                Anchor::Bottom,
+            )
+            .size(22.)
+            .color(HIGHLIGHT)
+            .offset(0., -line_height(22.));
+            if can_resume {
+                prompt = prompt.offset(0., -line_height(22.) * 2.);
+                Label::new("Press R to resume your last game", Anchor::Bottom)
+                    .size(22.)
+                    .offset(0., -line_height(22.))
+                    .draw(&mut renderer, &field);
             }
+            prompt.draw(&mut renderer, &field);
+            Label::new("Press C to change the controls", Anchor::Bottom)
+                .size(22.)
+                .draw(&mut renderer, &field);
+
+            self.canvas.present();
+        }
+    }
+
+    /// Let the autopilot play a game on its own, like an arcade cabinet
+    /// left alone, until anything is pressed or the game ends. Returns
+    /// false if the window was closed
+    fn attract(&mut self) -> Result<bool, String> {
+        let rules = Rules {
+            players: Players::Single,
+            ..self.rules
+        };
+        let field = rules.field;
+        use_playfield(&mut self.canvas, &field)?;
+
+        let mut game = Game::new(rand::random(), rules);
+        let mut autopilot = Autopilot::default();
+        let mut fonts = Fonts::new(&self.ttf_context, &self.texture_creator, &self.assets.font);
+        let mut stars = Starfield::new(&field, self.stars, &mut rand::thread_rng());
+        let mut effects = Effects::new(self.effects);
+        let mut last_update = self.timer_subsystem.ticks();
+        let mut lag = 0.;
 
-            // Check collisions
-            for i in (0..ship.get_lasers().len()).rev() {
-                if let Some(index) = asteroids
-                    .iter_mut()
-                    .position(|asteroid| asteroid.collision(ship.get_lasers()[i].get_pos()))
-                {
-                    asteroids.break_up(index);
-                    ship.remove_laser(i);
-                    score += 10;
-                    text = Text::new(score, &font, &self.texture_creator).unwrap();
+        loop {
+            for event in self.event_pump.poll_iter() {
+                self.pads.event(&event);
+                match event {
+                    Event::Quit { .. } => return Ok(false),
+                    Event::KeyDown { .. }
+                    | Event::ControllerButtonDown { .. }
+                    | Event::MouseButtonDown { .. } => return Ok(true),
+                    _ => (),
                 }
             }
 
-            // Check if game over
-            for asteroid in asteroids.iter_mut() {
-                if ship.check_collision(asteroid) {
-                    break 'running;
+            let current = self.timer_subsystem.ticks();
+            let dt = (current - last_update) as f32 / 1000.;
+            last_update = current;
+
+            if !effects.is_frozen() {
+                lag = (lag + dt).min(TICK * MAX_TICKS_PER_FRAME as f32);
+            }
+            while lag >= TICK {
+                lag -= TICK;
+                let input = autopilot.input(&game, 0);
+                for event in game.tick(&[input]) {
+                    if let GameEvent::GameOver = event {
+                        self.timer_subsystem.delay(GAME_OVER_DELAY);
+                        return Ok(true);
+                    }
+                    effects.event(&event);
                 }
             }
+            stars.update(&game.velocity(), dt, &field);
+            effects.update(dt, &mut rand::thread_rng());
 
-            // Do physics loop
-            let current: u32 = self.timer_subsystem.ticks();
+            let mut renderer = SdlRenderer::new(&mut self.canvas, &mut fonts);
+            scene::draw_game(&mut renderer, &stars, &game, 0, &effects);
+            Label::new("DEMO  -  PRESS ANY KEY", Anchor::Bottom)
+                .size(22.)
+                .color(HIGHLIGHT)
+                .draw(&mut renderer, &field);
+
+            self.canvas.present();
+        }
+    }
+
+    /// Wait for players to join a network game, or for the host to start
+    /// it. The host starts it with space once someone has joined. Returns
+    /// None if the player gave up
+    fn lobby(&mut self, mut lobby: Lobby, seed: u64) -> Result<Option<(Session, Game)>, String> {
+        let field = self.rules.field;
+        use_playfield(&mut self.canvas, &field)?;
+
+        let mut fonts = Fonts::new(&self.ttf_context, &self.texture_creator, &self.assets.font);
+        let host = lobby.is_host();
+
+        loop {
+            for event in self.event_pump.poll_iter() {
+                self.pads.event(&event);
+                if let Event::Quit { .. } = event {
+                    return Ok(None);
+                }
+                match menu_key(&event) {
+                    Some(Keycode::Escape) => return Ok(None),
+                    Some(Keycode::Space | Keycode::Return) if host && lobby.players() > 1 => {
+                        return lobby.start(seed, self.rules).map(Some)
+                    }
+                    _ => (),
+                }
+            }
+            if lobby.poll()? {
+                return lobby.start(seed, self.rules).map(Some);
+            }
+
+            let mut renderer = SdlRenderer::new(&mut self.canvas, &mut fonts);
+            renderer.clear(Color::BLACK);
+
+            Label::new("ONLINE", Anchor::Top)
+                .size(48.)
+                .offset(0., 130.)
+                .draw(&mut renderer, &field);
+            Label::new(&lobby.status(), Anchor::Centre)
+                .size(22.)
+                .draw(&mut renderer, &field);
+            let prompt = if host {
+                "Press Space to start once everyone has joined, Escape to give up"
+            } else {
+                "Press Escape to give up"
+            };
+            Label::new(prompt, Anchor::Bottom)
+                .size(18.)
+                .color(Color::GRAY)
+                .draw(&mut renderer, &field);
+
+            self.canvas.present();
+        }
+    }
+
+    /// Show a game streaming from another window as it happens, until the
+    /// stream ends or the player leaves
+    fn spectate(&mut self, mut spectator: Spectator) -> Result<(), String> {
+        let mut field = self.rules.field;
+        use_playfield(&mut self.canvas, &field)?;
+
+        let mut fonts = Fonts::new(&self.ttf_context, &self.texture_creator, &self.assets.font);
+        let mut stars = Starfield::new(&field, self.stars, &mut rand::thread_rng());
+        let effects = Effects::new(self.effects);
+        let mut last_update = self.timer_subsystem.ticks();
+        let mut watching = None;
+
+        loop {
+            for event in self.event_pump.poll_iter() {
+                self.pads.event(&event);
+                if let Event::Quit { .. } = event {
+                    return Ok(());
+                }
+                match self.bindings[0].event(&event) {
+                    Some((Action::Leave, true)) => return Ok(()),
+                    Some((Action::Fullscreen, true)) => toggle_fullscreen(&mut self.canvas)?,
+                    _ => (),
+                }
+            }
+
+            let snapshot = match spectator.latest() {
+                Ok(snapshot) => snapshot,
+                Err(e) => {
+                    eprintln!("{}", e);
+                    return Ok(());
+                }
+            };
+            let current = self.timer_subsystem.ticks();
             let dt = (current - last_update) as f32 / 1000.;
+            last_update = current;
+
+            if let Some(snapshot) = snapshot {
+                // Each new game can have a different playfield
+                if snapshot.rules.field != field {
+                    field = snapshot.rules.field;
+                    use_playfield(&mut self.canvas, &field)?;
+                    stars = Starfield::new(&field, self.stars, &mut rand::thread_rng());
+                }
+                stars.update(&snapshot.velocity(), dt, &field);
+                watching = Some(snapshot.to_game());
+            }
+
+            let mut renderer = SdlRenderer::new(&mut self.canvas, &mut fonts);
+            match &watching {
+                Some(game) => {
+                    scene::draw_game(&mut renderer, &stars, game, 0, &effects);
+                    Label::new("WATCHING", Anchor::Bottom)
+                        .size(16.)
+                        .color(Color::GRAY)
+                        .draw(&mut renderer, &field);
+                }
+                None => {
+                    renderer.clear(Color::BLACK);
+                    Label::new("Waiting for the game", Anchor::Centre)
+                        .size(22.)
+                        .draw(&mut renderer, &field);
+                }
+            }
+
+            self.canvas.present();
+        }
+    }
+
+    /// Arcade style initials entry, up and down pick a letter and left and
+    /// right move between them. Returns None if the player quit
+    fn enter_initials(&mut self, score: u32) -> Result<Option<String>, String> {
+        let field = self.rules.field;
+        use_playfield(&mut self.canvas, &field)?;
+
+        let mut fonts = Fonts::new(&self.ttf_context, &self.texture_creator, &self.assets.font);
+        let score = format!("Score {}", score);
+
+        let mut initials = [b'A'; INITIALS_LEN];
+        let mut cursor = 0;
+
+        loop {
+            for event in self.event_pump.poll_iter() {
+                self.pads.event(&event);
+                if let Some((Action::Fullscreen, true)) = self.bindings[0].event(&event) {
+                    toggle_fullscreen(&mut self.canvas)?;
+                    continue;
+                }
+
+                match (&event, menu_key(&event)) {
+                    (Event::Quit { .. }, _) => return Ok(None),
+                    (_, Some(keycode)) => match keycode {
+                        Keycode::Up => initials[cursor] = (initials[cursor] - b'A' + 1) % 26 + b'A',
+                        Keycode::Down => {
+                            initials[cursor] = (initials[cursor] - b'A' + 25) % 26 + b'A'
+                        }
+                        Keycode::Left | Keycode::Backspace => cursor = cursor.saturating_sub(1),
+                        Keycode::Right | Keycode::Space | Keycode::Return => cursor += 1,
+                        // Letters can be typed directly too
+                        _ if (Keycode::A as i32..=Keycode::Z as i32)
+                            .contains(&(keycode as i32)) =>
+                        {
+                            initials[cursor] = b'A' + (keycode as i32 - Keycode::A as i32) as u8;
+                            cursor += 1;
+                        }
+                        _ => (),
+                    },
+                    _ => (),
+                }
+
+                if cursor == INITIALS_LEN {
+                    return Ok(Some(String::from_utf8_lossy(&initials).into_owned()));
+                }
+            }
+
+            let mut renderer = SdlRenderer::new(&mut self.canvas, &mut fonts);
+            renderer.clear(Color::BLACK);
+
+            Label::new("NEW HIGH SCORE", Anchor::Top)
+                .size(48.)
+                .color(HIGHLIGHT)
+                .offset(0., 130.)
+                .draw(&mut renderer, &field);
+            Label::new(&score, Anchor::Top)
+                .size(22.)
+                .offset(0., 210.)
+                .draw(&mut renderer, &field);
+            Label::new("Enter your initials", Anchor::Bottom)
+                .size(22.)
+                .offset(0., -field.height * 0.1)
+                .draw(&mut renderer, &field);
+
+            for (i, &letter) in initials.iter().enumerate() {
+                let color = if i == cursor { HIGHLIGHT } else { Color::WHITE };
+                Label::new(&(letter as char).to_string(), Anchor::Centre)
+                    .size(64.)
+                    .color(color)
+                    .offset((i as f32 - 1.) * 80., 0.)
+                    .draw(&mut renderer, &field);
+            }
+
+            self.canvas.present();
+        }
+    }
+
+    /// List every action with its keys. Up and down pick an action, return
+    /// adds the next key pressed to it, backspace clears it, left and right
+    /// switch between the players and tab switches the control scheme.
+    /// Changes are saved to the config file on the way out. Returns false
+    /// if the window was closed
+    fn controls(&mut self, config_path: Option<&Path>) -> Result<bool, String> {
+        let field = self.rules.field;
+        use_playfield(&mut self.canvas, &field)?;
+
+        let mut fonts = Fonts::new(&self.ttf_context, &self.texture_creator, &self.assets.font);
+
+        let mut player = 0;
+        let mut selected = 0;
+        let mut listening = false;
+        let mut changed = false;
+
+        let open = 'controls: loop {
+            for event in self.event_pump.poll_iter() {
+                self.pads.event(&event);
+                let action = Action::ALL[selected];
+
+                if listening {
+                    let control = match event {
+                        Event::KeyDown {
+                            keycode: Some(Keycode::Escape),
+                            ..
+                        } => None,
+                        Event::KeyDown {
+                            keycode: Some(key), ..
+                        } => Some(Control::Key(key)),
+                        Event::ControllerButtonDown { button, .. } => Some(Control::Button(button)),
+                        _ => continue,
+                    };
+                    if let Some(control) = control {
+                        self.bindings[player].bind(action, control);
+                        // Players share the keyboard and mouse but each has
+                        // their own controller
+                        if let Control::Key(_) | Control::Mouse(_) = control {
+                            self.bindings[1 - player].unbind(control);
+                        }
+                        changed = true;
+                    }
+                    listening = false;
+                    continue;
+                }
+
+                if let Event::Quit { .. } = event {
+                    break 'controls false;
+                }
+                let key = match menu_key(&event) {
+                    Some(key) => key,
+                    None => continue,
+                };
+
+                match key {
+                    Keycode::Escape => break 'controls true,
+                    Keycode::Up => {
+                        selected = (selected + Action::ALL.len() - 1) % Action::ALL.len()
+                    }
+                    Keycode::Down => selected = (selected + 1) % Action::ALL.len(),
+                    Keycode::Return | Keycode::Space => listening = true,
+                    Keycode::Backspace | Keycode::Delete => {
+                        self.bindings[player].clear(action);
+                        changed = true;
+                    }
+                    Keycode::D => {
+                        self.bindings[player] = Bindings::for_player(player);
+                        changed = true;
+                    }
+                    Keycode::Left | Keycode::Right => player = 1 - player,
+                    Keycode::Tab => {
+                        self.scheme = self.scheme.next();
+                        changed = true;
+                    }
+                    _ => (),
+                }
+            }
+
+            let mut renderer = SdlRenderer::new(&mut self.canvas, &mut fonts);
+            renderer.clear(Color::BLACK);
+
+            Label::new("CONTROLS", Anchor::Top)
+                .size(48.)
+                .offset(0., 40.)
+                .draw(&mut renderer, &field);
+            let heading = format!("Player {}    Scheme  {}", player + 1, self.scheme.label());
+            Label::new(&heading, Anchor::Top)
+                .size(22.)
+                .color(HIGHLIGHT)
+                .offset(0., 110.)
+                .draw(&mut renderer, &field);
+
+            let row = line_height(20.);
+            for (i, &action) in Action::ALL.iter().enumerate() {
+                let color = if i == selected {
+                    HIGHLIGHT
+                } else {
+                    Color::WHITE
+                };
+                let keys = if i == selected && listening {
+                    "Press a key or button".to_string()
+                } else {
+                    self.bindings[player].describe(action)
+                };
+                let y = 160. + i as f32 * row;
+
+                Label::new(action.label(), Anchor::TopLeft)
+                    .size(20.)
+                    .color(color)
+                    .offset(field.width * 0.2, y)
+                    .draw(&mut renderer, &field);
+                Label::new(&keys, Anchor::TopLeft)
+                    .size(20.)
+                    .color(color)
+                    .offset(field.width * 0.55, y)
+                    .draw(&mut renderer, &field);
+            }
+
+            let help = if listening {
+                "Escape to cancel"
+            } else {
+                "Up and down to pick, Return to add a key or button, Backspace to clear\nLeft and right to switch player, Tab to switch scheme\nD for the defaults, Escape when you are done"
+            };
+            Label::new(help, Anchor::Bottom)
+                .size(18.)
+                .color(Color::GRAY)
+                .draw(&mut renderer, &field);
+
+            self.canvas.present();
+        };
+
+        if changed {
+            if let Err(e) = Config::save_controls(config_path, self.scheme, &self.bindings) {
+                eprintln!("Could not save the controls: {}", e);
+            }
+        }
+
+        Ok(open)
+    }
+
+    /// Play a game until it is over or the player leaves, in which case it
+    /// is saved. With `playback` the inputs come from a replay instead of
+    /// the keyboard and the game ends when they run out. With `net` only
+    /// one player plays here and the others come over the network, those
+    /// games can't be paused or saved
+    fn reset(
+        &mut self,
+        game: &mut Game,
+        high_score: u32,
+        playback: Option<&[Vec<Input>]>,
+        mut recording: Option<&mut Replay>,
+        mut net: Option<&mut Session>,
+    ) -> Result<Outcome, String> {
+        let field = game.rules.field;
+        use_playfield(&mut self.canvas, &field)?;
+
+        // Create Entities
+        let mut stars = Starfield::new(&field, self.stars, &mut rand::thread_rng());
+
+        let mut fonts = Fonts::new(&self.ttf_context, &self.texture_creator, &self.assets.font);
+
+        // Games are drawn on the CPU when asked to, or while the glow is on
+        let (width, height) = (field.width as u32, field.height as u32);
+        let mut buffer = SoftwareRenderer::new(width, height);
+        let mut texture = self
+            .texture_creator
+            .create_texture_streaming(PixelFormatEnum::RGBA32, width, height)
+            .map_err(|e| e.to_string())?;
+        let mut phosphor = if self.glow {
+            Some(Phosphor::new(width, height))
+        } else {
+            None
+        };
+        let mut screenshot = false;
+        let mut effects = Effects::new(self.effects);
+        let mut paused = false;
+
+        let mut heartbeat = Heartbeat::default();
+        let players = game.players.len();
+        // Players playing on this machine, and which one each is
+        let local = if net.is_some() { 1 } else { players };
+        let me = net.as_ref().map(|session| session.player());
+        // Controls held on the keyboard, mouse and controller buttons, then
+        // the sticks, for each of them
+        let mut held = vec![Input::default(); local];
+        let mut sticks: Vec<_> = (0..local).map(|_| Sticks::default()).collect();
+        let mut twin_sticks: Vec<_> = (0..local).map(|_| TwinStick::default()).collect();
+        let mut tick: usize = 0;
+        let mut lag = 0.;
+
+        let mut last_update = self.timer_subsystem.ticks();
+
+        // Main loop
+        let outcome = 'running: loop {
+            let start = self.timer_subsystem.performance_counter();
+
+            // Do event loop
+            for event in self.event_pump.poll_iter() {
+                if let Event::Quit { .. } = event {
+                    break 'running Outcome::Closed;
+                }
+                self.pads.event(&event);
+                // The mouse is player one's
+                twin_sticks[0].event(&event);
+
+                // Controllers only work for the player they belong to
+                let pad = self.pads.player(&event, local);
+                let mut actions = Vec::new();
+                for player in (0..local).filter(|&p| pad.is_none() || pad == Some(p)) {
+                    sticks[player].event(&event);
+                    if let Some((action, pressed)) = self.bindings[player].event(&event) {
+                        actions.push((player, action, pressed));
+                    }
+                }
+
+                for (player, action, pressed) in actions {
+                    if !pressed {
+                        action.hold(&mut held[player], false);
+                        twin_sticks[player].hold(action, false);
+                        continue;
+                    }
+
+                    match action {
+                        Action::Leave => break 'running Outcome::Saved,
+                        // Everyone else would have to wait too
+                        Action::Pause if net.is_some() => (),
+                        Action::Pause => paused = !paused,
+                        Action::Fullscreen => toggle_fullscreen(&mut self.canvas)?,
+                        Action::Screenshot => screenshot = true,
+                        Action::Glow => {
+                            phosphor = match phosphor {
+                                Some(_) => None,
+                                None => Some(Phosphor::new(width, height)),
+                            };
+                            self.glow = phosphor.is_some();
+                        }
+                        Action::Mute => {
+                            let mut settings = self.audio.settings();
+                            settings.muted = !settings.muted;
+                            self.audio.set_settings(settings);
+                        }
+                        Action::VolumeDown => {
+                            let mut settings = self.audio.settings();
+                            settings.volume_down();
+                            self.audio.set_settings(settings);
+                        }
+                        Action::VolumeUp => {
+                            let mut settings = self.audio.settings();
+                            settings.volume_up();
+                            self.audio.set_settings(settings);
+                        }
+                        _ => {
+                            action.hold(&mut held[player], true);
+                            twin_sticks[player].hold(action, true);
+                        }
+                    }
+                }
+            }
+
+            // Do physics loop in fixed steps so games can be replayed exactly
+            let current: u32 = self.timer_subsystem.ticks();
+            // Time stands still while paused
+            let dt = if paused {
+                0.
+            } else {
+                (current - last_update) as f32 / 1000.
+            };
 
             // Set updated time
             last_update = current;
 
-            ship.update(dt);
-            for asteroid in asteroids.iter_mut() {
-                asteroid.update();
+            // Hit-stops hold the game still without losing any ticks
+            if !effects.is_frozen() {
+                lag = (lag + dt).min(TICK * MAX_TICKS_PER_FRAME as f32);
             }
+            while lag >= TICK {
+                lag -= TICK;
 
-            // Do rendering loop
-            self.canvas.clear();
+                let inputs = match playback {
+                    Some(ticks) => match ticks.get(tick) {
+                        Some(inputs) => inputs.clone(),
+                        None => break 'running Outcome::Over,
+                    },
+                    None => (0..local)
+                        .map(|i| match self.scheme {
+                            Scheme::Classic => sticks[i].apply(held[i]),
+                            Scheme::TwinStick => match game.ship(me.unwrap_or(i)) {
+                                Some(ship) => twin_sticks[i].apply(held[i], &sticks[i], ship),
+                                None => Input::default(),
+                            },
+                        })
+                        .collect(),
+                };
+                let events = match net.as_mut() {
+                    Some(session) => match session.advance(game, inputs[0]) {
+                        Ok(Some(events)) => events,
+                        // The others are too far behind, try again next frame
+                        Ok(None) => {
+                            lag += TICK;
+                            break;
+                        }
+                        Err(e) => {
+                            eprintln!("{}", e);
+                            break 'running Outcome::Over;
+                        }
+                    },
+                    None => {
+                        if let Some(recording) = recording.as_mut() {
+                            recording.push(&inputs);
+                        }
+                        let mut events = game.tick(&inputs);
+                        self.mods.tick(game, &mut events);
+                        events
+                    }
+                };
+                tick += 1;
+                if let Some(stream) = self.stream.as_mut() {
+                    stream.send(game, tick as u64);
+                }
 
-            // check wrapping
-            wrap_verts(ship);
-            asteroids.iter_mut().for_each(wrap_verts);
+                for event in events {
+                    effects.event(&event);
+                    match event {
+                        GameEvent::Fired => self.audio.play(Sound::Fire),
+                        GameEvent::AsteroidDestroyed { large, .. } => self.audio.play(if large {
+                            Sound::BangLarge
+                        } else {
+                            Sound::BangSmall
+                        }),
+                        GameEvent::ShipDestroyed { player } => {
+                            self.audio.set_thrust(false);
+                            self.audio.play(Sound::ShipDeath);
+                            // Only this machine's player has a controller here
+                            match (self.rumble, me) {
+                                (true, Some(me)) if me == player => self.pads.rumble_death(0, 1),
+                                (true, None) => self.pads.rumble_death(player, players),
+                                _ => (),
+                            }
+                        }
+                        GameEvent::ExtraLife => self.audio.play(Sound::ExtraLife),
+                        GameEvent::Hyperspace | GameEvent::ShipSpawned { .. } => (),
+                        // The shown game can be wrong about that over the
+                        // network, so only the confirmed one is trusted
+                        GameEvent::GameOver if net.is_some() => (),
+                        GameEvent::GameOver => {
+                            self.timer_subsystem.delay(GAME_OVER_DELAY);
+                            break 'running Outcome::Over;
+                        }
+                    }
+                }
+            }
 
-            // Render
-            stars.iter_mut().for_each(|f| f.draw(&self.canvas));
-            ship.draw(&self.canvas);
-            asteroids.iter_mut().for_each(|f| f.draw(&self.canvas));
+            if let Some(session) = net.as_mut() {
+                // Keep talking while no ticks run, like during a hit-stop
+                if let Err(e) = session.pump() {
+                    eprintln!("{}", e);
+                    break 'running Outcome::Over;
+                }
+                if session.confirmed().0.is_over() {
+                    self.timer_subsystem.delay(GAME_OVER_DELAY);
+                    break 'running Outcome::Over;
+                }
+            }
 
-            self.canvas.set_draw_color(Color::BLACK);
+            self.audio.set_thrust(game.is_thrusting() && !paused);
 
-            // Draw text
-            self.canvas.copy(&text.texture, None, Some(text.target))?;
+            heartbeat.update(dt, game.asteroids().count(), self.audio.as_mut());
+            stars.update(&game.velocity(), dt, &field);
+            effects.update(dt, &mut rand::thread_rng());
+
+            // Do rendering loop
+            if self.software_render || phosphor.is_some() {
+                scene::draw_game(&mut buffer, &stars, game, high_score, &effects);
+                self.mods
+                    .draw(&mut Shifted::new(&mut buffer, effects.offset()), &field);
+                self.mods.draw_hud(&mut buffer, &field);
+                if paused {
+                    draw_paused(&mut buffer, &field);
+                }
+                if let Some(phosphor) = phosphor.as_mut() {
+                    phosphor.apply(&mut buffer, dt);
+                }
+
+                texture
+                    .update(None, buffer.pixels(), buffer.width() as usize * 4)
+                    .map_err(|e| e.to_string())?;
+                self.canvas.copy(&texture, None, None)?;
+
+                if screenshot {
+                    capture::screenshot(buffer.width(), buffer.height(), buffer.pixels());
+                }
+            } else {
+                let mut renderer = SdlRenderer::new(&mut self.canvas, &mut fonts);
+                scene::draw_game(&mut renderer, &stars, game, high_score, &effects);
+                self.mods
+                    .draw(&mut Shifted::new(&mut renderer, effects.offset()), &field);
+                self.mods.draw_hud(&mut renderer, &field);
+                if paused {
+                    draw_paused(&mut renderer, &field);
+                }
+
+                // Read back what is about to be shown, shake and all
+                if screenshot {
+                    let (width, height) = self.canvas.output_size()?;
+                    match self.canvas.read_pixels(None, PixelFormatEnum::RGBA32) {
+                        Ok(pixels) => capture::screenshot(width, height, &pixels),
+                        Err(e) => eprintln!("Could not read the screen: {}", e),
+                    }
+                }
+            }
+            screenshot = false;
 
             // Display
             self.canvas.present();
@@ -167,8 +1134,18 @@ impl Win {
             let _elapsed =
                 (end - start) as f32 / self.timer_subsystem.performance_frequency() as f32;
             // println!("{}", (1. / _elapsed).round());
+        };
+
+        self.audio.set_thrust(false);
+
+        // Replays can always be watched again so there is nothing to save,
+        // and network games can't be carried on alone
+        if let (Outcome::Saved | Outcome::Closed, None, None) = (&outcome, playback, &net) {
+            if let Err(e) = save::save(game) {
+                eprintln!("Could not save the game: {}", e);
+            }
         }
 
-        Ok(())
+        Ok(outcome)
     }
 }
@@ -0,0 +1,263 @@
+use crate::{
+    controls::bot::Autopilot,
+    game::{input::Input, rules::Rules, Game},
+    storage::replay::Replay,
+};
+use serde::Serialize;
+use std::{
+    panic,
+    str::FromStr,
+    sync::atomic::{AtomicUsize, Ordering},
+    thread,
+};
+
+/// Who is at the controls in a simulated game
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Driver {
+    /// The autopilot flies every ship
+    Bot,
+    /// Everyone holds nothing
+    Idle,
+}
+
+impl FromStr for Driver {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "bot" => Ok(Driver::Bot),
+            "idle" => Ok(Driver::Idle),
+            _ => Err(format!("expected bot or idle, got '{}'", s)),
+        }
+    }
+}
+
+/// How one game went
+#[derive(Serialize)]
+pub struct GameResult {
+    pub seed: u64,
+    pub score: u32,
+    pub wave: u32,
+    pub ticks: u64,
+    /// Whether it ended, rather than running out of ticks or replay
+    pub over: bool,
+    pub collision_checks: u64,
+}
+
+impl GameResult {
+    fn new(seed: u64, game: &Game, ticks: u64) -> Self {
+        Self {
+            seed,
+            score: game.score(),
+            wave: game.wave,
+            ticks,
+            over: game.is_over(),
+            collision_checks: game.collision_checks(),
+        }
+    }
+}
+
+/// Play a game from `seed` with `driver` at the controls, until it ends or
+/// `max_ticks` have gone by
+pub fn play(seed: u64, rules: Rules, driver: Driver, max_ticks: u64) -> GameResult {
+    let mut game = Game::new(seed, rules);
+    let mut autopilots: Vec<_> = game.players.iter().map(|_| Autopilot::default()).collect();
+    let mut inputs = vec![Input::default(); game.players.len()];
+
+    let mut ticks = 0;
+    while ticks < max_ticks && !game.is_over() {
+        if driver == Driver::Bot {
+            for (i, autopilot) in autopilots.iter_mut().enumerate() {
+                inputs[i] = autopilot.input(&game, i);
+            }
+        }
+        game.tick(&inputs);
+        ticks += 1;
+    }
+
+    GameResult::new(seed, &game, ticks)
+}
+
+/// Play a replay back until it runs out or the game ends
+pub fn play_replay(replay: &Replay) -> GameResult {
+    let mut game = Game::new(replay.seed, replay.rules);
+
+    let mut ticks = 0;
+    for inputs in replay.inputs() {
+        if game.is_over() {
+            break;
+        }
+        game.tick(&inputs);
+        ticks += 1;
+    }
+
+    GameResult::new(replay.seed, &game, ticks)
+}
+
+/// Work out `count` games with `play` across `threads` threads, handing
+/// out the next game to whichever thread is free. Results are in order.
+/// A game that panics panics here too, rather than going missing
+pub fn play_all<F>(count: usize, threads: usize, play: F) -> Vec<GameResult>
+where
+    F: Fn(usize) -> GameResult + Sync,
+{
+    let next = AtomicUsize::new(0);
+    let mut results: Vec<(usize, GameResult)> = thread::scope(|scope| {
+        let workers: Vec<_> = (0..threads.clamp(1, count.max(1)))
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut done = Vec::new();
+                    loop {
+                        let i = next.fetch_add(1, Ordering::Relaxed);
+                        if i >= count {
+                            break done;
+                        }
+                        done.push((i, play(i)));
+                    }
+                })
+            })
+            .collect();
+
+        workers
+            .into_iter()
+            .flat_map(|worker| worker.join().unwrap_or_else(|e| panic::resume_unwind(e)))
+            .collect()
+    });
+
+    results.sort_by_key(|(i, _)| *i);
+    results.into_iter().map(|(_, result)| result).collect()
+}
+
+/// The spread of some number over all the games
+#[derive(Serialize)]
+pub struct Distribution {
+    pub min: f64,
+    pub p10: f64,
+    pub median: f64,
+    pub p90: f64,
+    pub max: f64,
+    pub mean: f64,
+    pub std_dev: f64,
+}
+
+impl Distribution {
+    pub fn of(values: impl Iterator<Item = f64>) -> Distribution {
+        let mut values: Vec<f64> = values.collect();
+        values.sort_by(f64::total_cmp);
+        if values.is_empty() {
+            values.push(0.);
+        }
+
+        let n = values.len() as f64;
+        let mean = values.iter().sum::<f64>() / n;
+        let variance = values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / n;
+        // Nearest rank
+        let percentile = |p: f64| values[((p * n).ceil() as usize).clamp(1, values.len()) - 1];
+
+        Distribution {
+            min: values[0],
+            p10: percentile(0.1),
+            median: percentile(0.5),
+            p90: percentile(0.9),
+            max: values[values.len() - 1],
+            mean,
+            std_dev: variance.sqrt(),
+        }
+    }
+}
+
+/// Everything measured over a batch of games
+#[derive(Serialize)]
+pub struct Report {
+    pub games: usize,
+    /// Games that ended rather than running out of ticks
+    pub game_overs: usize,
+    pub score: Distribution,
+    pub wave: Distribution,
+    /// How long games lasted, in ticks
+    pub ticks: Distribution,
+    pub total_ticks: u64,
+    pub seconds: f64,
+    /// Ticks simulated a second of wall time, across every thread
+    pub ticks_per_second: f64,
+    pub threads: usize,
+    pub collision_checks: u64,
+    pub collision_checks_per_tick: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub results: Option<Vec<GameResult>>,
+}
+
+impl Report {
+    /// Sum up `results` played in `seconds`, keeping each game's result if
+    /// `each` is set
+    pub fn new(results: Vec<GameResult>, seconds: f64, threads: usize, each: bool) -> Report {
+        let total_ticks = results.iter().map(|r| r.ticks).sum::<u64>();
+        let collision_checks = results.iter().map(|r| r.collision_checks).sum::<u64>();
+
+        Report {
+            games: results.len(),
+            game_overs: results.iter().filter(|r| r.over).count(),
+            score: Distribution::of(results.iter().map(|r| r.score as f64)),
+            wave: Distribution::of(results.iter().map(|r| r.wave as f64)),
+            ticks: Distribution::of(results.iter().map(|r| r.ticks as f64)),
+            total_ticks,
+            seconds,
+            ticks_per_second: total_ticks as f64 / seconds.max(f64::EPSILON),
+            threads,
+            collision_checks,
+            collision_checks_per_tick: collision_checks as f64 / total_ticks.max(1) as f64,
+            results: if each { Some(results) } else { None },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        game::rules::{Difficulty, GameMode, Players},
+        math::playfield::Playfield,
+    };
+
+    const RULES: Rules = Rules {
+        difficulty: Difficulty::Hard,
+        mode: GameMode::Classic,
+        players: Players::Single,
+        field: Playfield {
+            width: 800.,
+            height: 600.,
+        },
+    };
+
+    #[test]
+    fn same_games_on_any_number_of_threads() {
+        let run = |threads| play_all(6, threads, |i| play(i as u64, RULES, Driver::Bot, 3000));
+        let (one, many) = (run(1), run(4));
+
+        for (a, b) in one.iter().zip(&many) {
+            assert_eq!(
+                (a.seed, a.score, a.ticks, a.collision_checks),
+                (b.seed, b.score, b.ticks, b.collision_checks)
+            );
+        }
+        assert!(one
+            .iter()
+            .all(|result| result.collision_checks > result.ticks));
+    }
+
+    #[test]
+    #[should_panic(expected = "game 3")]
+    fn panicking_games_are_not_dropped() {
+        play_all(6, 2, |i| {
+            assert_ne!(i, 3, "game {}", i);
+            play(i as u64, RULES, Driver::Idle, 10)
+        });
+    }
+
+    #[test]
+    fn percentiles() {
+        let spread = Distribution::of((1..=10).map(f64::from));
+        assert_eq!((spread.min, spread.p10, spread.median), (1., 1., 5.));
+        assert_eq!((spread.p90, spread.max, spread.mean), (9., 10., 5.5));
+    }
+}
@@ -0,0 +1,220 @@
+use super::{civil_date, data_dir, write_atomic};
+use serde::{Deserialize, Serialize};
+use std::{
+    cmp::Reverse,
+    fs,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+const FILE_NAME: &str = "highscores.json";
+pub const MAX_ENTRIES: usize = 10;
+pub const INITIALS_LEN: usize = 3;
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct HighScore {
+    pub initials: String,
+    pub score: u32,
+    pub wave: u32,
+    /// Seconds since the unix epoch
+    pub date: u64,
+}
+
+impl HighScore {
+    pub fn new(initials: &str, score: u32, wave: u32) -> Self {
+        let date = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs());
+
+        Self {
+            initials: initials.to_string(),
+            score,
+            wave,
+            date,
+        }
+    }
+
+    /// The date the score was set as YYYY-MM-DD
+    pub fn date_string(&self) -> String {
+        let (year, month, day) = civil_date(self.date);
+
+        format!("{:04}-{:02}-{:02}", year, month, day)
+    }
+}
+
+/// The top scores, best first, kept in sync with a file on disk
+pub struct HighScores {
+    entries: Vec<HighScore>,
+    path: Option<PathBuf>,
+}
+
+impl HighScores {
+    /// Load the table from the data directory. A missing or unreadable
+    /// file gives an empty table rather than an error, a corrupt one is
+    /// moved aside and the last good backup used instead
+    pub fn load() -> HighScores {
+        match data_dir() {
+            Ok(dir) => HighScores::load_from(dir.join(FILE_NAME)),
+            Err(e) => {
+                eprintln!("High scores will not be saved: {}", e);
+                HighScores {
+                    entries: Vec::new(),
+                    path: None,
+                }
+            }
+        }
+    }
+
+    fn load_from(path: PathBuf) -> HighScores {
+        let entries = match read(&path) {
+            Ok(entries) => entries,
+            Err(e) => {
+                eprintln!("High score table is corrupt, restoring backup: {}", e);
+                let _ = fs::rename(&path, path.with_extension("corrupt"));
+                read(&path.with_extension("bak")).unwrap_or_default()
+            }
+        };
+
+        let mut scores = HighScores {
+            entries,
+            path: Some(path),
+        };
+        scores.tidy();
+
+        scores
+    }
+
+    pub fn entries(&self) -> &[HighScore] {
+        &self.entries
+    }
+
+    /// The top score, or 0 if there are none yet
+    pub fn best(&self) -> u32 {
+        self.entries.first().map_or(0, |entry| entry.score)
+    }
+
+    /// Whether a score is good enough to make the table
+    pub fn qualifies(&self, score: u32) -> bool {
+        score > 0
+            && (self.entries.len() < MAX_ENTRIES
+                || self.entries.iter().any(|entry| score > entry.score))
+    }
+
+    pub fn insert(&mut self, entry: HighScore) {
+        self.entries.push(entry);
+        self.tidy();
+    }
+
+    /// Write the table out, keeping the previous file as a backup
+    pub fn save(&self) -> Result<(), String> {
+        let path = match &self.path {
+            Some(path) => path,
+            None => return Ok(()),
+        };
+
+        if path.exists() {
+            fs::copy(path, path.with_extension("bak")).map_err(|e| e.to_string())?;
+        }
+
+        let json = serde_json::to_vec_pretty(&self.entries).map_err(|e| e.to_string())?;
+        write_atomic(path, &json)
+    }
+
+    /// Sort best first, drop anything past the end of the table and
+    /// clean up initials that may have been edited by hand
+    fn tidy(&mut self) {
+        for entry in self.entries.iter_mut() {
+            entry.initials = entry
+                .initials
+                .chars()
+                .filter(char::is_ascii_alphanumeric)
+                .take(INITIALS_LEN)
+                .collect::<String>()
+                .to_ascii_uppercase();
+        }
+
+        // Stable sort so older scores stay ahead of equal newer ones
+        self.entries.sort_by_key(|entry| Reverse(entry.score));
+        self.entries.truncate(MAX_ENTRIES);
+    }
+}
+
+/// A missing file is an empty table, anything unparsable is an error
+fn read(path: &Path) -> Result<Vec<HighScore>, String> {
+    match fs::read(path) {
+        Ok(bytes) => serde_json::from_slice(&bytes).map_err(|e| e.to_string()),
+        Err(_) if !path.exists() => Ok(Vec::new()),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::test_dir;
+
+    fn entry(initials: &str, score: u32) -> HighScore {
+        HighScore {
+            initials: initials.to_string(),
+            score,
+            wave: 1,
+            date: 0,
+        }
+    }
+
+    fn table(scores: &[u32]) -> HighScores {
+        HighScores {
+            entries: scores.iter().map(|&score| entry("AAA", score)).collect(),
+            path: None,
+        }
+    }
+
+    #[test]
+    fn qualifies_until_the_table_is_full() {
+        let mut scores = table(&[]);
+        assert!(!scores.qualifies(0));
+        assert!(scores.qualifies(1));
+
+        scores = table(&[100; MAX_ENTRIES]);
+        assert!(!scores.qualifies(100));
+        assert!(scores.qualifies(101));
+    }
+
+    #[test]
+    fn table_is_sorted_and_trimmed() {
+        let mut scores = table(&[]);
+        for score in 1..=MAX_ENTRIES as u32 + 3 {
+            scores.insert(entry("ab!c", score * 10));
+        }
+
+        assert_eq!(scores.entries().len(), MAX_ENTRIES);
+        assert_eq!(scores.best(), (MAX_ENTRIES as u32 + 3) * 10);
+        assert!(scores
+            .entries()
+            .windows(2)
+            .all(|w| w[0].score >= w[1].score));
+        // Older scores stay ahead of equal newer ones
+        scores.insert(entry("NEW", scores.best()));
+        assert_eq!(scores.entries()[0].initials, "ABC");
+        assert_eq!(scores.entries()[1].initials, "NEW");
+    }
+
+    #[test]
+    fn corrupt_table_restores_the_backup() {
+        let path = test_dir("highscore-backup").join(FILE_NAME);
+
+        let mut scores = HighScores::load_from(path.clone());
+        scores.insert(entry("ONE", 100));
+        scores.save().unwrap();
+        scores.insert(entry("TWO", 200));
+        // The first save is backed up by the second
+        scores.save().unwrap();
+
+        fs::write(&path, b"{ not json").unwrap();
+        let restored = HighScores::load_from(path.clone());
+
+        assert_eq!(restored.best(), 100);
+        assert_eq!(restored.entries().len(), 1);
+        assert!(path.with_extension("corrupt").exists());
+    }
+}
@@ -0,0 +1,67 @@
+pub mod highscore;
+pub mod replay;
+pub mod save;
+
+use std::{
+    fs::{self, File},
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+const APP_DIR: &str = "asteroids";
+
+/// Directory in the users data directory where the game keeps its files
+pub fn data_dir() -> Result<PathBuf, String> {
+    let dir = dirs::data_dir()
+        .ok_or("Could not find a data directory")?
+        .join(APP_DIR);
+    fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+
+    Ok(dir)
+}
+
+/// Year, month and day of a unix timestamp in UTC
+pub fn civil_date(secs: u64) -> (i64, i64, i64) {
+    // Days to civil date, from Howard Hinnant's date algorithms
+    let z = (secs / 86_400) as i64 + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + (month <= 2) as i64;
+
+    (year, month, day)
+}
+
+/// FNV-1a, a hash that is the same on every machine and build unlike the
+/// standard library's
+pub fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &byte| {
+        (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+/// Write to a temporary file next to `path` and rename it over the top,
+/// so a crash mid write never leaves a half written file behind
+pub fn write_atomic(path: &Path, contents: &[u8]) -> Result<(), String> {
+    let tmp = path.with_extension("tmp");
+
+    let mut file = File::create(&tmp).map_err(|e| e.to_string())?;
+    file.write_all(contents).map_err(|e| e.to_string())?;
+    file.sync_all().map_err(|e| e.to_string())?;
+
+    fs::rename(&tmp, path).map_err(|e| e.to_string())
+}
+
+/// An empty directory of its own for a test to write files in
+#[cfg(test)]
+pub fn test_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("asteroids-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+
+    dir
+}
@@ -0,0 +1,217 @@
+use super::write_atomic;
+use crate::game::{input::Input, rules::Rules};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+const VERSION: u32 = 4;
+
+/// The seed, rules, mods and every tick's input of a game, enough to play
+/// it back exactly
+#[derive(Serialize, Deserialize)]
+pub struct Replay {
+    version: u32,
+    pub seed: u64,
+    pub rules: Rules,
+    /// Name and source hash of each mod that was running, as they change
+    /// how the game plays out
+    mods: Vec<(String, u64)>,
+    /// Each player's run length encoded inputs as (input bits, number of
+    /// ticks)
+    inputs: Vec<Vec<(u64, u32)>>,
+}
+
+impl Replay {
+    pub fn new(seed: u64, rules: Rules, mods: Vec<(String, u64)>) -> Self {
+        Self {
+            version: VERSION,
+            seed,
+            rules,
+            mods,
+            inputs: vec![Vec::new(); rules.players.count()],
+        }
+    }
+
+    /// Add a tick of everyone's input
+    pub fn push(&mut self, inputs: &[Input]) {
+        for (runs, input) in self.inputs.iter_mut().zip(inputs) {
+            let bits = input.to_bits();
+            match runs.last_mut() {
+                Some((last, count)) if *last == bits => *count += 1,
+                _ => runs.push((bits, 1)),
+            }
+        }
+    }
+
+    /// Every tick's inputs in order, one for each player
+    pub fn inputs(&self) -> Vec<Vec<Input>> {
+        let players: Vec<Vec<Input>> = self
+            .inputs
+            .iter()
+            .map(|runs| {
+                runs.iter()
+                    .flat_map(|&(bits, count)| (0..count).map(move |_| Input::from_bits(bits)))
+                    .collect()
+            })
+            .collect();
+
+        let ticks = players.iter().map(Vec::len).max().unwrap_or(0);
+        (0..ticks)
+            .map(|tick| {
+                players
+                    .iter()
+                    .map(|inputs| inputs.get(tick).copied().unwrap_or_default())
+                    .collect()
+            })
+            .collect()
+    }
+
+    pub fn load(path: &Path) -> Result<Replay, String> {
+        let bytes = fs::read(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        let replay: Replay = serde_json::from_slice(&bytes).map_err(|e| e.to_string())?;
+
+        if replay.version != VERSION {
+            return Err(format!(
+                "Replay is version {}, expected {}",
+                replay.version, VERSION
+            ));
+        }
+        replay
+            .rules
+            .check()
+            .map_err(|e| format!("Replay can't be played: {}", e))?;
+
+        Ok(replay)
+    }
+
+    /// Only the mods it was recorded with can play it back the same
+    pub fn check_mods(&self, loaded: &[(String, u64)]) -> Result<(), String> {
+        if self.mods == loaded {
+            return Ok(());
+        }
+
+        let names = |mods: &[(String, u64)]| match mods.len() {
+            0 => "no mods".to_string(),
+            _ => {
+                let names: Vec<_> = mods.iter().map(|(name, _)| name.as_str()).collect();
+                format!("mods {}", names.join(", "))
+            }
+        };
+        let (recorded, loaded) = (names(&self.mods), names(loaded));
+        if recorded == loaded {
+            Err(format!(
+                "Replay was recorded with different versions of {}",
+                recorded
+            ))
+        } else {
+            Err(format!(
+                "Replay was recorded with {} but {} are loaded",
+                recorded, loaded
+            ))
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let json = serde_json::to_vec(self).map_err(|e| e.to_string())?;
+        write_atomic(path, &json)
+    }
+}
+
+/// Where to record the `game`th game of a session, counting from 1. The
+/// first goes to `path` itself and later ones get a number on the end, so
+/// each game keeps its own replay
+pub fn numbered(path: &Path, game: usize) -> PathBuf {
+    if game <= 1 {
+        return path.to_path_buf();
+    }
+
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let name = match path.extension() {
+        Some(ext) => format!("{}-{}.{}", stem, game, ext.to_string_lossy()),
+        None => format!("{}-{}", stem, game),
+    };
+    path.with_file_name(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        game::rules::{Difficulty, GameMode, Players},
+        math::playfield::Playfield,
+        storage::test_dir,
+    };
+
+    fn rules() -> Rules {
+        Rules {
+            difficulty: Difficulty::Normal,
+            mode: GameMode::Classic,
+            players: Players::Single,
+            field: Playfield::new(800., 600.),
+        }
+    }
+
+    fn mods(names: &[(&str, u64)]) -> Vec<(String, u64)> {
+        names
+            .iter()
+            .map(|&(name, hash)| (name.to_string(), hash))
+            .collect()
+    }
+
+    #[test]
+    fn replays_need_the_mods_they_were_recorded_with() {
+        let recorded = mods(&[("gravity", 1), ("ufo", 2)]);
+        let replay = Replay::new(0, rules(), recorded.clone());
+        assert!(replay.check_mods(&recorded).is_ok());
+
+        let e = replay.check_mods(&[]).unwrap_err();
+        assert_eq!(
+            e,
+            "Replay was recorded with mods gravity, ufo but no mods are loaded"
+        );
+        let e = replay.check_mods(&mods(&[("gravity", 1)])).unwrap_err();
+        assert!(e.contains("but mods gravity are loaded"), "{}", e);
+        let e = replay
+            .check_mods(&mods(&[("gravity", 1), ("ufo", 3)]))
+            .unwrap_err();
+        assert!(
+            e.contains("different versions of mods gravity, ufo"),
+            "{}",
+            e
+        );
+
+        let plain = Replay::new(0, rules(), Vec::new());
+        assert!(plain.check_mods(&[]).is_ok());
+        assert!(plain.check_mods(&recorded).is_err());
+    }
+
+    #[test]
+    fn replays_of_impossible_games_are_rejected() {
+        let dir = test_dir("replay");
+        let path = dir.join("fine.replay");
+        Replay::new(0, rules(), Vec::new()).save(&path).unwrap();
+        assert!(Replay::load(&path).is_ok());
+
+        let path = dir.join("flat.replay");
+        let flat = Rules {
+            field: Playfield::new(800., 0.),
+            ..rules()
+        };
+        Replay::new(0, flat, Vec::new()).save(&path).unwrap();
+        let e = Replay::load(&path).err().unwrap();
+        assert_eq!(
+            e,
+            "Replay can't be played: playfield height has to be more than 0, not 0"
+        );
+    }
+
+    #[test]
+    fn later_games_get_numbered() {
+        let path = Path::new("runs/best.replay");
+        assert_eq!(numbered(path, 1), path);
+        assert_eq!(numbered(path, 2), Path::new("runs/best-2.replay"));
+        assert_eq!(numbered(Path::new("demo"), 3), Path::new("demo-3"));
+    }
+}
@@ -0,0 +1,149 @@
+use super::{data_dir, write_atomic};
+use crate::game::Game;
+use serde::{Deserialize, Serialize};
+use std::{fs, path::Path};
+
+const FILE_NAME: &str = "save.json";
+/// Bump whenever the layout of `Game` changes so old saves are rejected
+/// instead of being restored wrongly
+const VERSION: u32 = 7;
+
+#[derive(Deserialize)]
+struct Header {
+    version: u32,
+}
+
+#[derive(Serialize)]
+struct SaveRef<'a> {
+    version: u32,
+    game: &'a Game,
+}
+
+#[derive(Deserialize)]
+struct SaveFile {
+    game: Game,
+}
+
+pub fn exists() -> bool {
+    data_dir().is_ok_and(|dir| dir.join(FILE_NAME).exists())
+}
+
+/// Save a game in progress, replacing any earlier save
+pub fn save(game: &Game) -> Result<(), String> {
+    save_to(&data_dir()?.join(FILE_NAME), game)
+}
+
+/// Load the saved game and remove it, so a game can only be resumed once
+pub fn take() -> Result<Game, String> {
+    take_from(&data_dir()?.join(FILE_NAME))
+}
+
+fn save_to(path: &Path, game: &Game) -> Result<(), String> {
+    let json = serde_json::to_vec(&SaveRef {
+        version: VERSION,
+        game,
+    })
+    .map_err(|e| e.to_string())?;
+
+    write_atomic(path, &json)
+}
+
+/// A save that can't be read is left where it is
+fn take_from(path: &Path) -> Result<Game, String> {
+    let bytes = fs::read(path).map_err(|e| e.to_string())?;
+
+    let header: Header = serde_json::from_slice(&bytes).map_err(|e| e.to_string())?;
+    if header.version != VERSION {
+        return Err(format!(
+            "Save is version {}, expected {}",
+            header.version, VERSION
+        ));
+    }
+
+    let save: SaveFile = serde_json::from_slice(&bytes).map_err(|e| e.to_string())?;
+    fs::remove_file(path).map_err(|e| e.to_string())?;
+
+    Ok(save.game)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        game::{
+            input::Input,
+            rules::{Difficulty, GameMode, Players, Rules},
+        },
+        math::playfield::Playfield,
+        storage::test_dir,
+    };
+
+    fn game() -> Game {
+        let rules = Rules {
+            difficulty: Difficulty::Normal,
+            mode: GameMode::Classic,
+            players: Players::Coop,
+            field: Playfield::new(800., 600.),
+        };
+        let mut game = Game::new(7, rules);
+        let firing = Input {
+            right: true,
+            thrust: true,
+            fire: true,
+            ..Input::default()
+        };
+        for tick in 0..300 {
+            let input = if tick % 3 == 0 {
+                firing
+            } else {
+                Input::default()
+            };
+            game.tick(&[input, Input::default()]);
+        }
+        game
+    }
+
+    #[test]
+    fn save_and_take_restore_the_game_exactly() {
+        let path = test_dir("save-round-trip").join(FILE_NAME);
+        let mut game = game();
+        save_to(&path, &game).unwrap();
+
+        let mut restored = take_from(&path).unwrap();
+        assert!(!path.exists());
+        assert_eq!(restored.score(), game.score());
+        assert_eq!(restored.wave, game.wave);
+        let lives = |game: &Game| game.players.iter().map(|p| p.lives).collect::<Vec<_>>();
+        assert_eq!(lives(&restored), lives(&game));
+        assert_eq!(restored.world.len(), game.world.len());
+        assert_eq!(
+            serde_json::to_value(&restored).unwrap(),
+            serde_json::to_value(&game).unwrap()
+        );
+
+        // And it carries on the same, rng and all
+        for _ in 0..300 {
+            game.tick(&[]);
+            restored.tick(&[]);
+        }
+        assert_eq!(
+            serde_json::to_value(&restored).unwrap(),
+            serde_json::to_value(&game).unwrap()
+        );
+    }
+
+    #[test]
+    fn bad_saves_are_kept() {
+        let dir = test_dir("save-bad");
+
+        let corrupt = dir.join("corrupt.json");
+        fs::write(&corrupt, b"{ \"version\": ").unwrap();
+        assert!(take_from(&corrupt).is_err());
+        assert!(corrupt.exists());
+
+        let old = dir.join("old.json");
+        fs::write(&old, br#"{ "version": 1, "game": {} }"#).unwrap();
+        assert!(matches!(take_from(&old), Err(e) if e.contains("version 1")));
+        assert!(old.exists());
+    }
+}
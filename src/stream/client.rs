@@ -0,0 +1,54 @@
+use super::Snapshot;
+use std::{
+    io::{ErrorKind, Read},
+    net::TcpStream,
+};
+
+/// Watches a game streamed by a `Broadcaster`
+pub struct Spectator {
+    stream: TcpStream,
+    /// Bytes read that don't make a whole line yet
+    buffer: Vec<u8>,
+}
+
+impl Spectator {
+    /// Connect to a game streaming at `addr`, like `127.0.0.1:7780`
+    pub fn connect(addr: &str) -> Result<Spectator, String> {
+        let stream = TcpStream::connect(addr).map_err(|e| format!("{}: {}", addr, e))?;
+        stream.set_nonblocking(true).map_err(|e| e.to_string())?;
+
+        Ok(Spectator {
+            stream,
+            buffer: Vec::new(),
+        })
+    }
+
+    /// The newest snapshot to arrive since last time, skipping any older
+    /// ones and any that can't be drawn. An error once the game stops
+    /// streaming
+    pub fn latest(&mut self) -> Result<Option<Snapshot>, String> {
+        let mut chunk = [0; 16384];
+        loop {
+            match self.stream.read(&mut chunk) {
+                Ok(0) => return Err("The game stopped streaming".to_string()),
+                Ok(n) => self.buffer.extend_from_slice(&chunk[..n]),
+                Err(e) if e.kind() == ErrorKind::WouldBlock => break,
+                Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e.to_string()),
+            }
+        }
+
+        let end = match self.buffer.iter().rposition(|&byte| byte == b'\n') {
+            Some(end) => end,
+            None => return Ok(None),
+        };
+        let lines: Vec<u8> = self.buffer.drain(..=end).collect();
+        let latest = lines
+            .split(|&byte| byte == b'\n')
+            .rev()
+            .filter_map(|line| serde_json::from_slice::<Snapshot>(line).ok())
+            .find(|snapshot| snapshot.rules.check().is_ok());
+
+        Ok(latest)
+    }
+}
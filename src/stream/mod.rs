@@ -0,0 +1,298 @@
+pub mod client;
+pub mod server;
+
+use crate::{
+    entity::{
+        asteroid,
+        ship::{self, laser},
+        system,
+        world::World,
+    },
+    game::{rules::Rules, Game},
+    math::vec2::Vec2,
+};
+use serde::{Deserialize, Serialize};
+
+/// Everything needed to show a game as it is on one tick. Sent as a line
+/// of JSON, with positions rounded to a tenth of a unit to keep it small
+#[derive(Serialize, Deserialize)]
+pub struct Snapshot {
+    pub tick: u64,
+    pub rules: Rules,
+    pub wave: u32,
+    pub players: Vec<PlayerState>,
+    pub asteroids: Vec<AsteroidState>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct PlayerState {
+    /// Centre of the ship
+    pub x: f32,
+    pub y: f32,
+    /// Which way the nose points, in radians clockwise from the right
+    pub angle: f32,
+    /// Velocity in units a second
+    pub vx: f32,
+    pub vy: f32,
+    pub thrusting: bool,
+    pub score: u32,
+    pub lives: u32,
+    pub lasers: Vec<[f32; 2]>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct AsteroidState {
+    /// Centre of the asteroid
+    pub x: f32,
+    pub y: f32,
+    /// Corners around the centre, to the nearest unit
+    pub shape: Vec<[i8; 2]>,
+}
+
+fn round(value: f32) -> f32 {
+    (value * 10.).round() / 10.
+}
+
+impl Snapshot {
+    pub fn new(game: &Game, tick: u64) -> Snapshot {
+        let players = game
+            .players
+            .iter()
+            .enumerate()
+            .map(|(i, player)| {
+                // Once out there is no ship, it reads as still in the middle
+                let ship = game.ship(i);
+                let (center, velocity, facing) = match ship {
+                    Some(ship) => (ship.center(), ship.velocity(), ship::facing(ship)),
+                    None => (game.rules.field.center(), Vec2::new(0., 0.), 0.),
+                };
+                PlayerState {
+                    x: round(center.x),
+                    y: round(center.y),
+                    angle: (facing * 1000.).round() / 1000.,
+                    vx: round(velocity.x),
+                    vy: round(velocity.y),
+                    thrusting: ship.is_some_and(ship::is_thrusting),
+                    score: player.score,
+                    lives: player.lives,
+                    lasers: game
+                        .lasers(i)
+                        .map(|laser| {
+                            let pos = laser.center();
+                            [round(pos.x), round(pos.y)]
+                        })
+                        .collect(),
+                }
+            })
+            .collect();
+
+        let asteroids = game
+            .asteroids()
+            .map(|asteroid| {
+                let center = asteroid.center();
+                let shape = asteroid
+                    .verts()
+                    .iter()
+                    .map(|vert| {
+                        let x = (vert.x - center.x).round().clamp(-128., 127.) as i8;
+                        let y = (vert.y - center.y).round().clamp(-128., 127.) as i8;
+                        [x, y]
+                    })
+                    .collect();
+                AsteroidState {
+                    x: round(center.x),
+                    y: round(center.y),
+                    shape,
+                }
+            })
+            .collect();
+
+        Snapshot {
+            tick,
+            rules: game.rules,
+            wave: game.wave,
+            players,
+            asteroids,
+        }
+    }
+
+    /// A game that looks like this one for drawing. Nothing in it moves,
+    /// it is only good for showing this tick
+    pub fn to_game(&self) -> Game {
+        let mut game = Game::with_players(0, self.rules, self.players.len());
+        let field = &self.rules.field;
+
+        game.wave = self.wave;
+        game.world = World::default();
+        for (i, (player, state)) in game.players.iter_mut().zip(&self.players).enumerate() {
+            player.score = state.score;
+            player.lives = state.lives;
+            if player.is_out() {
+                continue;
+            }
+
+            let center = Vec2::new(state.x, state.y);
+            game.world.spawn(ship::posed(&center, state.angle, i));
+            for &[x, y] in state.lasers.iter() {
+                game.world.spawn(laser::new(Vec2::new(x, y), 0., i));
+            }
+        }
+
+        for state in self.asteroids.iter() {
+            let verts = state
+                .shape
+                .iter()
+                .map(|&[x, y]| Vec2::new(state.x + x as f32, state.y + y as f32))
+                .collect();
+            game.world.spawn(asteroid::outline(verts));
+        }
+        system::wrap(&mut game.world, field);
+
+        game
+    }
+
+    /// Average velocity of the ships still playing, for the starfield
+    pub fn velocity(&self) -> Vec2 {
+        let playing: Vec<_> = self.players.iter().filter(|p| p.lives > 0).collect();
+        if playing.is_empty() {
+            return Vec2::new(0., 0.);
+        }
+
+        let n = playing.len() as f32;
+        Vec2::new(
+            playing.iter().map(|p| p.vx).sum::<f32>() / n,
+            playing.iter().map(|p| p.vy).sum::<f32>() / n,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{client::Spectator, server::Broadcaster, *};
+    use crate::{
+        game::{
+            input::Input,
+            rules::{Difficulty, GameMode, Players},
+        },
+        math::playfield::Playfield,
+    };
+    use std::{
+        io::{BufRead, BufReader, Write},
+        net::{TcpListener, TcpStream},
+        thread,
+        time::{Duration, Instant},
+    };
+
+    fn game() -> Game {
+        let rules = Rules {
+            difficulty: Difficulty::Normal,
+            mode: GameMode::Classic,
+            players: Players::Coop,
+            field: Playfield::new(800., 600.),
+        };
+        let mut game = Game::new(9, rules);
+        let firing = Input {
+            left: true,
+            thrust: true,
+            fire: true,
+            ..Input::default()
+        };
+        for tick in 0..40 {
+            let input = if tick % 2 == 0 {
+                firing
+            } else {
+                Input::default()
+            };
+            game.tick(&[input, Input::default()]);
+        }
+        game
+    }
+
+    #[test]
+    fn snapshot_rebuilds_the_game() {
+        let game = game();
+        let snapshot = Snapshot::new(&game, 40);
+        let again = Snapshot::new(&snapshot.to_game(), 40);
+
+        assert_eq!(again.wave, snapshot.wave);
+        assert_eq!(again.asteroids.len(), game.asteroids().count());
+        for (a, b) in again.players.iter().zip(&snapshot.players) {
+            assert!((a.x - b.x).abs() < 0.2 && (a.y - b.y).abs() < 0.2);
+            assert!((a.angle - b.angle).abs() < 0.01);
+            assert_eq!(a.lasers, b.lasers);
+            assert_eq!((a.score, a.lives), (b.score, b.lives));
+        }
+        for (a, b) in again.asteroids.iter().zip(&snapshot.asteroids) {
+            assert!((a.x - b.x).abs() < 1. && (a.y - b.y).abs() < 1.);
+        }
+    }
+
+    #[test]
+    fn clients_get_every_tick() {
+        let mut server = Broadcaster::bind(0).unwrap();
+        let addr = server.local_addr().unwrap();
+        let mut raw = BufReader::new(TcpStream::connect(addr).unwrap());
+        let mut spectator = Spectator::connect(&addr.to_string()).unwrap();
+
+        let mut game = game();
+        for tick in 0..30 {
+            game.tick(&[]);
+            server.send(&game, tick);
+            thread::sleep(Duration::from_millis(1));
+        }
+
+        // A plain socket sees one line of JSON for every tick from when it
+        // was let in
+        let mut ticks = Vec::new();
+        let mut line = String::new();
+        while ticks.last() != Some(&29) {
+            line.clear();
+            raw.read_line(&mut line).unwrap();
+            let snapshot: Snapshot = serde_json::from_str(&line).unwrap();
+            assert_eq!(snapshot.players.len(), 2);
+            ticks.push(snapshot.tick);
+        }
+        assert!(ticks.windows(2).all(|pair| pair[1] == pair[0] + 1));
+
+        // The spectator only cares about the latest
+        let deadline = Instant::now() + Duration::from_secs(5);
+        let latest = loop {
+            assert!(Instant::now() < deadline, "the spectator got nothing");
+            if let Some(snapshot) = spectator.latest().unwrap() {
+                if snapshot.tick == 29 {
+                    break snapshot;
+                }
+            }
+            thread::sleep(Duration::from_millis(1));
+        };
+        assert_eq!(latest.wave, game.wave);
+        assert_eq!(latest.asteroids.len(), game.asteroids().count());
+    }
+
+    #[test]
+    fn snapshots_that_cant_be_drawn_are_dropped() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let mut spectator = Spectator::connect(&addr.to_string()).unwrap();
+        let (mut stream, _) = listener.accept().unwrap();
+
+        let game = game();
+        let mut flat = Snapshot::new(&game, 2);
+        flat.rules.field = Playfield::new(0., 600.);
+        for snapshot in [Snapshot::new(&game, 1), flat] {
+            let mut line = serde_json::to_vec(&snapshot).unwrap();
+            line.push(b'\n');
+            stream.write_all(&line).unwrap();
+        }
+
+        let deadline = Instant::now() + Duration::from_secs(5);
+        let latest = loop {
+            assert!(Instant::now() < deadline, "the spectator got nothing");
+            if let Some(snapshot) = spectator.latest().unwrap() {
+                break snapshot;
+            }
+            thread::sleep(Duration::from_millis(1));
+        };
+        assert_eq!(latest.tick, 1);
+    }
+}
@@ -0,0 +1,91 @@
+use super::Snapshot;
+use crate::game::Game;
+use std::{
+    io::{ErrorKind, Write},
+    net::{SocketAddr, TcpListener, TcpStream},
+};
+
+/// Most bytes waiting to go to one client before it is given up on as too
+/// slow, a few seconds of snapshots
+const MAX_BACKLOG: usize = 1 << 20;
+
+/// Streams snapshots to anyone connected to a local TCP port, one line of
+/// JSON per tick
+pub struct Broadcaster {
+    listener: TcpListener,
+    clients: Vec<Client>,
+}
+
+struct Client {
+    stream: TcpStream,
+    /// What hasn't been written yet
+    pending: Vec<u8>,
+}
+
+impl Broadcaster {
+    /// Listen on `port` of this machine only, 0 picks any free one
+    pub fn bind(port: u16) -> Result<Broadcaster, String> {
+        let listener = TcpListener::bind(("127.0.0.1", port)).map_err(|e| e.to_string())?;
+        listener.set_nonblocking(true).map_err(|e| e.to_string())?;
+
+        Ok(Broadcaster {
+            listener,
+            clients: Vec::new(),
+        })
+    }
+
+    pub fn local_addr(&self) -> Result<SocketAddr, String> {
+        self.listener.local_addr().map_err(|e| e.to_string())
+    }
+
+    /// Let in anyone new and send everyone a snapshot of the game, without
+    /// ever waiting on them
+    pub fn send(&mut self, game: &Game, tick: u64) {
+        while let Ok((stream, _)) = self.listener.accept() {
+            if stream.set_nonblocking(true).is_ok() {
+                stream.set_nodelay(true).ok();
+                self.clients.push(Client {
+                    stream,
+                    pending: Vec::new(),
+                });
+            }
+        }
+        if self.clients.is_empty() {
+            return;
+        }
+
+        let mut line = match serde_json::to_vec(&Snapshot::new(game, tick)) {
+            Ok(line) => line,
+            Err(e) => return eprintln!("Could not stream the game: {}", e),
+        };
+        line.push(b'\n');
+
+        self.clients.retain_mut(|client| {
+            if client.pending.len() > MAX_BACKLOG {
+                eprintln!("Dropped a spectator that fell behind");
+                return false;
+            }
+            client.pending.extend_from_slice(&line);
+            client.flush()
+        });
+    }
+}
+
+impl Client {
+    /// Write as much as will go now, returns false if the client has gone
+    fn flush(&mut self) -> bool {
+        while !self.pending.is_empty() {
+            match self.stream.write(&self.pending) {
+                Ok(0) => return false,
+                Ok(n) => {
+                    self.pending.drain(..n);
+                }
+                Err(e) if e.kind() == ErrorKind::WouldBlock => break,
+                Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+                Err(_) => return false,
+            }
+        }
+
+        true
+    }
+}